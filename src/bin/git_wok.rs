@@ -1,7 +1,11 @@
 use anyhow::{Context, Result, anyhow, bail};
 use clap::{ArgAction, Parser};
 use git_wok as wok;
-use std::{env, io::stdout, path};
+use std::{
+    env,
+    io::{self, Write, stdout},
+    path, process,
+};
 
 fn resolve_path(base: &path::Path, value: &path::Path) -> path::PathBuf {
     if value.is_absolute() {
@@ -33,6 +37,25 @@ struct Args {
     #[clap(global = true, short('v'), long)]
     verbose: bool,
 
+    /// Number of repos to process concurrently (overrides `jobs` in the wok
+    /// file)
+    #[clap(global = true, short('j'), long, value_parser = clap::value_parser!(u16).range(1..))]
+    jobs: Option<u16>,
+
+    /// Act only on repos in the group (may be repeated)
+    #[clap(global = true, short('g'), long = "group", value_name = "GROUP")]
+    groups: Vec<String>,
+
+    /// Output format; structured formats are supported by status, update,
+    /// push, switch, branch, tag, lock, test-auth and doctor
+    #[clap(global = true, long, value_enum, default_value_t)]
+    format: wok::output::Format,
+
+    /// Only print what would be done, without changing any repo; supported by
+    /// switch, lock, update, push, tag and assemble
+    #[clap(global = true, long)]
+    dry_run: bool,
+
     #[clap(subcommand)]
     cmd: Command,
 }
@@ -51,6 +74,16 @@ enum Command {
         directory: path::PathBuf,
     },
 
+    /// Clone an umbrella repo and check out all configured subrepos on their
+    /// heads.
+    Clone {
+        /// URL of the umbrella repo to clone.
+        url: String,
+
+        /// Directory to clone into (derived from the URL if not provided).
+        directory: Option<path::PathBuf>,
+    },
+
     /// Generate shell completion script.
     Completion {
         /// Shell to generate completion script for (bash, fish, zsh).
@@ -58,16 +91,51 @@ enum Command {
         shell: wok::cmd::CompletionShell,
     },
 
+    /// Report drift between the wok file, `.gitmodules` and the umbrella repo
+    Doctor {
+        /// Repair the problems that can be repaired without losing work
+        #[clap(long)]
+        fix: bool,
+    },
+
     #[clap(flatten)]
     App(App),
 }
 
+/// Options of `update` that only apply when starting an update.
+const UPDATE_OPTIONS: [&str; 9] = [
+    "no_commit",
+    "ff_only",
+    "rebase",
+    "merge",
+    "autostash",
+    "umbrella",
+    "no_umbrella",
+    "to",
+    "repos",
+];
+
+/// Options of `tag` that create, delete or push tags rather than list them.
+const TAG_CHANGE_OPTIONS: [&str; 6] =
+    ["create", "bump", "delete", "sign", "message", "push"];
+
 #[derive(Debug, Parser)]
 enum App {
-    /// Add an existing submodule to the wok workspace
+    /// Add an existing submodule to the wok workspace, or clone a repo as a new
+    /// submodule when given its URL and a path
     Add {
-        /// Path of the submodule relative to the umbrella repo
-        submodule_path: path::PathBuf,
+        /// Path of an existing submodule relative to the umbrella repo, or URL
+        /// of a repo to clone as a new submodule
+        #[clap(value_name = "SUBMODULE_PATH|URL")]
+        source: String,
+
+        /// Path of the new submodule relative to the umbrella repo
+        submodule_path: Option<path::PathBuf>,
+
+        /// Branch to check out and track in a new submodule (defaults to the
+        /// remote's default branch)
+        #[clap(short('b'), long, requires = "submodule_path")]
+        branch: Option<String>,
     },
 
     /// Remove a submodule from the wok workspace
@@ -75,6 +143,19 @@ enum App {
     Remove {
         /// Path of the submodule relative to the umbrella repo
         submodule_path: path::PathBuf,
+
+        /// Also deinitialize the submodule, remove it from the umbrella and
+        /// commit the result
+        #[clap(long)]
+        purge: bool,
+
+        /// Delete the submodule's git dir from `.git/modules` when purging
+        #[clap(long, requires = "purge")]
+        delete_git_dir: bool,
+
+        /// Purge even if the subrepo has uncommitted changes or unpushed commits
+        #[clap(long, requires = "purge")]
+        force: bool,
     },
 
     /// Switch repos to current main repo branch with options
@@ -83,6 +164,17 @@ enum App {
         #[clap(short('c'), long)]
         create: bool,
 
+        /// Create the branch from this base instead of the current commit of
+        /// each repo: a branch, a remote branch such as `origin/main` (fetched
+        /// first and tracked), a tag or a commit
+        #[clap(long, requires = "create", value_name = "BASE")]
+        from: Option<String>,
+
+        /// Fetch the remotes of each repo first, to find branches that were
+        /// pushed by others
+        #[clap(long)]
+        fetch: bool,
+
         /// Act on all configured repos
         #[clap(long)]
         all: bool,
@@ -91,12 +183,55 @@ enum App {
         #[clap(long)]
         branch: Option<String>,
 
+        /// Stash uncommitted changes in each repo before switching and re-apply
+        /// them afterwards
+        #[clap(long)]
+        autostash: bool,
+
+        /// Roll every repo back to its original branch if any of them fails to
+        /// switch (enabled by `atomic_switch` in the wok file)
+        #[clap(long, conflicts_with = "autostash")]
+        atomic: bool,
+
         /// Specific repos to switch (if not provided, acts on all matching repos)
         repos: Vec<path::PathBuf>,
     },
 
+    /// List the branches of all repos, or delete or rename a branch in every
+    /// repo that has it
+    Branch {
+        /// Delete the branch, if it is merged, in every repo that has it
+        #[clap(short('d'), long, value_name = "BRANCH", conflicts_with = "rename")]
+        delete: Option<String>,
+
+        /// Also delete the branch on the remote it would be pushed to
+        #[clap(long, requires = "delete")]
+        remote: bool,
+
+        /// Rename a branch in every repo that has it, updating the heads in the
+        /// wok file
+        #[clap(short('m'), long, num_args = 2, value_names = ["OLD", "NEW"])]
+        rename: Option<Vec<String>>,
+
+        /// Specific repos to act on (if not provided, acts on all configured
+        /// repos)
+        repos: Vec<path::PathBuf>,
+    },
+
     /// Lock submodule state by committing current submodule commits
-    Lock,
+    Lock {
+        /// Also record each repo's branch, commit and remote in a lock file
+        /// next to the wok file
+        #[clap(long)]
+        file: bool,
+    },
+
+    /// Check out subrepos at the commits locked in the umbrella repo
+    Sync {
+        /// Check out the branches and commits recorded in the lock file instead
+        #[clap(long)]
+        locked: bool,
+    },
 
     /// Update submodules to latest changes from remotes
     Update {
@@ -104,6 +239,24 @@ enum App {
         #[clap(long = "no-commit")]
         no_commit: bool,
 
+        /// Only fast-forward branches, leaving diverged branches as they are
+        /// (overrides `pull` in the wok file and the git config)
+        #[clap(long, conflicts_with_all = ["rebase", "merge"])]
+        ff_only: bool,
+
+        /// Rebase diverged branches onto their remote branches
+        #[clap(long, conflicts_with = "merge")]
+        rebase: bool,
+
+        /// Merge remote branches into diverged branches
+        #[clap(long)]
+        merge: bool,
+
+        /// Stash uncommitted changes in each subrepo before updating and
+        /// re-apply them afterwards
+        #[clap(long)]
+        autostash: bool,
+
         /// Include the umbrella repository (enabled by default)
         #[clap(long, action = ArgAction::SetTrue, conflicts_with = "no_umbrella")]
         umbrella: bool,
@@ -115,6 +268,37 @@ enum App {
               conflicts_with = "umbrella"
           )]
         no_umbrella: bool,
+
+        /// Check the given repos out at this tag or revision instead of their
+        /// branch tips, on a detached HEAD (skips the umbrella repository)
+        #[clap(
+            long,
+            value_name = "REV",
+            requires = "repos",
+            conflicts_with_all = ["ff_only", "rebase", "merge", "umbrella"],
+        )]
+        to: Option<String>,
+
+        /// Specific repos to pin with --to
+        #[clap(requires = "to")]
+        repos: Vec<path::PathBuf>,
+
+        /// Conclude the merges and rebases of an update that stopped on
+        /// conflicts, once they are resolved and staged, and commit the result
+        #[clap(
+            long = "continue",
+            conflicts_with_all = UPDATE_OPTIONS,
+        )]
+        continue_update: bool,
+
+        /// Restore the repos of an update that stopped on conflicts to where
+        /// they were before it
+        #[clap(
+            long = "abort",
+            conflicts_with = "continue_update",
+            conflicts_with_all = UPDATE_OPTIONS,
+        )]
+        abort_update: bool,
     },
 
     /// Show subprojects status (clean/dirty, branch info)
@@ -122,6 +306,11 @@ enum App {
         /// Fetch from remotes before comparing
         #[clap(long)]
         fetch: bool,
+
+        /// Exit with a non-zero code when repos are dirty, on the wrong branch,
+        /// behind their remote or out of sync with the umbrella
+        #[clap(long)]
+        check: bool,
     },
 
     /// Push changes from configured repos to remotes
@@ -150,16 +339,85 @@ enum App {
         )]
         no_umbrella: bool,
 
+        /// Force-push, but only where the remote branch still matches the
+        /// local remote-tracking branch
+        #[clap(long)]
+        force_with_lease: bool,
+
+        /// Check that every repo can be pushed before pushing any of them
+        #[clap(long)]
+        atomic: bool,
+
         /// Specific repos to push (if not provided, acts on all matching repos)
         repos: Vec<path::PathBuf>,
     },
 
+    /// Run a command in each selected repo
+    #[clap(alias = "foreach")]
+    Exec {
+        /// Act on all configured repos
+        #[clap(long)]
+        all: bool,
+
+        /// Run the command in several repos at once
+        #[clap(short('p'), long)]
+        parallel: bool,
+
+        /// Keep running in the remaining repos after a command fails
+        #[clap(short('k'), long)]
+        keep_going: bool,
+
+        /// Specific repos to run in (if not provided, acts on all matching repos)
+        repos: Vec<path::PathBuf>,
+
+        /// Command to run, given after `--`
+        #[clap(last = true, required = true)]
+        command: Vec<String>,
+    },
+
     /// Add tags to repos, show existing tags, sign and push
     Tag {
         /// Create a new tag
         #[clap(long)]
         create: Option<String>,
 
+        /// Tag each repo with the next semantic version after its highest
+        /// version tag
+        #[clap(long, value_name = "LEVEL", conflicts_with = "create")]
+        bump: Option<wok::cmd::BumpLevel>,
+
+        /// Make the bumped version a prerelease with this identifier, e.g. `rc`
+        #[clap(long, value_name = "ID", requires = "bump")]
+        pre: Option<String>,
+
+        /// Delete a tag, and with --push on the remotes too
+        #[clap(
+            long,
+            value_name = "TAG",
+            conflicts_with_all = ["create", "bump", "sign", "message"],
+        )]
+        delete: Option<String>,
+
+        /// List only the tags matching a glob
+        #[clap(
+            long,
+            value_name = "GLOB",
+            conflicts_with_all = TAG_CHANGE_OPTIONS,
+        )]
+        list: Option<String>,
+
+        /// Order of the listed tags [default: name]
+        #[clap(long, value_enum, conflicts_with_all = TAG_CHANGE_OPTIONS)]
+        sort: Option<wok::cmd::TagSort>,
+
+        /// List only the tags pointing at this revision of each repo, e.g. HEAD
+        #[clap(
+            long,
+            value_name = "REV",
+            conflicts_with_all = TAG_CHANGE_OPTIONS,
+        )]
+        points_at: Option<String>,
+
         /// Sign the tag with GPG
         #[clap(short('s'), long)]
         sign: bool,
@@ -194,15 +452,30 @@ enum App {
 
     /// Test git authentication for the current repository
     TestAuth,
+
+    /// Restore the repos and the wok file to their state before the last
+    /// add, remove, switch, lock, sync or update
+    Undo,
+
+    /// List the operations that can be undone, newest first
+    History,
+
+    /// List, apply or drop the changes stashed by `--autostash`
+    Stash {
+        /// What to do with the newest wok stash of each subrepo
+        #[clap(default_value = "list")]
+        action: wok::cmd::StashAction,
+    },
 }
 
 fn resolve_tag_arguments<'a>(
     create: &'a Option<String>,
+    named: bool,
     all: bool,
     repos: &'a [path::PathBuf],
     config: &wok::config::Config,
 ) -> Result<(Option<String>, &'a [path::PathBuf])> {
-    if create.is_some() {
+    if create.is_some() || named {
         if all && !repos.is_empty() {
             bail!("Cannot specify repositories when using --all");
         }
@@ -245,6 +518,17 @@ fn resolve_tag_arguments<'a>(
     }
 }
 
+fn clone_dir_from_url(url: &str) -> Result<path::PathBuf> {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .map(|name| name.trim_end_matches(".git"))
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| anyhow!("Cannot derive a directory name from '{}'", url))?;
+    Ok(path::PathBuf::from(name))
+}
+
 fn resolve_include_umbrella(umbrella_flag: bool, no_umbrella_flag: bool) -> bool {
     if umbrella_flag {
         true
@@ -253,14 +537,132 @@ fn resolve_include_umbrella(umbrella_flag: bool, no_umbrella_flag: bool) -> bool
     }
 }
 
+fn resolve_pull_strategy(
+    ff_only: bool,
+    rebase: bool,
+    merge: bool,
+) -> Option<wok::config::PullStrategy> {
+    if ff_only {
+        Some(wok::config::PullStrategy::FastForwardOnly)
+    } else if rebase {
+        Some(wok::config::PullStrategy::Rebase)
+    } else if merge {
+        Some(wok::config::PullStrategy::Merge)
+    } else {
+        None
+    }
+}
+
+/// Load the wok file, warning about locked repos it no longer has.
+fn load_config(config_path: &path::Path) -> Result<wok::config::Config> {
+    let config = wok::config::Config::load(config_path)?;
+    for stale_path in &config.stale_locked {
+        eprintln!(
+            "Warning: ignoring locked repo `{}` which is not in the wok file; \
+             run `wok lock --file` to update `{}`",
+            stale_path.display(),
+            wok::config::Lock::path_for(config_path).display()
+        );
+    }
+    Ok(config)
+}
+
+fn resolve_jobs(jobs_flag: Option<u16>, config: &wok::config::Config) -> usize {
+    jobs_flag
+        .map(usize::from)
+        .or(config.jobs)
+        .unwrap_or(1)
+        .max(1)
+}
+
+fn records_journal(cmd: &App) -> bool {
+    matches!(
+        cmd,
+        App::Add { .. }
+            | App::Remove { .. }
+            | App::Switch { .. }
+            | App::Branch {
+                delete: Some(_),
+                ..
+            }
+            | App::Branch {
+                rename: Some(_),
+                ..
+            }
+            | App::Stash {
+                action: wok::cmd::StashAction::Pop | wok::cmd::StashAction::Drop
+            }
+            | App::Lock { .. }
+            | App::Sync { .. }
+            | App::Update { .. }
+    )
+}
+
+fn supports_dry_run(cmd: &Command) -> bool {
+    matches!(
+        cmd,
+        Command::Assemble { .. }
+            | Command::App(
+                App::Switch { .. }
+                    | App::Lock { .. }
+                    | App::Update { .. }
+                    | App::Push { .. }
+                    | App::Tag { .. }
+            )
+    )
+}
+
+fn supports_structured_output(cmd: &Command) -> bool {
+    matches!(
+        cmd,
+        Command::Doctor { .. }
+            | Command::App(
+                App::Status { .. }
+                    | App::Update { .. }
+                    | App::Push { .. }
+                    | App::Switch { .. }
+                    | App::Branch { .. }
+                    | App::Tag { .. }
+                    | App::Lock { .. }
+                    | App::TestAuth
+            )
+    )
+}
+
 fn main() -> Result<()> {
     let Args {
         wokfile_path,
         verbose: _verbose,
+        jobs,
+        format,
+        groups,
+        dry_run,
         cmd,
     } = Args::parse();
     let cwd = env::current_dir().context("Cannot access the current directory")?;
-    let mut output = stdout();
+
+    if format != wok::output::Format::Text && !supports_structured_output(&cmd) {
+        bail!(
+            "Structured output is only supported by status, update, push, switch, \
+             branch, tag, lock, test-auth and doctor"
+        );
+    }
+
+    if dry_run && !supports_dry_run(&cmd) {
+        bail!(
+            "Dry run is only supported by switch, lock, update, push, tag and assemble"
+        );
+    }
+
+    // Structured formats replace the text output of the command
+    let mut output: Box<dyn Write> = match format {
+        wok::output::Format::Text => Box::new(stdout()),
+        _ => Box::new(io::sink()),
+    };
+
+    if dry_run {
+        writeln!(output, "Dry run: no changes will be made")?;
+    }
 
     match cmd {
         Command::Init {} => {
@@ -287,9 +689,42 @@ fn main() -> Result<()> {
                 workspace_dir.join(&wokfile_path)
             };
 
-            wok::cmd::assemble(&workspace_dir, &config_path, &mut output)?
+            wok::cmd::assemble(&workspace_dir, &config_path, &mut output, dry_run)?
+        },
+        Command::Clone { url, directory } => {
+            let target_dir = match directory {
+                Some(directory) => resolve_path(&cwd, &directory),
+                None => cwd.join(clone_dir_from_url(&url)?),
+            };
+
+            wok::cmd::clone(&url, &target_dir, &wokfile_path, &mut output)?
         },
         Command::Completion { shell } => wok::cmd::completion::<Args>(shell)?,
+        Command::Doctor { fix } => {
+            let config_path = resolve_path(&cwd, &wokfile_path);
+
+            if !config_path.exists() {
+                bail!("Git Wok file not found at `{}`", config_path.display());
+            };
+
+            let repo_dir = config_path.parent().with_context(|| {
+                format!("Cannot open work dir for `{}`", config_path.display())
+            })?;
+
+            // Doctor opens the umbrella itself, since subrepos it reports on may
+            // not load as `Repo`
+            let mut wok_config = load_config(&config_path)?;
+            let report = wok::cmd::doctor(&mut wok_config, repo_dir, &mut output, fix)?;
+            wok::output::write_report(&mut stdout(), format, &report)?;
+
+            if report.config_updated {
+                wok_config.save(&config_path)?;
+            }
+            if report.exit_code != 0 {
+                output.flush()?;
+                process::exit(report.exit_code);
+            }
+        },
         Command::App(app_cmd) => {
             let config_path = resolve_path(&cwd, &wokfile_path);
 
@@ -303,113 +738,410 @@ fn main() -> Result<()> {
 
             let umbrella = wok::repo::Repo::new(repo_dir, None)?;
 
-            let mut wok_config = wok::config::Config::load(&config_path)?;
-
-            if match app_cmd {
-                App::Add { submodule_path } => {
-                    wok::cmd::repo::add(&mut wok_config, &umbrella, &submodule_path)?
-                },
-                App::Remove { submodule_path } => {
-                    wok::cmd::repo::rm(&mut wok_config, &submodule_path)?
-                },
-                App::Switch {
-                    create,
-                    all,
-                    branch,
-                    repos,
-                } => wok::cmd::switch(
-                    &mut wok_config,
-                    &umbrella,
-                    &mut output,
-                    create,
-                    all,
-                    branch.as_deref(),
-                    &repos,
-                )?,
-                App::Lock => {
-                    wok::cmd::lock(&mut wok_config, &umbrella, &mut output)?;
-                    false // Don't save config for lock command
-                },
-                App::Update {
-                    no_commit,
-                    umbrella: umbrella_flag,
-                    no_umbrella: no_umbrella_flag,
-                } => {
-                    let include_umbrella =
-                        resolve_include_umbrella(umbrella_flag, no_umbrella_flag);
-                    wok::cmd::update(
+            let mut wok_config = load_config(&config_path)?;
+            // exec --parallel defaults to the number of cores rather than to 1
+            let jobs_flag = jobs.map(usize::from);
+            let jobs = resolve_jobs(jobs, &wok_config);
+
+            if let Some(group) =
+                groups.iter().find(|group| !wok_config.has_group(group))
+            {
+                bail!("No repos in group `{}`", group);
+            }
+
+            // Record the workspace state so that the command can be undone
+            let journal = wok::journal::Journal::open(&umbrella);
+            let journal_entry = if !dry_run && records_journal(&app_cmd) {
+                let command_line = env::args().skip(1).collect::<Vec<_>>().join(" ");
+                Some(journal.record(&umbrella, &config_path, &command_line)?)
+            } else {
+                None
+            };
+
+            let mut exit_code = 0;
+            let result = (|| -> Result<()> {
+                if match app_cmd {
+                    App::Add {
+                        source,
+                        submodule_path: None,
+                        ..
+                    } => wok::cmd::repo::add(
                         &mut wok_config,
                         &umbrella,
-                        &mut output,
+                        &path::PathBuf::from(source),
+                    )?,
+                    App::Add {
+                        source,
+                        submodule_path: Some(submodule_path),
+                        branch,
+                    } => {
+                        wok::cmd::repo::add_from_url(
+                            &mut wok_config,
+                            &umbrella,
+                            &config_path,
+                            &source,
+                            &submodule_path,
+                            branch.as_deref(),
+                        )?;
+                        false // Config is saved and committed by add
+                    },
+                    App::Remove {
+                        submodule_path,
+                        purge: false,
+                        ..
+                    } => wok::cmd::repo::rm(&mut wok_config, &submodule_path)?,
+                    App::Remove {
+                        submodule_path,
+                        purge: true,
+                        delete_git_dir,
+                        force,
+                    } => {
+                        wok::cmd::repo::rm_purge(
+                            &mut wok_config,
+                            &umbrella,
+                            &config_path,
+                            &submodule_path,
+                            delete_git_dir,
+                            force,
+                        )?;
+                        false // Config is saved and committed by rm
+                    },
+                    App::Switch {
+                        create,
+                        from,
+                        fetch,
+                        all,
+                        branch,
+                        autostash,
+                        atomic,
+                        repos,
+                    } => {
+                        let report = wok::cmd::switch(
+                            &mut wok_config,
+                            &umbrella,
+                            &mut output,
+                            &wok::cmd::SwitchOptions {
+                                create,
+                                from: from.as_deref(),
+                                fetch,
+                                all,
+                                branch: branch.as_deref(),
+                                repos: &repos,
+                                groups: &groups,
+                                autostash,
+                                atomic,
+                                dry_run,
+                            },
+                        )?;
+                        wok::output::write_report(&mut stdout(), format, &report)?;
+                        report.config_updated
+                    },
+                    App::Branch {
+                        delete,
+                        remote,
+                        rename,
+                        repos,
+                    } => {
+                        let action = match (delete, rename) {
+                            (Some(branch), _) => {
+                                wok::cmd::BranchAction::Delete { branch, remote }
+                            },
+                            (None, Some(names)) => wok::cmd::BranchAction::Rename {
+                                from: names[0].clone(),
+                                to: names[1].clone(),
+                            },
+                            (None, None) => wok::cmd::BranchAction::List,
+                        };
+                        let report = wok::cmd::branch(
+                            &mut wok_config,
+                            &umbrella,
+                            &mut output,
+                            &action,
+                            &repos,
+                            &groups,
+                        )?;
+                        wok::output::write_report(&mut stdout(), format, &report)?;
+
+                        // Exit once the journal entry is taken care of
+                        exit_code = report.exit_code;
+                        report.config_updated
+                    },
+                    App::Lock { file } => {
+                        let lock_path = wok::config::Lock::path_for(&config_path);
+                        let report = wok::cmd::lock(
+                            &mut wok_config,
+                            &umbrella,
+                            &mut output,
+                            &groups,
+                            file.then_some(lock_path.as_path()),
+                            dry_run,
+                        )?;
+                        wok::output::write_report(&mut stdout(), format, &report)?;
+                        false // Don't save config for lock command
+                    },
+                    App::Sync { locked } => {
+                        wok::cmd::sync(
+                            &mut wok_config,
+                            &umbrella,
+                            &mut output,
+                            locked,
+                        )?;
+                        false // Don't save config for sync command
+                    },
+                    App::Update {
+                        continue_update: true,
+                        ..
+                    } => {
+                        if dry_run {
+                            bail!("Dry run is not supported by update --continue");
+                        }
+                        let report = wok::cmd::update_continue(&umbrella, &mut output)?;
+                        wok::output::write_report(&mut stdout(), format, &report)?;
+                        exit_code = report.exit_code;
+                        false // Don't save config for update command
+                    },
+                    App::Update {
+                        abort_update: true, ..
+                    } => {
+                        if dry_run {
+                            bail!("Dry run is not supported by update --abort");
+                        }
+                        let report = wok::cmd::update_abort(&umbrella, &mut output)?;
+                        wok::output::write_report(&mut stdout(), format, &report)?;
+                        exit_code = report.exit_code;
+                        false // Don't save config for update command
+                    },
+                    App::Update {
                         no_commit,
-                        include_umbrella,
-                    )?;
-                    false // Don't save config for update command
-                },
-                App::Status { fetch } => {
-                    wok::cmd::status(&mut wok_config, &umbrella, &mut output, fetch)?;
-                    false // Don't save config for status command
-                },
-                App::Push {
-                    set_upstream,
-                    all,
-                    branch,
-                    umbrella: umbrella_flag,
-                    no_umbrella: no_umbrella_flag,
-                    repos,
-                } => {
-                    let include_umbrella =
-                        resolve_include_umbrella(umbrella_flag, no_umbrella_flag);
-
-                    wok::cmd::push(
-                        &mut wok_config,
-                        &umbrella,
-                        &mut output,
+                        ff_only,
+                        rebase,
+                        merge,
+                        autostash,
+                        umbrella: umbrella_flag,
+                        no_umbrella: no_umbrella_flag,
+                        to,
+                        repos,
+                        ..
+                    } => {
+                        // Pinning only moves the given subrepos
+                        let include_umbrella = to.is_none()
+                            && resolve_include_umbrella(
+                                umbrella_flag,
+                                no_umbrella_flag,
+                            );
+                        let report = wok::cmd::update(
+                            &mut wok_config,
+                            &umbrella,
+                            &mut output,
+                            &wok::cmd::UpdateOptions {
+                                no_commit,
+                                include_umbrella,
+                                jobs,
+                                groups: &groups,
+                                pull: resolve_pull_strategy(ff_only, rebase, merge),
+                                autostash,
+                                to: to.as_deref(),
+                                repos: &repos,
+                                dry_run,
+                            },
+                        )?;
+                        wok::output::write_report(&mut stdout(), format, &report)?;
+                        exit_code = report.exit_code;
+                        false // Don't save config for update command
+                    },
+                    App::Status { fetch, check } => {
+                        let report = wok::cmd::status(
+                            &mut wok_config,
+                            &umbrella,
+                            &mut output,
+                            fetch,
+                            check,
+                            jobs,
+                            &groups,
+                        )?;
+                        wok::output::write_report(&mut stdout(), format, &report)?;
+
+                        if report.exit_code != 0 {
+                            output.flush()?;
+                            process::exit(report.exit_code);
+                        }
+                        false // Don't save config for status command
+                    },
+                    App::Push {
                         set_upstream,
                         all,
-                        branch.as_deref(),
-                        include_umbrella,
-                        &repos,
-                    )?;
-                    false // Don't save config for push command
-                },
-                App::Tag {
-                    create,
-                    sign,
-                    message,
-                    push,
-                    all,
-                    umbrella: umbrella_flag,
-                    no_umbrella: no_umbrella_flag,
-                    repos,
-                } => {
-                    let (positional_tag, repo_args) =
-                        resolve_tag_arguments(&create, all, &repos, &wok_config)?;
-                    let tag_name = create.as_deref().or(positional_tag.as_deref());
-
-                    let include_umbrella =
-                        resolve_include_umbrella(umbrella_flag, no_umbrella_flag);
-
-                    wok::cmd::tag(
-                        &mut wok_config,
-                        &umbrella,
-                        &mut output,
-                        tag_name,
+                        branch,
+                        umbrella: umbrella_flag,
+                        no_umbrella: no_umbrella_flag,
+                        force_with_lease,
+                        atomic,
+                        repos,
+                    } => {
+                        let include_umbrella =
+                            resolve_include_umbrella(umbrella_flag, no_umbrella_flag);
+
+                        let report = wok::cmd::push(
+                            &mut wok_config,
+                            &umbrella,
+                            &mut output,
+                            &wok::cmd::PushOptions {
+                                set_upstream,
+                                all,
+                                branch: branch.as_deref(),
+                                include_umbrella,
+                                repos: &repos,
+                                jobs,
+                                groups: &groups,
+                                force_with_lease,
+                                atomic,
+                                dry_run,
+                            },
+                        )?;
+                        wok::output::write_report(&mut stdout(), format, &report)?;
+
+                        if report.exit_code != 0 {
+                            output.flush()?;
+                            process::exit(report.exit_code);
+                        }
+                        false // Don't save config for push command
+                    },
+                    App::Exec {
+                        all,
+                        parallel,
+                        keep_going,
+                        repos,
+                        command,
+                    } => {
+                        wok::cmd::exec(
+                            &mut wok_config,
+                            &umbrella,
+                            &mut output,
+                            all,
+                            &repos,
+                            &command,
+                            parallel,
+                            keep_going,
+                            jobs_flag,
+                            &groups,
+                        )?;
+                        false // Don't save config for exec command
+                    },
+                    App::Tag {
+                        create,
+                        bump,
+                        pre,
+                        delete,
+                        list,
+                        sort,
+                        points_at,
                         sign,
-                        message.as_deref(),
+                        message,
                         push,
                         all,
-                        include_umbrella,
-                        repo_args,
-                    )?;
-                    false // Don't save config for tag command
-                },
-                App::TestAuth => {
-                    wok::cmd::test_auth(&umbrella, &mut output)?;
-                    false // Don't save config for test-auth command
-                },
-            } {
-                wok_config.save(&config_path)?;
+                        umbrella: umbrella_flag,
+                        no_umbrella: no_umbrella_flag,
+                        repos,
+                    } => {
+                        // These options say which tags to act on, so every positional
+                        // argument is a repo
+                        let named = bump.is_some()
+                            || delete.is_some()
+                            || list.is_some()
+                            || sort.is_some()
+                            || points_at.is_some();
+                        let (positional_tag, repo_args) = resolve_tag_arguments(
+                            &create,
+                            named,
+                            all,
+                            &repos,
+                            &wok_config,
+                        )?;
+                        let tag_name = create.as_deref().or(positional_tag.as_deref());
+
+                        let include_umbrella =
+                            resolve_include_umbrella(umbrella_flag, no_umbrella_flag);
+
+                        let options = wok::cmd::TagOptions {
+                            sign,
+                            message: message.as_deref(),
+                            push,
+                            all,
+                            include_umbrella,
+                            repos: repo_args,
+                            jobs,
+                            groups: &groups,
+                            dry_run,
+                        };
+                        let report = if let Some(name) = &delete {
+                            wok::cmd::tag_delete(
+                                &mut wok_config,
+                                &umbrella,
+                                &mut output,
+                                name,
+                                &options,
+                            )?
+                        } else if let Some(level) = bump {
+                            wok::cmd::tag_bump(
+                                &mut wok_config,
+                                &umbrella,
+                                &mut output,
+                                level,
+                                pre.as_deref(),
+                                &options,
+                            )?
+                        } else {
+                            let filter = wok::cmd::TagFilter {
+                                pattern: list,
+                                points_at,
+                                sort: sort.unwrap_or_default(),
+                            };
+                            wok::cmd::tag(
+                                &mut wok_config,
+                                &umbrella,
+                                &mut output,
+                                tag_name,
+                                &filter,
+                                &options,
+                            )?
+                        };
+                        wok::output::write_report(&mut stdout(), format, &report)?;
+                        exit_code = report.exit_code;
+                        false // Don't save config for tag command
+                    },
+                    App::TestAuth => {
+                        let report = wok::cmd::test_auth(&umbrella, &mut output)?;
+                        wok::output::write_report(&mut stdout(), format, &report)?;
+                        false // Don't save config for test-auth command
+                    },
+                    App::Undo => {
+                        wok::cmd::undo(&umbrella, &config_path, &mut output)?;
+                        false // Config is restored by undo
+                    },
+                    App::History => {
+                        wok::cmd::history(&umbrella, &mut output)?;
+                        false // Don't save config for history command
+                    },
+                    App::Stash { action } => {
+                        wok::cmd::stash(&umbrella, &mut output, action)?;
+                        false // Don't save config for stash command
+                    },
+                } {
+                    wok_config.save(&config_path)?;
+                }
+                Ok(())
+            })();
+
+            // Drop the entry of a command that failed or changed nothing, so
+            // that undo doesn't use it up. The error of the command comes first.
+            let forgotten = journal_entry
+                .map(|id| journal.forget_if_unchanged(id, &umbrella, &config_path))
+                .transpose();
+            result?;
+            forgotten?;
+
+            if exit_code != 0 {
+                output.flush()?;
+                process::exit(exit_code);
             }
         },
     };
@@ -433,12 +1165,40 @@ mod tests {
         let repos = vec![path::PathBuf::from("v2.0.0")];
 
         let (positional_tag, remaining) =
-            resolve_tag_arguments(&None, true, &repos, &config).unwrap();
+            resolve_tag_arguments(&None, false, true, &repos, &config).unwrap();
 
         assert_eq!(positional_tag.as_deref(), Some("v2.0.0"));
         assert!(remaining.is_empty());
     }
 
+    #[test]
+    fn keep_positional_repos_when_bumping() {
+        let config = config_with_repo("api");
+        let repos = vec![path::PathBuf::from("web")];
+
+        let (positional_tag, remaining) =
+            resolve_tag_arguments(&None, true, false, &repos, &config).unwrap();
+
+        assert_eq!(positional_tag, None);
+        assert_eq!(remaining, repos.as_slice());
+    }
+
+    #[test]
+    fn clone_dir_strips_git_suffix() {
+        assert_eq!(
+            clone_dir_from_url("https://example.com/team/workspace.git").unwrap(),
+            path::PathBuf::from("workspace")
+        );
+    }
+
+    #[test]
+    fn clone_dir_handles_scp_like_urls() {
+        assert_eq!(
+            clone_dir_from_url("git@example.com:workspace.git/").unwrap(),
+            path::PathBuf::from("workspace")
+        );
+    }
+
     #[test]
     fn resolve_include_umbrella_defaults_to_true() {
         assert!(resolve_include_umbrella(false, false));
@@ -449,6 +1209,15 @@ mod tests {
         assert!(!resolve_include_umbrella(false, true));
     }
 
+    #[test]
+    fn resolve_pull_strategy_defaults_to_config() {
+        assert_eq!(resolve_pull_strategy(false, false, false), None);
+        assert_eq!(
+            resolve_pull_strategy(true, false, false),
+            Some(wok::config::PullStrategy::FastForwardOnly)
+        );
+    }
+
     #[test]
     fn resolve_include_umbrella_respects_umbrella_flag() {
         assert!(resolve_include_umbrella(true, false));
@@ -459,13 +1228,27 @@ mod tests {
         assert!(resolve_include_umbrella(true, true));
     }
 
+    #[test]
+    fn resolve_jobs_defaults_to_sequential() {
+        let config = config_with_repo("api");
+        assert_eq!(resolve_jobs(None, &config), 1);
+    }
+
+    #[test]
+    fn resolve_jobs_prefers_flag_over_config() {
+        let mut config = config_with_repo("api");
+        config.jobs = Some(4);
+        assert_eq!(resolve_jobs(None, &config), 4);
+        assert_eq!(resolve_jobs(Some(8), &config), 8);
+    }
+
     #[test]
     fn allows_explicit_repos_with_all_when_tag_is_positional() {
         let config = config_with_repo("api");
         let repos = vec![path::PathBuf::from("v2.0.0"), path::PathBuf::from("api")];
 
         let (positional_tag, remaining) =
-            resolve_tag_arguments(&None, true, &repos, &config).unwrap();
+            resolve_tag_arguments(&None, false, true, &repos, &config).unwrap();
 
         assert_eq!(positional_tag.as_deref(), Some("v2.0.0"));
         assert_eq!(remaining, &repos[1..]);
@@ -477,7 +1260,7 @@ mod tests {
         let repos = vec![path::PathBuf::from("api")];
 
         let (positional_tag, remaining) =
-            resolve_tag_arguments(&None, false, &repos, &config).unwrap();
+            resolve_tag_arguments(&None, false, false, &repos, &config).unwrap();
 
         assert!(positional_tag.is_none());
         assert_eq!(remaining, repos.as_slice());
@@ -489,7 +1272,7 @@ mod tests {
         let repos = vec![path::PathBuf::from("api")];
         let create = Some(String::from("v2.0.0"));
 
-        let result = resolve_tag_arguments(&create, true, &repos, &config);
+        let result = resolve_tag_arguments(&create, false, true, &repos, &config);
         assert!(result.is_err());
     }
 
@@ -499,7 +1282,7 @@ mod tests {
         let repos = vec![path::PathBuf::from("v2.0.0"), path::PathBuf::from("api")];
 
         let (positional_tag, remaining) =
-            resolve_tag_arguments(&None, false, &repos, &config).unwrap();
+            resolve_tag_arguments(&None, false, false, &repos, &config).unwrap();
 
         assert_eq!(positional_tag.as_deref(), Some("v2.0.0"));
         assert_eq!(remaining, &repos[1..]);
@@ -516,7 +1299,7 @@ mod tests {
         ];
 
         let (positional_tag, remaining) =
-            resolve_tag_arguments(&None, true, &repos, &config).unwrap();
+            resolve_tag_arguments(&None, false, true, &repos, &config).unwrap();
 
         assert_eq!(positional_tag.as_deref(), Some("v2.0.0"));
         assert_eq!(remaining, &repos[1..]);
@@ -15,6 +15,7 @@ pub fn assemble<W: Write>(
     workspace_dir: &path::Path,
     config_path: &path::Path,
     stdout: &mut W,
+    dry_run: bool,
 ) -> Result<()> {
     if !workspace_dir.exists() {
         bail!(
@@ -35,6 +36,10 @@ pub fn assemble<W: Write>(
         workspace_dir.display()
     )?;
 
+    if dry_run {
+        return plan_assemble(workspace_dir, config_path, stdout);
+    }
+
     let mut workspace_repo =
         ensure_git_repo(workspace_dir, true).with_context(|| {
             format!("Cannot prepare repo at `{}`", workspace_dir.display())
@@ -98,6 +103,104 @@ pub fn assemble<W: Write>(
     Ok(())
 }
 
+/// Print what `assemble` would do without initializing or registering anything.
+fn plan_assemble<W: Write>(
+    workspace_dir: &path::Path,
+    config_path: &path::Path,
+    stdout: &mut W,
+) -> Result<()> {
+    let submodule_paths = match Repository::open(workspace_dir) {
+        std::result::Result::Ok(repo) => {
+            plan_initial_commit(&repo, workspace_dir, stdout)?;
+            current_submodule_paths(&repo)?
+        },
+        Err(_) => {
+            writeln!(
+                stdout,
+                "Would init git repo at `{}`",
+                workspace_dir.display()
+            )?;
+            HashSet::new()
+        },
+    };
+
+    let mut subrepos = Vec::new();
+    for entry in std::fs::read_dir(workspace_dir).with_context(|| {
+        format!(
+            "Cannot read workspace directory at `{}`",
+            workspace_dir.display()
+        )
+    })? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let name = entry.file_name();
+        if name == OsStr::new(".git") || name == OsStr::new(".gitmodules") {
+            continue;
+        }
+
+        let entry_path = entry.path();
+        let rel_path = PathBuf::from(&name);
+
+        let head = match Repository::open(&entry_path) {
+            std::result::Result::Ok(child_repo) => {
+                plan_initial_commit(&child_repo, &entry_path, stdout)?;
+                branch_name(&child_repo).unwrap_or_else(|| String::from("main"))
+            },
+            Err(_) => {
+                writeln!(stdout, "Would init git repo at `{}`", entry_path.display())?;
+                String::from("main")
+            },
+        };
+
+        if !submodule_paths.contains(&rel_path) {
+            writeln!(
+                stdout,
+                "Would register `{}` as submodule",
+                rel_path.display()
+            )?;
+        }
+        subrepos.push((rel_path, head));
+    }
+
+    subrepos.sort();
+    writeln!(stdout, "Would write config at `{}`", config_path.display())?;
+    for (rel_path, head) in &subrepos {
+        writeln!(stdout, "- '{}': on '{}'", rel_path.display(), head)?;
+    }
+
+    Ok(())
+}
+
+fn plan_initial_commit<W: Write>(
+    repo: &Repository,
+    path: &path::Path,
+    stdout: &mut W,
+) -> Result<()> {
+    if repo.head().is_err() {
+        writeln!(
+            stdout,
+            "Would create initial commit in `{}`",
+            path.display()
+        )?;
+    }
+    Ok(())
+}
+
+/// Name of the branch HEAD points to, even if it has no commits yet.
+fn branch_name(repo: &Repository) -> Option<String> {
+    let head = repo.find_reference("HEAD").ok()?;
+    let target = head.symbolic_target()?;
+    Some(
+        target
+            .strip_prefix("refs/heads/")
+            .unwrap_or(target)
+            .to_string(),
+    )
+}
+
 fn ensure_git_repo(path: &path::Path, ensure_commit: bool) -> Result<Repository> {
     let repo = match Repository::open(path) {
         std::result::Result::Ok(repo) => repo,
@@ -240,7 +343,7 @@ fn repo_remote_url(repo: &Repository) -> Result<Option<String>> {
     }
 }
 
-fn run_git<I, S>(cwd: &path::Path, args: I) -> Result<()>
+pub(crate) fn run_git<I, S>(cwd: &path::Path, args: I) -> Result<()>
 where
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
@@ -0,0 +1,471 @@
+use anyhow::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path;
+use std::result::Result::Ok;
+use std::sync::{Arc, Mutex};
+
+use crate::{config, output, repo};
+
+/// What `wok branch` does with the branches of the selected repos.
+#[derive(Debug, Clone)]
+pub enum BranchAction {
+    /// Show which repos have which local and remote branches
+    List,
+    /// Delete a merged branch, and optionally its remote counterpart
+    Delete { branch: String, remote: bool },
+    /// Rename a branch, updating the heads in the wok file
+    Rename { from: String, to: String },
+}
+
+/// Manage branches across the workspace. Every configured repo is selected
+/// unless `target_repos` or `groups` narrow the selection.
+pub fn branch<W: Write>(
+    wok_config: &mut config::Config,
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+    action: &BranchAction,
+    target_repos: &[path::PathBuf],
+    groups: &[String],
+) -> Result<output::Report> {
+    let mut report = output::Report::new("branch");
+    let selected = wok_config.select_repos(
+        "branch",
+        &umbrella.head,
+        target_repos.is_empty(),
+        groups,
+        target_repos,
+    );
+
+    match action {
+        BranchAction::List => list(umbrella, &selected, stdout, &mut report)?,
+        BranchAction::Delete { branch, remote } => {
+            delete(umbrella, &selected, stdout, &mut report, branch, *remote)?
+        },
+        BranchAction::Rename { from, to } => rename(
+            wok_config,
+            umbrella,
+            &selected,
+            stdout,
+            &mut report,
+            from,
+            to,
+        )?,
+    }
+
+    Ok(report)
+}
+
+/// Branches of a single repo, keyed by branch name.
+#[derive(Default)]
+struct Branches {
+    local: BTreeSet<String>,
+    /// Remote-tracking branches as `remote/branch`
+    remote: BTreeMap<String, Vec<String>>,
+    current: Option<String>,
+}
+
+impl Branches {
+    fn read(repo: &repo::Repo) -> Result<Self> {
+        let mut branches = Branches::default();
+
+        for entry in repo.git_repo.branches(None)? {
+            let (branch, branch_type) = entry?;
+            let name = match branch.name()? {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+
+            match branch_type {
+                git2::BranchType::Local => {
+                    if branch.is_head() {
+                        branches.current = Some(name.clone());
+                    }
+                    branches.local.insert(name);
+                },
+                git2::BranchType::Remote => {
+                    // The remote's default branch is only a pointer to another one
+                    if branch.get().symbolic_target().is_some() {
+                        continue;
+                    }
+                    if let Some((_, short_name)) = name.split_once('/') {
+                        branches
+                            .remote
+                            .entry(short_name.to_owned())
+                            .or_default()
+                            .push(name.clone());
+                    }
+                },
+            }
+        }
+
+        Ok(branches)
+    }
+
+    fn names(&self) -> impl Iterator<Item = &String> {
+        self.local.iter().chain(self.remote.keys())
+    }
+
+    /// Short marker for `branch`: `*` when checked out, `L` when local, `R`
+    /// when on a remote, `-` when missing.
+    fn marker(&self, branch: &str) -> String {
+        let mut marker = String::new();
+        if self.current.as_deref() == Some(branch) {
+            marker.push('*');
+        } else if self.local.contains(branch) {
+            marker.push('L');
+        }
+        if self.remote.contains_key(branch) {
+            marker.push('R');
+        }
+        if marker.is_empty() {
+            marker.push('-');
+        }
+        marker
+    }
+}
+
+fn list<W: Write>(
+    umbrella: &repo::Repo,
+    selected: &[config::Repo],
+    stdout: &mut W,
+    report: &mut output::Report,
+) -> Result<()> {
+    // The umbrella comes first, without a path
+    let mut columns = vec![(None, String::from("umbrella"), Branches::read(umbrella)?)];
+    for config_repo in selected {
+        if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
+            columns.push((
+                Some(&config_repo.path),
+                config_repo.path.display().to_string(),
+                Branches::read(subrepo)?,
+            ));
+        }
+    }
+
+    for (path, _, branches) in &columns {
+        let remote: Vec<&String> = branches.remote.values().flatten().collect();
+        match path {
+            Some(path) => {
+                report.set(path, "current", &branches.current);
+                report.set(path, "local", &branches.local);
+                report.set(path, "remote", &remote);
+            },
+            None => {
+                report.set_umbrella("current", &branches.current);
+                report.set_umbrella("local", &branches.local);
+                report.set_umbrella("remote", &remote);
+            },
+        }
+    }
+
+    let names: BTreeSet<&String> = columns
+        .iter()
+        .flat_map(|(_, _, branches)| branches.names())
+        .collect();
+
+    // One row per branch and one column per repo
+    let mut rows = vec![
+        std::iter::once(String::from("branch"))
+            .chain(columns.iter().map(|(_, label, _)| label.clone()))
+            .collect::<Vec<_>>(),
+    ];
+    for name in &names {
+        rows.push(
+            std::iter::once((*name).clone())
+                .chain(columns.iter().map(|(_, _, branches)| branches.marker(name)))
+                .collect(),
+        );
+    }
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(stdout, "{}", line.trim_end())?;
+    }
+    writeln!(stdout, "(* checked out, L local, R remote)")?;
+
+    Ok(())
+}
+
+fn delete<W: Write>(
+    umbrella: &repo::Repo,
+    selected: &[config::Repo],
+    stdout: &mut W,
+    report: &mut output::Report,
+    branch_name: &str,
+    remote: bool,
+) -> Result<()> {
+    writeln!(stdout, "Deleting branch '{}'...", branch_name)?;
+    let mut deleted = 0;
+    let mut failed = 0;
+    let mut found = false;
+
+    for config_repo in selected {
+        let subrepo = match umbrella.get_subrepo_by_path(&config_repo.path) {
+            Some(subrepo) => subrepo,
+            None => continue,
+        };
+        let label = config_repo.path.display();
+
+        let local = subrepo
+            .git_repo
+            .find_branch(branch_name, git2::BranchType::Local)
+            .ok();
+        let upstream = if remote {
+            remote_tracking_branch(subrepo, branch_name)?
+        } else {
+            None
+        };
+        if local.is_none() && upstream.is_none() {
+            continue;
+        }
+        found = true;
+
+        let refusal = if config_repo.head == branch_name {
+            Some(format!(
+                "'{}' is the head of the repo in the wok file",
+                branch_name
+            ))
+        } else if local.as_ref().is_some_and(|local| local.is_head()) {
+            Some(format!("'{}' is checked out", branch_name))
+        } else {
+            unmerged(subrepo, branch_name, local.as_ref(), remote)?
+        };
+        if let Some(reason) = refusal {
+            failed += 1;
+            writeln!(
+                stdout,
+                "- '{}': not deleting '{}' - {}",
+                label, branch_name, reason
+            )?;
+            report.set(&config_repo.path, "result", "refused");
+            report.set(&config_repo.path, "reason", &reason);
+            continue;
+        }
+
+        if let Some((remote_name, tracking_ref)) = &upstream {
+            if let Err(e) =
+                delete_remote_branch(subrepo, remote_name, tracking_ref, branch_name)
+            {
+                failed += 1;
+                writeln!(
+                    stdout,
+                    "- '{}': failed to delete '{}' on '{}' - {:#}",
+                    label, branch_name, remote_name, e
+                )?;
+                report.set(&config_repo.path, "result", "failed");
+                report.set(&config_repo.path, "reason", format!("{:#}", e));
+                continue;
+            }
+            report.set(&config_repo.path, "remote", remote_name);
+        }
+        if let Some(mut local) = local {
+            local.delete().with_context(|| {
+                format!("Cannot delete '{}' in repo at `{}`", branch_name, label)
+            })?;
+        }
+
+        deleted += 1;
+        match &upstream {
+            Some((remote_name, _)) => writeln!(
+                stdout,
+                "- '{}': deleted '{}' here and on '{}'",
+                label, branch_name, remote_name
+            )?,
+            None => writeln!(stdout, "- '{}': deleted '{}'", label, branch_name)?,
+        }
+        report.set(&config_repo.path, "result", "deleted");
+    }
+
+    if !found {
+        writeln!(stdout, "No repositories have branch '{}'", branch_name)?;
+    }
+    report.summarize("deleted", deleted);
+    report.summarize("failed", failed);
+    if failed > 0 {
+        report.exit_code = 1;
+    }
+
+    Ok(())
+}
+
+/// Remote name and remote-tracking ref of `branch_name` on the remote it would
+/// be pushed to, if that ref exists.
+fn remote_tracking_branch(
+    repo: &repo::Repo,
+    branch_name: &str,
+) -> Result<Option<(String, String)>> {
+    let remote_name = repo.get_remote_name_for_branch(branch_name)?;
+    let tracking_ref = format!("refs/remotes/{}/{}", remote_name, branch_name);
+    Ok(repo
+        .git_repo
+        .find_reference(&tracking_ref)
+        .is_ok()
+        .then_some((remote_name, tracking_ref)))
+}
+
+/// Why `branch_name` cannot be deleted without losing commits, if it can't.
+/// The branch must be merged into its upstream, or into HEAD when it has no
+/// upstream or the upstream is deleted along with it.
+fn unmerged(
+    repo: &repo::Repo,
+    branch_name: &str,
+    local: Option<&git2::Branch>,
+    remote: bool,
+) -> Result<Option<String>> {
+    let tip = match local {
+        Some(local) => local.get().peel_to_commit()?.id(),
+        None => match remote_tracking_branch(repo, branch_name)? {
+            Some((_, tracking_ref)) => repo.git_repo.refname_to_id(&tracking_ref)?,
+            None => return Ok(None),
+        },
+    };
+
+    let upstream = match (remote, local) {
+        (false, Some(local)) => local.upstream().ok(),
+        _ => None,
+    };
+    let (base, base_name) = match upstream {
+        Some(upstream) => (
+            upstream.get().peel_to_commit()?.id(),
+            upstream.name()?.unwrap_or_default().to_owned(),
+        ),
+        None => {
+            let head = repo.git_repo.head()?;
+            (
+                head.peel_to_commit()?.id(),
+                head.shorthand().unwrap_or("HEAD").to_owned(),
+            )
+        },
+    };
+
+    if base == tip || repo.git_repo.graph_descendant_of(base, tip)? {
+        Ok(None)
+    } else {
+        Ok(Some(format!(
+            "'{}' is not fully merged into '{}'",
+            branch_name, base_name
+        )))
+    }
+}
+
+fn delete_remote_branch(
+    repo: &repo::Repo,
+    remote_name: &str,
+    tracking_ref: &str,
+    branch_name: &str,
+) -> Result<()> {
+    let mut remote = repo.git_repo.find_remote(remote_name)?;
+
+    // The remote reports rejected refs through a callback rather than an error
+    let rejected = Arc::new(Mutex::new(None));
+    let mut callbacks = repo.remote_callbacks()?;
+    let rejected_status = Arc::clone(&rejected);
+    callbacks.push_update_reference(move |_, status| {
+        if let Some(message) = status {
+            *rejected_status.lock().unwrap() = Some(message.to_owned());
+        }
+        Ok(())
+    });
+    let mut push_options = git2::PushOptions::new();
+    push_options.remote_callbacks(callbacks);
+
+    remote.push(
+        &[&format!(":refs/heads/{}", branch_name)],
+        Some(&mut push_options),
+    )?;
+    if let Some(message) = rejected.lock().unwrap().take() {
+        bail!("{}", message);
+    }
+
+    if let Ok(mut reference) = repo.git_repo.find_reference(tracking_ref) {
+        reference.delete()?;
+    }
+    Ok(())
+}
+
+fn rename<W: Write>(
+    wok_config: &mut config::Config,
+    umbrella: &repo::Repo,
+    selected: &[config::Repo],
+    stdout: &mut W,
+    report: &mut output::Report,
+    from: &str,
+    to: &str,
+) -> Result<()> {
+    writeln!(stdout, "Renaming branch '{}' to '{}'...", from, to)?;
+    let mut renamed = 0;
+    let mut failed = 0;
+
+    for config_repo in selected {
+        let subrepo = match umbrella.get_subrepo_by_path(&config_repo.path) {
+            Some(subrepo) => subrepo,
+            None => continue,
+        };
+        let label = config_repo.path.display();
+
+        let mut branch =
+            match subrepo.git_repo.find_branch(from, git2::BranchType::Local) {
+                Ok(branch) => branch,
+                Err(_) => continue,
+            };
+
+        if subrepo
+            .git_repo
+            .find_branch(to, git2::BranchType::Local)
+            .is_ok()
+        {
+            failed += 1;
+            writeln!(
+                stdout,
+                "- '{}': not renaming '{}' - '{}' already exists",
+                label, from, to
+            )?;
+            report.set(&config_repo.path, "result", "refused");
+            continue;
+        }
+
+        if let Err(e) = branch.rename(to, false) {
+            failed += 1;
+            writeln!(
+                stdout,
+                "- '{}': failed to rename '{}' - {}",
+                label,
+                from,
+                e.message()
+            )?;
+            report.set(&config_repo.path, "result", "failed");
+            report.set(&config_repo.path, "reason", e.message());
+            continue;
+        }
+        renamed += 1;
+        writeln!(stdout, "- '{}': renamed '{}' to '{}'", label, from, to)?;
+        report.set(&config_repo.path, "result", "renamed");
+
+        if config_repo.head == from
+            && wok_config.set_repo_head(&config_repo.path, &to.to_owned())
+        {
+            report.set(&config_repo.path, "head", to);
+            report.config_updated = true;
+        }
+    }
+
+    if renamed + failed == 0 {
+        writeln!(stdout, "No repositories have branch '{}'", from)?;
+    }
+    report.summarize("renamed", renamed);
+    report.summarize("failed", failed);
+    if failed > 0 {
+        report.exit_code = 1;
+    }
+
+    Ok(())
+}
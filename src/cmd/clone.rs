@@ -0,0 +1,147 @@
+use anyhow::*;
+use std::io::Write;
+use std::path;
+use std::result::Result::Ok;
+
+use crate::{config, repo};
+
+/// Clone an umbrella repo and bring up every subrepo listed in its wok file on
+/// the configured branch.
+pub fn clone<W: Write>(
+    url: &str,
+    target_dir: &path::Path,
+    wokfile_path: &path::Path,
+    stdout: &mut W,
+) -> Result<()> {
+    if target_dir.exists()
+        && target_dir
+            .read_dir()
+            .with_context(|| {
+                format!("Cannot read directory `{}`", target_dir.display())
+            })?
+            .next()
+            .is_some()
+    {
+        bail!(
+            "Destination `{}` already exists and is not empty",
+            target_dir.display()
+        );
+    }
+
+    writeln!(
+        stdout,
+        "Cloning '{}' into `{}`...",
+        url,
+        target_dir.display()
+    )?;
+
+    let mut fetch_options = git2::FetchOptions::new();
+    fetch_options.remote_callbacks(repo::default_remote_callbacks()?);
+
+    let git_repo = git2::build::RepoBuilder::new()
+        .fetch_options(fetch_options)
+        .clone(url, target_dir)
+        .with_context(|| format!("Cannot clone umbrella repo from '{}'", url))?;
+
+    let config_path = if wokfile_path.is_absolute() {
+        wokfile_path.to_path_buf()
+    } else {
+        target_dir.join(wokfile_path)
+    };
+
+    if !config_path.exists() {
+        bail!(
+            "Cloned repo has no Git Wok file at `{}`",
+            config_path.display()
+        );
+    }
+
+    let wok_config = config::Config::load(&config_path)?;
+
+    for config_repo in &wok_config.repos {
+        let submodule_path = config_repo.path.to_string_lossy();
+
+        let mut submodule =
+            git_repo.find_submodule(&submodule_path).with_context(|| {
+                format!("Cannot find submodule at `{}`", config_repo.path.display())
+            })?;
+
+        let mut fetch_options = git2::FetchOptions::new();
+        fetch_options.remote_callbacks(repo::default_remote_callbacks()?);
+        let mut update_options = git2::SubmoduleUpdateOptions::new();
+        update_options.fetch(fetch_options);
+
+        submodule
+            .update(true, Some(&mut update_options))
+            .with_context(|| {
+                format!(
+                    "Cannot initialize submodule at `{}`",
+                    config_repo.path.display()
+                )
+            })?;
+
+        let subrepo_git = submodule.open().with_context(|| {
+            format!("Cannot open submodule at `{}`", config_repo.path.display())
+        })?;
+
+        let tracking = checkout_head(&subrepo_git, &config_repo.head)?;
+        let commit = subrepo_git.head()?.peel_to_commit()?.id().to_string();
+        let short_commit = &commit[..std::cmp::min(8, commit.len())];
+
+        match tracking {
+            Some(upstream) => writeln!(
+                stdout,
+                "- '{}': checked out '{}' ({}) tracking '{}'",
+                config_repo.path.display(),
+                config_repo.head,
+                short_commit,
+                upstream
+            )?,
+            None => writeln!(
+                stdout,
+                "- '{}': checked out '{}' ({}), no upstream branch found",
+                config_repo.path.display(),
+                config_repo.head,
+                short_commit
+            )?,
+        }
+    }
+
+    writeln!(
+        stdout,
+        "Cloned workspace with {} repositories",
+        wok_config.repos.len()
+    )?;
+    Ok(())
+}
+
+/// Put a freshly initialized submodule on `branch_name` at the commit recorded
+/// by the umbrella and set up tracking against `origin` when possible.
+/// Returns the upstream branch name if tracking was configured.
+pub(crate) fn checkout_head(
+    git_repo: &git2::Repository,
+    branch_name: &str,
+) -> Result<Option<String>> {
+    let commit = git_repo.head()?.peel_to_commit()?;
+    git_repo.set_head_detached(commit.id())?;
+
+    // The clone may have created the branch at the remote tip already; the
+    // umbrella's recorded commit takes precedence.
+    let mut branch = git_repo.branch(branch_name, &commit, true)?;
+
+    let upstream = format!("origin/{}", branch_name);
+    let tracking = if git_repo
+        .find_branch(&upstream, git2::BranchType::Remote)
+        .is_ok()
+    {
+        branch.set_upstream(Some(&upstream))?;
+        Some(upstream)
+    } else {
+        None
+    };
+
+    git_repo.set_head(&format!("refs/heads/{}", branch_name))?;
+    git_repo.checkout_head(None)?;
+
+    Ok(tracking)
+}
@@ -0,0 +1,506 @@
+use anyhow::*;
+use serde::Serialize;
+use std::fmt;
+use std::io::Write;
+use std::path;
+use std::result::Result::Ok;
+
+use crate::{config, output, repo};
+
+/// Report drift between the Wokfile, `.gitmodules` and the umbrella repo, and
+/// repair what can be repaired without losing work when `fix` is set.
+///
+/// Works on the raw umbrella repo, since detached or uninitialized subrepos
+/// cannot be loaded as [`repo::Repo`].
+pub fn doctor<W: Write>(
+    wok_config: &mut config::Config,
+    umbrella_dir: &path::Path,
+    stdout: &mut W,
+    fix: bool,
+) -> Result<output::Report> {
+    let mut report = output::Report::new("doctor");
+
+    let git_repo = git2::Repository::open(umbrella_dir)
+        .with_context(|| format!("Cannot open repo at `{}`", umbrella_dir.display()))?;
+
+    writeln!(stdout, "Checking workspace...")?;
+
+    let findings = diagnose(wok_config, &git_repo, umbrella_dir)?;
+
+    let mut fixed = 0;
+    let mut records = Vec::new();
+
+    for (path, problem) in &findings {
+        let outcome = if fix {
+            Some(fix_problem(wok_config, &git_repo, path, problem))
+        } else {
+            None
+        };
+
+        let mut record = ProblemRecord {
+            kind: problem.kind(),
+            message: problem.to_string(),
+            fixed: None,
+            fix: None,
+        };
+
+        match outcome {
+            None => {
+                writeln!(stdout, "- '{}': {}", path.display(), problem)?;
+            },
+            Some(Ok(Some(action))) => {
+                fixed += 1;
+                report.config_updated |=
+                    matches!(problem, Problem::Unconfigured { .. });
+                writeln!(
+                    stdout,
+                    "- '{}': {}, fixed: {}",
+                    path.display(),
+                    problem,
+                    action
+                )?;
+                record.fixed = Some(true);
+                record.fix = Some(action);
+            },
+            Some(Ok(None)) => {
+                writeln!(
+                    stdout,
+                    "- '{}': {}, cannot be fixed automatically",
+                    path.display(),
+                    problem
+                )?;
+                record.fixed = Some(false);
+            },
+            Some(Err(e)) => {
+                writeln!(
+                    stdout,
+                    "- '{}': {}, failed to fix - {}",
+                    path.display(),
+                    problem,
+                    e
+                )?;
+                record.fixed = Some(false);
+                record.fix = Some(format!("failed - {}", e));
+            },
+        }
+
+        records.push((path, record));
+    }
+
+    for (path, _) in &findings {
+        if report
+            .repos
+            .iter()
+            .any(|repo_record| repo_record.path == path.to_string_lossy())
+        {
+            continue;
+        }
+        let problems: Vec<&ProblemRecord> = records
+            .iter()
+            .filter(|(record_path, _)| *record_path == path)
+            .map(|(_, record)| record)
+            .collect();
+        report.set(path, "problems", problems);
+    }
+
+    let remaining = findings.len() - fixed;
+
+    if findings.is_empty() {
+        writeln!(stdout, "No problems found")?;
+    } else if fix {
+        writeln!(stdout, "Fixed {} of {} problems", fixed, findings.len())?;
+    } else {
+        writeln!(
+            stdout,
+            "Found {} problems, run with --fix to repair what can be repaired",
+            findings.len()
+        )?;
+    }
+
+    report.summarize("problems", findings.len());
+    report.summarize("fixed", fixed);
+    report.summarize("remaining", remaining);
+    if remaining > 0 {
+        report.exit_code = 1;
+    }
+
+    Ok(report)
+}
+
+/// Drift between the Wokfile, `.gitmodules` and the umbrella repo.
+#[derive(Debug, Clone, PartialEq)]
+enum Problem {
+    /// Listed in the Wokfile but not a submodule of the umbrella.
+    MissingSubmodule,
+    /// A submodule the Wokfile doesn't list, with the branch to add it on.
+    Unconfigured {
+        head: Option<String>,
+    },
+    /// `.gitmodules` tracks another branch than the configured head.
+    BranchMismatch {
+        tracked: String,
+        head: String,
+    },
+    Uninitialized,
+    DetachedHead,
+    /// `.git/config` points the submodule at another URL than `.gitmodules`.
+    UrlMismatch {
+        gitmodules: String,
+        config: String,
+    },
+}
+
+impl Problem {
+    fn kind(&self) -> &'static str {
+        match self {
+            Problem::MissingSubmodule => "missing_submodule",
+            Problem::Unconfigured { .. } => "unconfigured",
+            Problem::BranchMismatch { .. } => "branch_mismatch",
+            Problem::Uninitialized => "uninitialized",
+            Problem::DetachedHead => "detached_head",
+            Problem::UrlMismatch { .. } => "url_mismatch",
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::MissingSubmodule => {
+                write!(f, "configured in the wok file but not a submodule")
+            },
+            Problem::Unconfigured { .. } => {
+                write!(f, "submodule missing from the wok file")
+            },
+            Problem::BranchMismatch { tracked, head } => write!(
+                f,
+                ".gitmodules tracks branch '{}' instead of configured head '{}'",
+                tracked, head
+            ),
+            Problem::Uninitialized => write!(f, "submodule is not initialized"),
+            Problem::DetachedHead => write!(f, "HEAD is detached"),
+            Problem::UrlMismatch { gitmodules, config } => write!(
+                f,
+                ".gitmodules URL '{}' differs from '{}' in .git/config",
+                gitmodules, config
+            ),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProblemRecord {
+    kind: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fixed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<String>,
+}
+
+fn diagnose(
+    wok_config: &config::Config,
+    git_repo: &git2::Repository,
+    umbrella_dir: &path::Path,
+) -> Result<Vec<(path::PathBuf, Problem)>> {
+    let submodules = git_repo.submodules().with_context(|| {
+        format!(
+            "Cannot load submodules for repo at `{}`",
+            umbrella_dir.display()
+        )
+    })?;
+
+    // libgit2 overlays `.git/config` on submodule settings, so `.gitmodules`
+    // is read on its own to compare the two
+    let gitmodules_path = umbrella_dir.join(".gitmodules");
+    let gitmodules = if gitmodules_path.exists() {
+        Some(git2::Config::open(&gitmodules_path)?)
+    } else {
+        None
+    };
+    let repo_config = git_repo.config()?;
+
+    let mut findings = Vec::new();
+
+    for config_repo in &wok_config.repos {
+        if !submodules
+            .iter()
+            .any(|submodule| submodule.path() == config_repo.path)
+        {
+            findings.push((config_repo.path.clone(), Problem::MissingSubmodule));
+        }
+    }
+
+    // Report in Wokfile order, then the submodules it doesn't list
+    let mut ordered: Vec<&git2::Submodule> = Vec::new();
+    for config_repo in &wok_config.repos {
+        ordered.extend(
+            submodules
+                .iter()
+                .filter(|submodule| submodule.path() == config_repo.path),
+        );
+    }
+    ordered.extend(submodules.iter().filter(|submodule| {
+        !wok_config
+            .repos
+            .iter()
+            .any(|config_repo| config_repo.path == submodule.path())
+    }));
+
+    for submodule in ordered {
+        let path = submodule.path().to_path_buf();
+        let name = submodule.name().with_context(|| {
+            format!("Invalid submodule name at `{}`", path.display())
+        })?;
+        let subrepo = submodule.open().ok();
+
+        let tracked = gitmodules.as_ref().and_then(|gitmodules| {
+            gitmodules
+                .get_string(&format!("submodule.{}.branch", name))
+                .ok()
+        });
+
+        match wok_config
+            .repos
+            .iter()
+            .find(|config_repo| config_repo.path == path)
+        {
+            Some(config_repo) => {
+                if let Some(tracked) = &tracked
+                    && *tracked != config_repo.head
+                {
+                    findings.push((
+                        path.clone(),
+                        Problem::BranchMismatch {
+                            tracked: tracked.clone(),
+                            head: config_repo.head.clone(),
+                        },
+                    ));
+                }
+            },
+            None => {
+                let current = subrepo
+                    .as_ref()
+                    .filter(|subrepo| !subrepo.head_detached().unwrap_or(true))
+                    .and_then(|subrepo| {
+                        subrepo
+                            .head()
+                            .ok()
+                            .and_then(|head| head.shorthand().map(String::from))
+                    });
+                findings.push((
+                    path.clone(),
+                    Problem::Unconfigured {
+                        head: tracked.clone().or(current),
+                    },
+                ));
+            },
+        }
+
+        // Uninitialized submodules have no URL registered in `.git/config`
+        if let Some(gitmodules_url) = gitmodules.as_ref().and_then(|gitmodules| {
+            gitmodules
+                .get_string(&format!("submodule.{}.url", name))
+                .ok()
+        }) && let Ok(config_url) =
+            repo_config.get_string(&format!("submodule.{}.url", name))
+        {
+            let resolved = resolve_url(git_repo, umbrella_dir, &gitmodules_url);
+            if !urls_match(&resolved, &config_url) {
+                findings.push((
+                    path.clone(),
+                    Problem::UrlMismatch {
+                        gitmodules: gitmodules_url,
+                        config: config_url,
+                    },
+                ));
+            }
+        }
+
+        match subrepo {
+            None => findings.push((path, Problem::Uninitialized)),
+            Some(subrepo) => {
+                // Repos pinned to a tag or revision are detached on purpose
+                let pinned = wok_config.repos.iter().any(|config_repo| {
+                    config_repo.path == path && config_repo.pin().is_some()
+                });
+                if !pinned && subrepo.head_detached().unwrap_or(false) {
+                    findings.push((path, Problem::DetachedHead));
+                }
+            },
+        }
+    }
+
+    Ok(findings)
+}
+
+/// Apply the fix for `problem`. Returns a description of what was done, or
+/// `None` when fixing it could lose work or needs a decision.
+fn fix_problem(
+    wok_config: &mut config::Config,
+    git_repo: &git2::Repository,
+    path: &path::Path,
+    problem: &Problem,
+) -> Result<Option<String>> {
+    match problem {
+        // The entry may still be wanted, and the wok file has no URL to add
+        // the submodule back from
+        Problem::MissingSubmodule => Ok(None),
+        Problem::Unconfigured { head } => match head {
+            Some(head) => {
+                wok_config.add_repo(path, head);
+                Ok(Some(format!("added to the wok file on '{}'", head)))
+            },
+            None => Ok(None),
+        },
+        Problem::BranchMismatch { head, .. } => {
+            let submodule = find_submodule(git_repo, path)?;
+            let name = submodule.name().context("Invalid submodule name")?;
+            // `submodule_set_branch` needs a mutable repo; the config edit is
+            // the same as `git config -f .gitmodules`
+            let mut gitmodules = git2::Config::open(
+                &git_repo
+                    .workdir()
+                    .context("Umbrella repo has no work dir")?
+                    .join(".gitmodules"),
+            )?;
+            gitmodules.set_str(&format!("submodule.{}.branch", name), head)?;
+            Ok(Some(format!("set .gitmodules branch to '{}'", head)))
+        },
+        Problem::Uninitialized => {
+            let head = match wok_config
+                .repos
+                .iter()
+                .find(|config_repo| config_repo.path == path)
+            {
+                Some(config_repo) => config_repo.head.clone(),
+                None => return Ok(None),
+            };
+
+            let mut submodule = find_submodule(git_repo, path)?;
+
+            let mut fetch_options = git2::FetchOptions::new();
+            fetch_options.remote_callbacks(repo::default_remote_callbacks()?);
+            let mut update_options = git2::SubmoduleUpdateOptions::new();
+            update_options.fetch(fetch_options);
+
+            submodule
+                .update(true, Some(&mut update_options))
+                .with_context(|| {
+                    format!("Cannot initialize submodule at `{}`", path.display())
+                })?;
+
+            let subrepo = submodule.open()?;
+            super::clone::checkout_head(&subrepo, &head)?;
+
+            Ok(Some(format!("initialized on '{}'", head)))
+        },
+        Problem::DetachedHead => {
+            let head = match wok_config
+                .repos
+                .iter()
+                .find(|config_repo| config_repo.path == path)
+            {
+                Some(config_repo) => config_repo.head.clone(),
+                None => return Ok(None),
+            };
+
+            let subrepo = find_submodule(git_repo, path)?.open()?;
+            let head_oid = subrepo.head()?.peel_to_commit()?.id();
+            let branch_ref = format!("refs/heads/{}", head);
+
+            match subrepo.refname_to_id(&branch_ref) {
+                // Reattaching to a branch at the same commit changes nothing
+                // in the work tree
+                Ok(branch_oid) if branch_oid == head_oid => {},
+                // Moving the branch or HEAD could leave commits behind
+                Ok(_) => return Ok(None),
+                Err(_) => {
+                    subrepo.branch(&head, &subrepo.find_commit(head_oid)?, false)?;
+                },
+            }
+            subrepo.set_head(&branch_ref)?;
+
+            Ok(Some(format!("attached to '{}'", head)))
+        },
+        Problem::UrlMismatch { .. } => {
+            let mut submodule = find_submodule(git_repo, path)?;
+            submodule.sync()?;
+            Ok(Some(String::from("synced URL from .gitmodules")))
+        },
+    }
+}
+
+fn find_submodule<'a>(
+    git_repo: &'a git2::Repository,
+    path: &path::Path,
+) -> Result<git2::Submodule<'a>> {
+    git_repo
+        .find_submodule(&path.to_string_lossy())
+        .with_context(|| format!("Cannot find submodule at `{}`", path.display()))
+}
+
+/// Resolve a relative submodule URL the way git does: against the URL of the
+/// umbrella's default remote, or its work dir when it has no remote.
+fn resolve_url(
+    git_repo: &git2::Repository,
+    umbrella_dir: &path::Path,
+    url: &str,
+) -> String {
+    if !(url.starts_with("./") || url.starts_with("../")) {
+        return String::from(url);
+    }
+
+    let remote_name = git_repo
+        .head()
+        .ok()
+        .and_then(|head| head.shorthand().map(String::from))
+        .and_then(|branch| {
+            git_repo
+                .config()
+                .ok()?
+                .get_string(&format!("branch.{}.remote", branch))
+                .ok()
+        })
+        .unwrap_or_else(|| String::from("origin"));
+
+    let base = git_repo
+        .find_remote(&remote_name)
+        .ok()
+        .and_then(|remote| remote.url().map(String::from))
+        .unwrap_or_else(|| umbrella_dir.to_string_lossy().into_owned());
+
+    let mut resolved = String::from(base.trim_end_matches('/'));
+    for component in url.split('/') {
+        match component {
+            "." | "" => {},
+            ".." => {
+                if let Some(index) = resolved.rfind(['/', ':']) {
+                    resolved.truncate(index);
+                }
+            },
+            component => {
+                resolved.push('/');
+                resolved.push_str(component);
+            },
+        }
+    }
+    resolved
+}
+
+fn urls_match(left: &str, right: &str) -> bool {
+    let left = left.trim_end_matches('/');
+    let right = right.trim_end_matches('/');
+    if left == right {
+        return true;
+    }
+
+    // Local paths may be spelled differently, e.g. through a symlink
+    match (
+        path::Path::new(left).canonicalize(),
+        path::Path::new(right).canonicalize(),
+    ) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => false,
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path;
+
+use crate::repo;
+
+/// Paths of the submodules whose gitlink in the umbrella's HEAD would change
+/// by recording the `planned` commits. Other submodules are compared at their
+/// checked out commit, unless `only_planned` is set.
+pub(crate) fn gitlink_changes(
+    umbrella: &repo::Repo,
+    planned: &HashMap<path::PathBuf, git2::Oid>,
+    only_planned: bool,
+) -> Result<Vec<path::PathBuf>> {
+    let head_tree = umbrella
+        .git_repo
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_tree().ok());
+
+    let mut changes = Vec::new();
+    for submodule in umbrella.git_repo.submodules()? {
+        let submodule_path = submodule.path().to_path_buf();
+
+        let oid = match planned.get(&submodule_path) {
+            Some(oid) => *oid,
+            None if only_planned => continue,
+            None => match submodule.workdir_id() {
+                Some(oid) => oid,
+                None => continue,
+            },
+        };
+
+        let locked_oid = head_tree
+            .as_ref()
+            .and_then(|tree| tree.get_path(&submodule_path).ok())
+            .map(|entry| entry.id());
+        if locked_oid != Some(oid) {
+            changes.push(submodule_path);
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Show the commit message the umbrella repo would get.
+pub(crate) fn write_commit_message<W: Write>(
+    stdout: &mut W,
+    message: &str,
+) -> Result<()> {
+    writeln!(stdout, "Would commit to the umbrella repo:")?;
+    for line in message.lines() {
+        if line.is_empty() {
+            writeln!(stdout)?;
+        } else {
+            writeln!(stdout, "    {}", line)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use anyhow::*;
+use std::io::Write;
+use std::process;
+use std::result::Result::Ok;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{config, repo};
+
+#[allow(clippy::too_many_arguments)]
+pub fn exec<W: Write>(
+    wok_config: &mut config::Config,
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+    all: bool,
+    target_repos: &[std::path::PathBuf],
+    command: &[String],
+    parallel: bool,
+    keep_going: bool,
+    jobs: Option<usize>,
+    groups: &[String],
+) -> Result<()> {
+    let (program, args) = command.split_first().context("No command given to run")?;
+
+    // Determine which repos to run in
+    let repos_to_run: Vec<config::Repo> =
+        wok_config.select_repos("exec", &umbrella.head, all, groups, target_repos);
+
+    let subrepos: Vec<(&config::Repo, &repo::Repo)> = repos_to_run
+        .iter()
+        .filter_map(|config_repo| {
+            umbrella
+                .get_subrepo_by_path(&config_repo.path)
+                .map(|subrepo| (config_repo, subrepo))
+        })
+        .collect();
+
+    if subrepos.is_empty() {
+        writeln!(stdout, "No repositories to run in")?;
+        return Ok(());
+    }
+
+    writeln!(
+        stdout,
+        "Running '{}' in {} repositories...",
+        command.join(" "),
+        subrepos.len()
+    )?;
+
+    let umbrella_dir = umbrella
+        .work_dir
+        .canonicalize()
+        .unwrap_or_else(|_| umbrella.work_dir.clone());
+    let command_in = |config_repo: &config::Repo, subrepo: &repo::Repo| {
+        let mut command = process::Command::new(program);
+        command
+            .args(args)
+            .current_dir(&subrepo.work_dir)
+            .env("WOK_REPO_PATH", &config_repo.path)
+            .env("WOK_REPO_HEAD", &config_repo.head)
+            .env("WOK_UMBRELLA", &umbrella_dir);
+        command
+    };
+    let cannot_run = |config_repo: &config::Repo| {
+        format!(
+            "Cannot run '{}' in '{}'",
+            program,
+            config_repo.path.display()
+        )
+    };
+
+    let statuses = if parallel {
+        let failed = AtomicBool::new(false);
+        let jobs = resolve_parallel_jobs(jobs, subrepos.len());
+        let results =
+            super::parallel::for_each_repo(&subrepos, jobs, |config_repo, subrepo| {
+                // Without --keep-going nothing new is started once a command has
+                // failed
+                if !keep_going && failed.load(Ordering::SeqCst) {
+                    return Ok(None);
+                }
+
+                let result = command_in(config_repo, subrepo)
+                    .stdin(process::Stdio::null())
+                    .output()
+                    .with_context(|| cannot_run(config_repo));
+
+                if !matches!(&result, Ok(output) if output.status.success()) {
+                    failed.store(true, Ordering::SeqCst);
+                }
+
+                result.map(Some)
+            });
+
+        // The captured output is printed in the configured order
+        let mut statuses = Vec::new();
+        for ((config_repo, _), result) in subrepos.iter().zip(results) {
+            if let Ok(Some(output)) = &result {
+                writeln!(stdout, "- '{}':", config_repo.path.display())?;
+                stdout.write_all(&output.stdout)?;
+                stdout.write_all(&output.stderr)?;
+            }
+            statuses.push(result.map(|output| output.map(|output| output.status)));
+        }
+        statuses
+    } else {
+        // One repo at a time the command gets the terminal, so that its output
+        // shows as it comes and it can prompt for input
+        let mut statuses = Vec::new();
+        let mut failed = false;
+        for (config_repo, subrepo) in &subrepos {
+            if !keep_going && failed {
+                statuses.push(Ok(None));
+                continue;
+            }
+
+            writeln!(stdout, "- '{}':", config_repo.path.display())?;
+            stdout.flush()?;
+            let status = command_in(config_repo, subrepo)
+                .status()
+                .with_context(|| cannot_run(config_repo));
+
+            failed |= !matches!(&status, Ok(status) if status.success());
+            statuses.push(status.map(Some));
+        }
+        statuses
+    };
+
+    let mut summary = Vec::new();
+    let mut failures = 0;
+
+    for ((config_repo, _), status) in subrepos.iter().zip(statuses) {
+        let outcome = match status {
+            Ok(Some(status)) if status.success() => "exit code 0".to_string(),
+            Ok(Some(status)) => {
+                failures += 1;
+                match status.code() {
+                    Some(code) => format!("exit code {}", code),
+                    None => "terminated by signal".to_string(),
+                }
+            },
+            Ok(None) => "not run".to_string(),
+            Err(e) => {
+                failures += 1;
+                format!("failed to run - {}", e)
+            },
+        };
+        summary.push((config_repo.path.display().to_string(), outcome));
+    }
+
+    writeln!(stdout, "Summary:")?;
+    for (path, outcome) in &summary {
+        writeln!(stdout, "- '{}': {}", path, outcome)?;
+    }
+
+    if failures > 0 {
+        bail!(
+            "Command failed in {} of {} repositories",
+            failures,
+            summary.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// `--parallel` honors an explicit job count and otherwise runs as many repos
+/// at once as the machine has cores.
+fn resolve_parallel_jobs(jobs: Option<usize>, repo_count: usize) -> usize {
+    let jobs = jobs.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|cores| cores.get())
+            .unwrap_or(1)
+    });
+    jobs.min(repo_count).max(1)
+}
@@ -1,15 +1,55 @@
 use anyhow::*;
+use std::collections::HashMap;
 use std::io::Write;
+use std::path;
+use std::result::Result::Ok;
+use std::time::SystemTime;
 
-use crate::{config, repo};
+use super::dry_run;
+use crate::{config, output, repo};
 
+/// Lock the current subrepo commits in the umbrella repo. With `lock_path` the
+/// state is also recorded in a lock file, which is committed along with the
+/// gitlinks when it is inside the umbrella repo.
 pub fn lock<W: Write>(
     wok_config: &mut config::Config,
     umbrella: &repo::Repo,
     stdout: &mut W,
-) -> Result<()> {
+    groups: &[String],
+    lock_path: Option<&path::Path>,
+    dry_run: bool,
+) -> Result<output::Report> {
+    let mut report = output::Report::new("lock");
+    let repos_to_lock =
+        wok_config.select_repos("lock", &umbrella.head, true, groups, &[]);
+    // Configured repos outside of the selected groups or opted out of lock via
+    // `skip_for` keep their gitlink as it is
+    let left_out: Vec<&path::Path> = wok_config
+        .repos
+        .iter()
+        .filter(|config_repo| {
+            !repos_to_lock
+                .iter()
+                .any(|selected| selected.path == config_repo.path)
+        })
+        .map(|config_repo| config_repo.path.as_path())
+        .collect();
+
+    if dry_run {
+        plan_lock(
+            umbrella,
+            stdout,
+            &mut report,
+            &repos_to_lock,
+            &left_out,
+            groups,
+            lock_path,
+        )?;
+        return Ok(report);
+    }
+
     // Ensure each repo is switched to its configured branch
-    for config_repo in &wok_config.repos {
+    for config_repo in &repos_to_lock {
         if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
             // Switch subrepo to its configured branch
             subrepo.switch(&config_repo.head)?;
@@ -21,12 +61,36 @@ pub fn lock<W: Write>(
     for submodule in umbrella.git_repo.submodules()? {
         let submodule_path = submodule.path();
 
+        // Leave submodules outside of the selected groups or opted out of lock
+        // untouched
+        if left_out.contains(&submodule_path)
+            || (!groups.is_empty()
+                && !repos_to_lock
+                    .iter()
+                    .any(|config_repo| config_repo.path == submodule_path))
+        {
+            continue;
+        }
+
         // Only add submodules that have a head (are initialized)
         if let Some(_submodule_oid) = submodule.head_id() {
             // Add the submodule entry to the index
             index.add_path(submodule_path)?;
         }
     }
+
+    if let Some(lock_path) = lock_path {
+        let lock = build_lock(wok_config, umbrella, &repos_to_lock)?;
+        lock.save(lock_path)?;
+        writeln!(stdout, "Wrote lock file `{}`", lock_path.display())?;
+        report.summarize("lock_file", lock_path.display().to_string());
+
+        if let Ok(lock_rel_path) = lock_path.strip_prefix(&umbrella.work_dir) {
+            index.add_path(lock_rel_path)?;
+        }
+        wok_config.lock = Some(lock);
+    }
+
     index.write()?;
 
     // Check if there are any changes to commit
@@ -41,11 +105,12 @@ pub fn lock<W: Write>(
     // If nothing changed, don't create a commit
     if tree.id() == parent_tree.id() {
         writeln!(stdout, "No submodule changes detected; nothing to lock")?;
-        return Ok(());
+        report.summarize("locked", false);
+        return Ok(report);
     }
 
     // Build commit message with changed submodule summary
-    let (commit_message, _changed_submodules) =
+    let (commit_message, changed_submodules) =
         build_lock_commit_message(umbrella, &parent_tree, &tree)?;
 
     umbrella.git_repo.commit(
@@ -57,8 +122,69 @@ pub fn lock<W: Write>(
         &[&parent_commit],
     )?;
 
+    for (name, message) in &changed_submodules {
+        report.set(std::path::Path::new(name), "commit_message", message);
+    }
+
     writeln!(stdout, "Locked submodule state")?;
-    Ok(())
+    report.summarize("locked", true);
+    Ok(report)
+}
+
+/// Record the state of `repos_to_lock` on top of the previous lock, keeping the
+/// timestamps of repos whose state didn't change.
+fn build_lock(
+    wok_config: &config::Config,
+    umbrella: &repo::Repo,
+    repos_to_lock: &[config::Repo],
+) -> Result<config::Lock> {
+    let previous = wok_config.lock.clone().unwrap_or_default();
+    let now = config::timestamp(SystemTime::now())?;
+    let mut lock = config::Lock::new();
+
+    for config_repo in &wok_config.repos {
+        let subrepo = match umbrella.get_subrepo_by_path(&config_repo.path) {
+            Some(subrepo)
+                if repos_to_lock
+                    .iter()
+                    .any(|locked_repo| locked_repo.path == config_repo.path) =>
+            {
+                subrepo
+            },
+            // Repos left out of this lock keep their previous state
+            _ => {
+                lock.repos.extend(previous.get(&config_repo.path).cloned());
+                continue;
+            },
+        };
+
+        let commit = subrepo.git_repo.head()?.peel_to_commit()?.id().to_string();
+        let url = subrepo
+            .get_remote_name_for_branch(&config_repo.head)
+            .ok()
+            .and_then(|remote_name| subrepo.git_repo.find_remote(&remote_name).ok())
+            .and_then(|remote| remote.url().map(String::from));
+
+        let mut locked_repo = config::LockedRepo {
+            path: config_repo.path.clone(),
+            branch: config_repo.head.clone(),
+            commit,
+            url,
+            locked_at: now,
+        };
+        if let Some(previous_repo) = previous.get(&config_repo.path) {
+            let unchanged = config::LockedRepo {
+                locked_at: previous_repo.locked_at,
+                ..locked_repo.clone()
+            };
+            if unchanged == *previous_repo {
+                locked_repo = unchanged;
+            }
+        }
+        lock.repos.push(locked_repo);
+    }
+
+    Ok(lock)
 }
 
 /// Build a commit message for lock operation and return changed submodule info.
@@ -95,21 +221,7 @@ fn build_lock_commit_message(
                                     std::result::Result::Ok(head_ref) => {
                                         match head_ref.peel_to_commit() {
                                             std::result::Result::Ok(commit) => {
-                                                let message = commit
-                                                    .message()
-                                                    .unwrap_or("(no message)");
-
-                                                // Get first line of commit message and truncate to 50 chars
-                                                let first_line = message
-                                                    .lines()
-                                                    .next()
-                                                    .unwrap_or("(no message)");
-                                                let truncated = if first_line.len() > 50
-                                                {
-                                                    format!("{}...", &first_line[..47])
-                                                } else {
-                                                    first_line.to_string()
-                                                };
+                                                let truncated = commit_summary(&commit);
 
                                                 let submodule_name = submodule
                                                     .path()
@@ -133,15 +245,100 @@ fn build_lock_commit_message(
         }
     }
 
-    // Build the commit message
+    let message = format_lock_commit_message(&changed_submodules);
+    std::result::Result::Ok((message, changed_submodules))
+}
+
+/// Format the lock commit message from the changed submodules and their commit
+/// summaries.
+fn format_lock_commit_message(changed_submodules: &[(String, String)]) -> String {
     let mut message = String::from("Lock submodule state");
 
     if !changed_submodules.is_empty() {
         message.push_str("\n\nChanged submodules:");
-        for (name, commit_msg) in &changed_submodules {
+        for (name, commit_msg) in changed_submodules {
             message.push_str(&format!("\n- {}: {}", name, commit_msg));
         }
     }
 
-    std::result::Result::Ok((message, changed_submodules))
+    message
+}
+
+/// First line of the commit message, truncated to 50 chars.
+fn commit_summary(commit: &git2::Commit) -> String {
+    let first_line = commit
+        .message()
+        .unwrap_or("(no message)")
+        .lines()
+        .next()
+        .unwrap_or("(no message)");
+    if first_line.len() > 50 {
+        format!("{}...", &first_line[..47])
+    } else {
+        first_line.to_string()
+    }
+}
+
+/// Print what `lock` would commit, locking each repo at the tip of its
+/// configured branch.
+fn plan_lock<W: Write>(
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+    report: &mut output::Report,
+    repos_to_lock: &[config::Repo],
+    left_out: &[&path::Path],
+    groups: &[String],
+    lock_path: Option<&path::Path>,
+) -> Result<()> {
+    report.summarize("dry_run", true);
+
+    let mut planned = HashMap::new();
+    let mut summaries = HashMap::new();
+    for config_repo in repos_to_lock {
+        if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
+            let commit = subrepo
+                .git_repo
+                .resolve_reference_from_short_name(&config_repo.head)
+                .and_then(|reference| reference.peel_to_commit())
+                .with_context(|| {
+                    format!(
+                        "Cannot find branch '{}' in repo at `{}`",
+                        config_repo.head,
+                        config_repo.path.display()
+                    )
+                })?;
+            planned.insert(config_repo.path.clone(), commit.id());
+            summaries.insert(config_repo.path.clone(), commit_summary(&commit));
+        }
+    }
+
+    if let Some(lock_path) = lock_path {
+        writeln!(stdout, "Would write lock file `{}`", lock_path.display())?;
+        report.summarize("lock_file", lock_path.display().to_string());
+    }
+
+    let mut changes = dry_run::gitlink_changes(umbrella, &planned, !groups.is_empty())?;
+    changes.retain(|path| !left_out.contains(&path.as_path()));
+    if changes.is_empty() {
+        writeln!(stdout, "No submodule changes detected; nothing to lock")?;
+        report.summarize("locked", false);
+        return Ok(());
+    }
+
+    let mut changed_submodules = Vec::new();
+    for path in &changes {
+        let summary = match summaries.get(path) {
+            Some(summary) => summary.clone(),
+            None => continue,
+        };
+        report.set(path, "commit_message", &summary);
+        changed_submodules.push((path.to_string_lossy().to_string(), summary));
+    }
+
+    dry_run::write_commit_message(
+        stdout,
+        &format_lock_commit_message(&changed_submodules),
+    )?;
+    report.summarize("locked", true);
+    Ok(())
 }
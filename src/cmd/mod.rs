@@ -1,22 +1,38 @@
 mod assemble;
+mod branch;
+mod clone;
 mod completion;
+mod doctor;
+mod dry_run;
+mod exec;
 mod init;
 mod lock;
+mod parallel;
 mod push;
+mod stash;
 mod status;
 mod switch;
+mod sync;
 mod tag;
 mod test_auth;
+mod undo;
 mod update;
 
 pub mod repo;
 pub use assemble::assemble;
+pub use branch::{BranchAction, branch};
+pub use clone::clone;
 pub use completion::{CompletionShell, completion};
+pub use doctor::doctor;
+pub use exec::exec;
 pub use init::init;
 pub use lock::lock;
-pub use push::push;
+pub use push::{PushOptions, push};
+pub use stash::{StashAction, stash};
 pub use status::status;
-pub use switch::switch;
-pub use tag::tag;
+pub use switch::{SwitchOptions, switch};
+pub use sync::sync;
+pub use tag::{BumpLevel, TagFilter, TagOptions, TagSort, tag, tag_bump, tag_delete};
 pub use test_auth::test_auth;
-pub use update::update;
+pub use undo::{history, undo};
+pub use update::{UpdateOptions, update, update_abort, update_continue};
@@ -0,0 +1,79 @@
+use anyhow::*;
+use std::result::Result::Ok;
+use std::{
+    panic, path,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use crate::repo;
+
+/// Run `work` for each repo using up to `jobs` worker threads.
+///
+/// Each repo comes with a context value (typically its config entry) that is
+/// handed to `work` along with the repo. Results are returned in the order of
+/// `repos` regardless of the order in which the work completes. With a single
+/// job everything runs on the calling thread using the given repo handles.
+pub(crate) fn for_each_repo<C, T, F>(
+    repos: &[(C, &repo::Repo)],
+    jobs: usize,
+    work: F,
+) -> Vec<Result<T>>
+where
+    C: Sync,
+    T: Send,
+    F: Fn(&C, &repo::Repo) -> Result<T> + Sync,
+{
+    if jobs <= 1 || repos.len() <= 1 {
+        return repos
+            .iter()
+            .map(|(context, repo)| work(context, repo))
+            .collect();
+    }
+
+    // git2 repository handles cannot be shared between threads, so every worker
+    // reopens the repos it processes.
+    let targets: Vec<(&C, path::PathBuf, String)> = repos
+        .iter()
+        .map(|(context, repo)| (context, repo.work_dir.clone(), repo.head.clone()))
+        .collect();
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<Option<Result<T>>> = targets.iter().map(|_| None).collect();
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.min(targets.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((context, work_dir, head)) = targets.get(index) else {
+                            break;
+                        };
+                        let result = repo::Repo::new(work_dir, Some(head))
+                            .and_then(|repo| work(context, &repo));
+                        done.push((index, result));
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            match worker.join() {
+                Ok(done) => {
+                    for (index, result) in done {
+                        results[index] = Some(result);
+                    }
+                },
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.expect("every repo is processed by a worker"))
+        .collect()
+}
@@ -1,20 +1,76 @@
 use anyhow::*;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::io::Write;
+use std::path;
 use std::result::Result::Ok;
+use std::sync::{Arc, Mutex};
 
-use crate::{config, repo};
+use crate::{config, output, repo};
+
+/// How `push` pushes the repos, and which ones.
+#[derive(Debug, Clone, Copy)]
+pub struct PushOptions<'a> {
+    /// Set the pushed remote branch as upstream of the local branch
+    pub set_upstream: bool,
+    /// Push all configured repos rather than those on the umbrella's branch
+    pub all: bool,
+    /// Branch to push, the umbrella's branch if not given
+    pub branch: Option<&'a str>,
+    /// Push the umbrella too, after the subrepos
+    pub include_umbrella: bool,
+    pub repos: &'a [std::path::PathBuf],
+    /// Number of repos pushed at once
+    pub jobs: usize,
+    pub groups: &'a [String],
+    pub force_with_lease: bool,
+    /// Check every repo before pushing any of them
+    pub atomic: bool,
+    pub dry_run: bool,
+}
+
+impl Default for PushOptions<'_> {
+    fn default() -> Self {
+        PushOptions {
+            set_upstream: false,
+            all: false,
+            branch: None,
+            include_umbrella: true,
+            repos: &[],
+            jobs: 1,
+            groups: &[],
+            force_with_lease: false,
+            atomic: false,
+            dry_run: false,
+        }
+    }
+}
 
-#[allow(clippy::too_many_arguments)]
 pub fn push<W: Write>(
     wok_config: &mut config::Config,
     umbrella: &repo::Repo,
     stdout: &mut W,
-    set_upstream: bool,
-    all: bool,
-    branch_name: Option<&str>,
-    include_umbrella: bool,
-    target_repos: &[std::path::PathBuf],
-) -> Result<()> {
+    options: &PushOptions,
+) -> Result<output::Report> {
+    let PushOptions {
+        set_upstream,
+        all,
+        branch: branch_name,
+        include_umbrella,
+        repos: target_repos,
+        jobs,
+        groups,
+        force_with_lease,
+        atomic,
+        dry_run,
+    } = *options;
+    let mut report = output::Report::new("push");
+    let upstream_note = if set_upstream {
+        " and set upstream"
+    } else {
+        ""
+    };
+
     // Determine the target branch
     let target_branch = match branch_name {
         Some(name) => name.to_string(),
@@ -22,40 +78,14 @@ pub fn push<W: Write>(
     };
 
     // Determine which repos to push
-    let repos_to_push: Vec<config::Repo> = if all {
-        // Push all configured repos, skipping those opted out unless explicitly targeted
-        wok_config
-            .repos
-            .iter()
-            .filter(|config_repo| {
-                !config_repo.is_skipped_for("push")
-                    || target_repos.contains(&config_repo.path)
-            })
-            .cloned()
-            .collect()
-    } else if !target_repos.is_empty() {
-        // Push only specified repos
-        wok_config
-            .repos
-            .iter()
-            .filter(|config_repo| target_repos.contains(&config_repo.path))
-            .cloned()
-            .collect()
-    } else {
-        // Push repos that match the current main repo branch
-        wok_config
-            .repos
-            .iter()
-            .filter(|config_repo| config_repo.head == umbrella.head)
-            .cloned()
-            .collect()
-    };
+    let repos_to_push: Vec<config::Repo> =
+        wok_config.select_repos("push", &umbrella.head, all, groups, target_repos);
 
     let total_targets = repos_to_push.len() + usize::from(include_umbrella);
 
     if total_targets == 0 {
         writeln!(stdout, "No repositories to push")?;
-        return Ok(());
+        return Ok(report);
     }
 
     writeln!(
@@ -64,59 +94,176 @@ pub fn push<W: Write>(
         total_targets, target_branch
     )?;
 
+    let subrepos: Vec<(&config::Repo, &repo::Repo)> = repos_to_push
+        .iter()
+        .filter_map(|config_repo| {
+            umbrella
+                .get_subrepo_by_path(&config_repo.path)
+                .map(|subrepo| (config_repo, subrepo))
+        })
+        .collect();
+
+    if atomic {
+        check_all_pushable(
+            stdout,
+            umbrella,
+            &subrepos,
+            &target_branch,
+            include_umbrella,
+            force_with_lease,
+            jobs,
+        )?;
+    }
+
     // Push submodules first, then umbrella repo
     // This ensures submodule commits exist remotely before the umbrella repo references them
-    for config_repo in &repos_to_push {
-        if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
-            match push_repo(subrepo, &target_branch, set_upstream) {
-                Ok(result) => match result {
-                    PushResult::Pushed => {
-                        writeln!(
-                            stdout,
-                            "- '{}': pushed to '{}'",
-                            config_repo.path.display(),
-                            target_branch
-                        )?;
-                    },
-                    PushResult::UpstreamSet => {
-                        writeln!(
-                            stdout,
-                            "- '{}': pushed to '{}' and set upstream",
-                            config_repo.path.display(),
-                            target_branch
-                        )?;
-                    },
-                    PushResult::UpToDate => {
-                        writeln!(
-                            stdout,
-                            "- '{}': already up to date",
-                            config_repo.path.display()
-                        )?;
-                    },
-                    PushResult::NoRemote => {
-                        writeln!(
-                            stdout,
-                            "- '{}': no remote configured, skipping",
-                            config_repo.path.display()
-                        )?;
-                    },
+    let push_results = super::parallel::for_each_repo(&subrepos, jobs, |_, subrepo| {
+        push_repo(
+            subrepo,
+            &target_branch,
+            set_upstream,
+            force_with_lease,
+            dry_run,
+        )
+    });
+
+    let mut failed = 0;
+    // Commits that are only planned to be pushed in a dry run
+    let mut planned = HashMap::new();
+
+    for ((config_repo, subrepo), push_result) in subrepos.iter().zip(push_results) {
+        report.set(&config_repo.path, "branch", &target_branch);
+        if let Ok(PushResult::Planned) = push_result
+            && let Ok(oid) = subrepo
+                .git_repo
+                .refname_to_id(&format!("refs/heads/{}", target_branch))
+        {
+            planned.insert(config_repo.path.clone(), oid);
+        }
+        match &push_result {
+            Ok(result) => {
+                report.set(&config_repo.path, "result", result);
+                if let PushResult::Rejected(reason) = result {
+                    failed += 1;
+                    report.set(&config_repo.path, "reason", reason);
+                }
+            },
+            Err(e) => {
+                failed += 1;
+                report.set(&config_repo.path, "error", e.to_string());
+            },
+        }
+
+        match push_result {
+            Ok(result) => match result {
+                PushResult::Pushed => {
+                    writeln!(
+                        stdout,
+                        "- '{}': pushed to '{}'",
+                        config_repo.path.display(),
+                        target_branch
+                    )?;
+                },
+                PushResult::UpstreamSet => {
+                    writeln!(
+                        stdout,
+                        "- '{}': pushed to '{}' and set upstream",
+                        config_repo.path.display(),
+                        target_branch
+                    )?;
+                },
+                PushResult::UpToDate => {
+                    writeln!(
+                        stdout,
+                        "- '{}': already up to date",
+                        config_repo.path.display()
+                    )?;
                 },
-                Err(e) => {
+                PushResult::NoRemote => {
+                    writeln!(
+                        stdout,
+                        "- '{}': no remote configured, skipping",
+                        config_repo.path.display()
+                    )?;
+                },
+                PushResult::Planned => {
+                    writeln!(
+                        stdout,
+                        "- '{}': would push '{}' to '{}'{}",
+                        config_repo.path.display(),
+                        push_refspec(&target_branch, force_with_lease),
+                        subrepo.get_remote_name_for_branch(&target_branch)?,
+                        upstream_note
+                    )?;
+                },
+                PushResult::Rejected(reason) => {
                     writeln!(
                         stdout,
-                        "- '{}': failed to push to '{}' - {}",
+                        "- '{}': push to '{}' rejected - {}",
                         config_repo.path.display(),
                         target_branch,
-                        e
+                        reason
                     )?;
                 },
-            }
+            },
+            Err(e) => {
+                writeln!(
+                    stdout,
+                    "- '{}': failed to push to '{}' - {}",
+                    config_repo.path.display(),
+                    target_branch,
+                    e
+                )?;
+            },
         }
     }
 
-    // Push umbrella repo last, after all submodules
-    if include_umbrella {
-        match push_repo(umbrella, &target_branch, set_upstream) {
+    // Push umbrella repo last, after all submodules, and only if the remotes of
+    // the submodules have every commit it references
+    let missing = if include_umbrella {
+        missing_gitlinks(umbrella, &target_branch, &planned)?
+    } else {
+        vec![]
+    };
+
+    if !missing.is_empty() {
+        failed += 1;
+        report.set_umbrella("branch", &target_branch);
+        report.set_umbrella("result", "blocked");
+        report.set_umbrella("missing", &missing);
+
+        writeln!(
+            stdout,
+            "- 'umbrella': not pushed to '{}', it references commits missing from \
+             subrepo remotes:",
+            target_branch
+        )?;
+        write_missing_gitlinks(stdout, &missing)?;
+    } else if include_umbrella {
+        let push_result = push_repo(
+            umbrella,
+            &target_branch,
+            set_upstream,
+            force_with_lease,
+            dry_run,
+        );
+
+        report.set_umbrella("branch", &target_branch);
+        match &push_result {
+            Ok(result) => {
+                report.set_umbrella("result", result);
+                if let PushResult::Rejected(reason) = result {
+                    failed += 1;
+                    report.set_umbrella("reason", reason);
+                }
+            },
+            Err(e) => {
+                failed += 1;
+                report.set_umbrella("error", e.to_string());
+            },
+        }
+
+        match push_result {
             Ok(result) => match result {
                 PushResult::Pushed => {
                     writeln!(stdout, "- 'umbrella': pushed to '{}'", target_branch)?;
@@ -134,6 +281,22 @@ pub fn push<W: Write>(
                 PushResult::NoRemote => {
                     writeln!(stdout, "- 'umbrella': no remote configured, skipping")?;
                 },
+                PushResult::Planned => {
+                    writeln!(
+                        stdout,
+                        "- 'umbrella': would push '{}' to '{}'{}",
+                        push_refspec(&target_branch, force_with_lease),
+                        umbrella.get_remote_name_for_branch(&target_branch)?,
+                        upstream_note
+                    )?;
+                },
+                PushResult::Rejected(reason) => {
+                    writeln!(
+                        stdout,
+                        "- 'umbrella': push to '{}' rejected - {}",
+                        target_branch, reason
+                    )?;
+                },
             },
             Err(e) => {
                 writeln!(
@@ -145,12 +308,29 @@ pub fn push<W: Write>(
         }
     }
 
-    writeln!(
-        stdout,
-        "Successfully processed {} repositories",
-        total_targets
-    )?;
-    Ok(())
+    if failed > 0 {
+        report.exit_code = 1;
+        writeln!(
+            stdout,
+            "Processed {} repositories, {} failed",
+            total_targets, failed
+        )?;
+    } else {
+        writeln!(
+            stdout,
+            "Successfully processed {} repositories",
+            total_targets
+        )?;
+    }
+
+    report.summarize("branch", &target_branch);
+    report.summarize("repos", total_targets);
+    report.summarize("failed", failed);
+    if dry_run {
+        report.summarize("dry_run", true);
+    }
+
+    Ok(report)
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -159,60 +339,331 @@ enum PushResult {
     UpstreamSet,
     UpToDate,
     NoRemote,
+    Planned,
+    /// The push would not fast-forward the remote branch, or the remote
+    /// refused it, for the given reason
+    Rejected(String),
 }
 
-/// Check if the local branch has new commits compared to the remote branch.
-/// Returns Ok(true) if push is needed, Ok(false) if already up-to-date, Err on failure.
-fn needs_push(
+impl Serialize for PushResult {
+    // Reports carry the reason of a rejection in a field of its own
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(match self {
+            PushResult::Pushed => "pushed",
+            PushResult::UpstreamSet => "upstream_set",
+            PushResult::UpToDate => "up_to_date",
+            PushResult::NoRemote => "no_remote",
+            PushResult::Planned => "planned",
+            PushResult::Rejected(_) => "rejected",
+        })
+    }
+}
+
+/// A commit recorded in the umbrella that the remote of its subrepo lacks.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct MissingGitlink {
+    path: path::PathBuf,
+    commit: String,
+    /// Remote the commit was looked up on, if the subrepo has one
+    remote: Option<String>,
+}
+
+fn push_refspec(branch_name: &str, force: bool) -> String {
+    format!(
+        "{}refs/heads/{}:refs/heads/{}",
+        if force { "+" } else { "" },
+        branch_name,
+        branch_name
+    )
+}
+
+/// Gitlinks on `branch_name` of the umbrella whose commits can't be found on
+/// the remotes of their subrepos. Commits reachable from the `planned` tips
+/// are assumed to be pushed. Nothing is checked if the umbrella has no remote.
+fn missing_gitlinks(
+    umbrella: &repo::Repo,
+    branch_name: &str,
+    planned: &HashMap<path::PathBuf, git2::Oid>,
+) -> Result<Vec<MissingGitlink>> {
+    let remote_name = umbrella.get_remote_name_for_branch(branch_name)?;
+    if umbrella.git_repo.find_remote(&remote_name).is_err() {
+        return Ok(vec![]);
+    }
+    let tree = match umbrella
+        .git_repo
+        .find_reference(&format!("refs/heads/{}", branch_name))
+        .and_then(|reference| reference.peel_to_tree())
+    {
+        Ok(tree) => tree,
+        // Pushing the umbrella will report the missing branch
+        Err(_) => return Ok(vec![]),
+    };
+
+    let mut missing = vec![];
+    for submodule in umbrella.git_repo.submodules()? {
+        let submodule_path = submodule.path().to_path_buf();
+        let commit = match tree.get_path(&submodule_path) {
+            Ok(entry) if entry.kind() == Some(git2::ObjectType::Commit) => entry.id(),
+            _ => continue,
+        };
+        // Uninitialized submodules can't have local-only commits
+        let subrepo = match umbrella.get_subrepo_by_path(&submodule_path) {
+            Some(subrepo) => subrepo,
+            None => continue,
+        };
+
+        if planned
+            .get(&submodule_path)
+            .is_some_and(|tip| contains(subrepo, *tip, commit))
+        {
+            continue;
+        }
+
+        let subrepo_remote = subrepo.get_remote_name_for_branch(branch_name)?;
+        let remote = match subrepo.git_repo.find_remote(&subrepo_remote) {
+            Ok(mut remote) => {
+                let heads = remote_heads(subrepo, &mut remote, git2::Direction::Fetch)?;
+                // Tips that were never fetched can't be looked into
+                if heads
+                    .iter()
+                    .any(|(_, tip)| subrepo.git_repo.find_commit(*tip).is_err())
+                {
+                    subrepo.fetch_remote(&subrepo_remote)?;
+                }
+                if heads.iter().any(|(_, tip)| contains(subrepo, *tip, commit)) {
+                    continue;
+                }
+                Some(subrepo_remote)
+            },
+            Err(_) => None,
+        };
+
+        missing.push(MissingGitlink {
+            path: submodule_path,
+            commit: commit.to_string(),
+            remote,
+        });
+    }
+
+    Ok(missing)
+}
+
+fn write_missing_gitlinks<W: Write>(
+    stdout: &mut W,
+    missing: &[MissingGitlink],
+) -> Result<()> {
+    for gitlink in missing {
+        match &gitlink.remote {
+            Some(remote) => writeln!(
+                stdout,
+                "    - '{}': {} is not on '{}'",
+                gitlink.path.display(),
+                &gitlink.commit[..8],
+                remote
+            )?,
+            None => writeln!(
+                stdout,
+                "    - '{}': {} cannot be checked, no remote configured",
+                gitlink.path.display(),
+                &gitlink.commit[..8]
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// Whether `commit` is `tip` or one of its ancestors in `repo`.
+fn contains(repo: &repo::Repo, tip: git2::Oid, commit: git2::Oid) -> bool {
+    tip == commit
+        || repo
+            .git_repo
+            .graph_descendant_of(tip, commit)
+            .unwrap_or(false)
+}
+
+/// Names and targets of the refs advertised by `remote`.
+fn remote_heads(
     repo: &repo::Repo,
     remote: &mut git2::Remote,
+    direction: git2::Direction,
+) -> Result<Vec<(String, git2::Oid)>> {
+    let connection =
+        remote.connect_auth(direction, Some(repo.remote_callbacks()?), None)?;
+    Ok(connection
+        .list()?
+        .iter()
+        .map(|head| (head.name().to_owned(), head.oid()))
+        .collect())
+}
+
+/// Make sure every repo can be pushed without being rejected before pushing
+/// any of them, including the commits the umbrella references.
+fn check_all_pushable<W: Write>(
+    stdout: &mut W,
+    umbrella: &repo::Repo,
+    subrepos: &[(&config::Repo, &repo::Repo)],
     branch_name: &str,
-) -> Result<bool> {
-    // Get local branch OID
-    let local_branch_ref = format!("refs/heads/{}", branch_name);
-    let local_oid = repo.git_repo.refname_to_id(&local_branch_ref)?;
-
-    // Connect to remote to check remote branch state
-    let connection = remote.connect_auth(
-        git2::Direction::Push,
-        Some(repo.remote_callbacks()?),
-        None,
-    )?;
+    include_umbrella: bool,
+    force_with_lease: bool,
+    jobs: usize,
+) -> Result<()> {
+    let checks = super::parallel::for_each_repo(subrepos, jobs, |_, subrepo| {
+        check_pushable(subrepo, branch_name, force_with_lease)
+    });
 
-    // Check if remote branch exists and get its OID
-    let remote_branch_ref = format!("refs/heads/{}", branch_name);
-    let mut remote_oid: Option<git2::Oid> = None;
+    let mut problems = vec![];
+    let mut pushed_tips = HashMap::new();
+    for ((config_repo, subrepo), check) in subrepos.iter().zip(checks) {
+        match check {
+            Ok(()) => {
+                if let Ok(oid) = subrepo
+                    .git_repo
+                    .refname_to_id(&format!("refs/heads/{}", branch_name))
+                {
+                    pushed_tips.insert(config_repo.path.clone(), oid);
+                }
+            },
+            Err(e) => problems.push((config_repo.path.display().to_string(), e)),
+        }
+    }
 
-    for head in connection.list()?.iter() {
-        if head.name() == remote_branch_ref {
-            remote_oid = Some(head.oid());
-            break;
+    let mut missing = vec![];
+    if include_umbrella {
+        if let Err(e) = check_pushable(umbrella, branch_name, force_with_lease) {
+            problems.push((String::from("umbrella"), e));
         }
+        missing = missing_gitlinks(umbrella, branch_name, &pushed_tips)?;
+    }
+
+    if problems.is_empty() && missing.is_empty() {
+        return Ok(());
     }
 
-    drop(connection);
+    for (label, e) in &problems {
+        writeln!(stdout, "- '{}': cannot be pushed - {}", label, e)?;
+    }
+    if !missing.is_empty() {
+        writeln!(
+            stdout,
+            "- 'umbrella': references commits that would be missing from subrepo \
+             remotes:"
+        )?;
+        write_missing_gitlinks(stdout, &missing)?;
+    }
+    bail!(
+        "Cannot push to '{}'; no repositories were pushed",
+        branch_name
+    )
+}
 
-    // If remote branch doesn't exist, we need to push
-    let remote_oid = match remote_oid {
-        Some(oid) => oid,
-        None => return Ok(true), // New branch, push needed
+/// Check that pushing `branch_name` of `repo` would not be rejected.
+fn check_pushable(
+    repo: &repo::Repo,
+    branch_name: &str,
+    force_with_lease: bool,
+) -> Result<()> {
+    let remote_name = repo.get_remote_name_for_branch(branch_name)?;
+    let mut remote = match repo.git_repo.find_remote(&remote_name) {
+        Ok(remote) => remote,
+        // Repos without a remote are skipped when pushing
+        Err(_) => return Ok(()),
     };
 
-    // If OIDs match, no push needed
-    if local_oid == remote_oid {
-        return Ok(false);
+    let local_oid = local_branch_oid(repo, branch_name)?;
+    let remote_oid = remote_branch_oid(repo, &mut remote, branch_name)?;
+    match rejection(
+        repo,
+        &remote_name,
+        branch_name,
+        local_oid,
+        remote_oid,
+        force_with_lease,
+    )? {
+        Some(reason) => bail!(reason),
+        None => Ok(()),
     }
+}
 
-    // Check if local is ahead of remote (can fast-forward)
-    // If remote is ahead or diverged, we still return true because
-    // git push will fail with proper error message
-    Ok(true)
+fn local_branch_oid(repo: &repo::Repo, branch_name: &str) -> Result<git2::Oid> {
+    repo.git_repo
+        .refname_to_id(&format!("refs/heads/{}", branch_name))
+        .map_err(|_| anyhow!("Branch '{}' does not exist locally", branch_name))
+}
+
+/// Commit the branch points to on the remote, if the remote has the branch.
+fn remote_branch_oid(
+    repo: &repo::Repo,
+    remote: &mut git2::Remote,
+    branch_name: &str,
+) -> Result<Option<git2::Oid>> {
+    let remote_branch_ref = format!("refs/heads/{}", branch_name);
+    Ok(remote_heads(repo, remote, git2::Direction::Push)?
+        .into_iter()
+        .find(|(name, _)| *name == remote_branch_ref)
+        .map(|(_, oid)| oid))
+}
+
+/// Why pushing `local_oid` over `remote_oid` would be rejected, if it would.
+///
+/// A plain push must fast-forward the remote branch. A push with
+/// `force_with_lease` may rewrite it, but only if the remote branch is still
+/// where the local remote-tracking ref says it is.
+fn rejection(
+    repo: &repo::Repo,
+    remote_name: &str,
+    branch_name: &str,
+    local_oid: git2::Oid,
+    remote_oid: Option<git2::Oid>,
+    force_with_lease: bool,
+) -> Result<Option<String>> {
+    if force_with_lease {
+        if remote_oid != lease_oid(repo, remote_name, branch_name)? {
+            return Ok(Some(lease_broken(remote_name, branch_name)));
+        }
+        return Ok(None);
+    }
+
+    match remote_oid {
+        Some(remote_oid) if !contains(repo, local_oid, remote_oid) => {
+            Ok(Some(format!(
+                "'{}' on '{}' has commits that are not in the local branch",
+                branch_name, remote_name
+            )))
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Commit a force-push with lease expects the remote branch to be on: that of
+/// its remote-tracking branch, if any.
+fn lease_oid(
+    repo: &repo::Repo,
+    remote_name: &str,
+    branch_name: &str,
+) -> Result<Option<git2::Oid>> {
+    let tracking_ref = match repo.tracking_branch(branch_name)? {
+        Some(tracking) if tracking.remote == remote_name => tracking.remote_ref,
+        _ => format!("refs/remotes/{}/{}", remote_name, branch_name),
+    };
+    Ok(repo.git_repo.refname_to_id(&tracking_ref).ok())
+}
+
+fn lease_broken(remote_name: &str, branch_name: &str) -> String {
+    format!(
+        "'{}' on '{}' has changed since it was last fetched",
+        branch_name, remote_name
+    )
 }
 
 fn push_repo(
     repo: &repo::Repo,
     branch_name: &str,
     set_upstream: bool,
+    force_with_lease: bool,
+    dry_run: bool,
 ) -> Result<PushResult> {
     // Get the remote name for this branch
     let remote_name = repo.get_remote_name_for_branch(branch_name)?;
@@ -225,22 +676,32 @@ fn push_repo(
         },
     };
 
-    // Get the current branch reference
-    let branch_ref = format!("refs/heads/{}", branch_name);
+    let local_oid = local_branch_oid(repo, branch_name)?;
 
-    // Check if the branch exists locally
-    if repo.git_repo.refname_to_id(&branch_ref).is_err() {
-        return Err(anyhow!("Branch '{}' does not exist locally", branch_name));
-    }
-
-    // Check if push is actually needed
-    match needs_push(repo, &mut remote, branch_name) {
-        Ok(false) => {
+    // Check if push is actually needed, and whether it would be accepted
+    match remote_branch_oid(repo, &mut remote, branch_name) {
+        Ok(Some(remote_oid)) if remote_oid == local_oid => {
             // Already up to date, skip the push entirely
             return Ok(PushResult::UpToDate);
         },
-        Ok(true) => {
-            // Push is needed, continue
+        Ok(remote_oid) => {
+            if let Some(reason) = rejection(
+                repo,
+                &remote_name,
+                branch_name,
+                local_oid,
+                remote_oid,
+                force_with_lease,
+            )? {
+                return Ok(PushResult::Rejected(reason));
+            }
+        },
+        Err(e) if force_with_lease => {
+            // Never force-push without knowing what gets overwritten
+            return Err(e.context(format!(
+                "Cannot check '{}' on '{}' before force-pushing",
+                branch_name, remote_name
+            )));
         },
         Err(e) => {
             // If we can't check remote state (e.g., network issue),
@@ -250,14 +711,53 @@ fn push_repo(
         },
     }
 
+    if dry_run {
+        return Ok(PushResult::Planned);
+    }
+
     // Prepare the refspec for pushing
-    let refspec = format!("{}:refs/heads/{}", branch_ref, branch_name);
+    let refspec = push_refspec(branch_name, force_with_lease);
+
+    // The remote reports rejected refs through a callback rather than an error
+    let rejected = Arc::new(Mutex::new(None));
+    let mut callbacks = repo.remote_callbacks()?;
+    let rejected_status = Arc::clone(&rejected);
+    callbacks.push_update_reference(move |_, status| {
+        if let Some(message) = status {
+            *rejected_status.lock().unwrap() = Some(message.to_owned());
+        }
+        Ok(())
+    });
+    if force_with_lease {
+        // The remote may have moved since it was checked above, so the lease
+        // is checked again against the ref the remote advertises for this push
+        let expected_oid =
+            lease_oid(repo, &remote_name, branch_name)?.unwrap_or_else(git2::Oid::zero);
+        let branch_ref = format!("refs/heads/{}", branch_name);
+        let reason = lease_broken(&remote_name, branch_name);
+        let rejected_lease = Arc::clone(&rejected);
+        callbacks.push_negotiation(move |updates| {
+            let broken = updates.iter().any(|update| {
+                update.dst_refname() == Some(branch_ref.as_str())
+                    && update.src() != expected_oid
+            });
+            if broken {
+                *rejected_lease.lock().unwrap() = Some(reason.clone());
+                return Err(git2::Error::from_str(&reason));
+            }
+            Ok(())
+        });
+    }
 
     // Perform the push
     let mut push_options = git2::PushOptions::new();
-    push_options.remote_callbacks(repo.remote_callbacks()?);
+    push_options.remote_callbacks(callbacks);
 
-    match remote.push(&[&refspec], Some(&mut push_options)) {
+    let pushed = remote.push(&[&refspec], Some(&mut push_options));
+    if let Some(message) = rejected.lock().unwrap().take() {
+        return Ok(PushResult::Rejected(message));
+    }
+    match pushed {
         Ok(_) => {
             if set_upstream {
                 // Set the upstream branch
@@ -269,10 +769,10 @@ fn push_repo(
         },
         Err(e) => {
             // Check if it's an "up to date" error
-            if e.message().contains("up to date")
-                || e.message().contains("non-fast-forward")
-            {
+            if e.message().contains("up to date") {
                 Ok(PushResult::UpToDate)
+            } else if e.message().contains("non-fast-forward") {
+                Ok(PushResult::Rejected(e.message().to_owned()))
             } else {
                 Err(e.into())
             }
@@ -1,5 +1,6 @@
 use anyhow::*;
 use std::path;
+use std::result::Result::Ok;
 
 use crate::{config, repo};
 
@@ -33,3 +34,128 @@ pub fn add(
     );
     Ok(true)
 }
+
+/// Clone `url` as a new submodule at `submodule_path`, record it in the wok file
+/// and commit both to the umbrella repo.
+///
+/// The subrepo is checked out on `branch`, or on the remote's default branch
+/// when not given, which also becomes the `branch` tracked in `.gitmodules`.
+/// The wok file is saved before committing, so there is nothing left for the
+/// caller to save.
+pub fn add_from_url(
+    wok_config: &mut config::Config,
+    umbrella: &repo::Repo,
+    config_path: &path::Path,
+    url: &str,
+    submodule_path: &path::Path,
+    branch: Option<&str>,
+) -> Result<()> {
+    if wok_config
+        .repos
+        .iter()
+        .any(|config_repo| config_repo.path == submodule_path)
+    {
+        bail!(
+            "Subrepo at `{}` is already in the wok file",
+            submodule_path.display()
+        );
+    }
+
+    let subrepo_path = umbrella.work_dir.join(submodule_path);
+    if subrepo_path.exists()
+        && subrepo_path
+            .read_dir()
+            .with_context(|| {
+                format!("Cannot read directory `{}`", subrepo_path.display())
+            })?
+            .next()
+            .is_some()
+    {
+        bail!(
+            "Destination `{}` already exists and is not empty",
+            subrepo_path.display()
+        );
+    }
+
+    println!("Cloning '{}' into `{}`...", url, subrepo_path.display());
+
+    let mut submodule = umbrella
+        .git_repo
+        .submodule(url, submodule_path, true)
+        .with_context(|| {
+            format!("Cannot add submodule at `{}`", submodule_path.display())
+        })?;
+
+    let mut fetch_options = git2::FetchOptions::new();
+    fetch_options.remote_callbacks(repo::default_remote_callbacks()?);
+    let mut update_options = git2::SubmoduleUpdateOptions::new();
+    update_options.fetch(fetch_options);
+
+    let subrepo_git = submodule
+        .clone(Some(&mut update_options))
+        .with_context(|| format!("Cannot clone submodule from '{}'", url))?;
+
+    let head = match branch {
+        Some(branch) => {
+            checkout_remote_branch(&subrepo_git, branch)?;
+            String::from(branch)
+        },
+        None => String::from(
+            subrepo_git
+                .head()
+                .context("Cloned repo has no default branch")?
+                .shorthand()
+                .context("Cannot read the default branch of the cloned repo")?,
+        ),
+    };
+
+    let name = submodule
+        .name()
+        .context("Invalid submodule name")?
+        .to_owned();
+    git2::Config::open(&umbrella.work_dir.join(".gitmodules"))?
+        .set_str(&format!("submodule.{}.branch", name), &head)?;
+
+    // Stages `.gitmodules` and the gitlink of the checked out commit
+    submodule.add_finalize()?;
+
+    wok_config.add_repo(submodule_path, &head);
+    super::commit_with_config(
+        wok_config,
+        umbrella,
+        config_path,
+        &format!("Add subrepo {}", submodule_path.display()),
+    )?;
+
+    println!(
+        "Added subrepo at `{}` with head `{}`",
+        subrepo_path.display(),
+        head
+    );
+    Ok(())
+}
+
+/// Check out `branch_name` of a fresh clone, tracking the remote branch.
+fn checkout_remote_branch(
+    git_repo: &git2::Repository,
+    branch_name: &str,
+) -> Result<()> {
+    let upstream = format!("origin/{}", branch_name);
+    let remote_branch = git_repo
+        .find_branch(&upstream, git2::BranchType::Remote)
+        .with_context(|| {
+            format!("Cannot find branch '{}' on the remote", branch_name)
+        })?;
+    let commit = remote_branch.get().peel_to_commit()?;
+
+    let mut branch = match git_repo.find_branch(branch_name, git2::BranchType::Local) {
+        Ok(branch) => branch,
+        Err(_) => git_repo.branch(branch_name, &commit, false)?,
+    };
+    branch.set_upstream(Some(&upstream))?;
+
+    git_repo.set_head(&format!("refs/heads/{}", branch_name))?;
+    git_repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+
+    Ok(())
+}
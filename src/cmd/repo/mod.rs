@@ -1,5 +1,50 @@
 mod add;
 mod rm;
 
-pub use add::add;
-pub use rm::rm;
+pub use add::{add, add_from_url};
+pub use rm::{rm, rm_purge};
+
+use anyhow::*;
+use std::path;
+use std::result::Result::Ok;
+
+use crate::{config, repo};
+
+/// Save the wok file and commit it together with the already staged changes to
+/// the umbrella repo.
+fn commit_with_config(
+    wok_config: &config::Config,
+    umbrella: &repo::Repo,
+    config_path: &path::Path,
+    message: &str,
+) -> Result<()> {
+    wok_config.save(config_path)?;
+
+    let mut index = umbrella.git_repo.index()?;
+    // Git may have changed the index behind our back
+    index.read(true)?;
+    if let Ok(config_rel_path) = config_path.strip_prefix(&umbrella.work_dir) {
+        index.add_path(config_rel_path)?;
+
+        // Keep a committed lock file in step with the wok file
+        let lock_rel_path = config::Lock::path_for(config_rel_path);
+        if wok_config.lock.is_some() && index.get_path(&lock_rel_path, 0).is_some() {
+            index.add_path(&lock_rel_path)?;
+        }
+    }
+    index.write()?;
+
+    let signature = umbrella.git_repo.signature()?;
+    let tree = umbrella.git_repo.find_tree(index.write_tree()?)?;
+    let parent_commit = umbrella.git_repo.head()?.peel_to_commit()?;
+    umbrella.git_repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &[&parent_commit],
+    )?;
+
+    Ok(())
+}
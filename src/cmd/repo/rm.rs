@@ -1,7 +1,11 @@
 use anyhow::*;
+use std::ffi::OsStr;
+use std::fs;
 use std::path;
+use std::result::Result::Ok;
 
-use crate::config;
+use crate::cmd::{assemble, status};
+use crate::{config, repo};
 
 pub fn rm(
     wok_config: &mut config::Config,
@@ -15,3 +19,96 @@ pub fn rm(
     println!("Removed subrepo at `{}`", submodule_path.display());
     Ok(true)
 }
+
+/// Remove the subrepo at `submodule_path` from the wok file and from git as
+/// well: deinitialize the submodule, drop its gitlink and `.gitmodules` section
+/// and commit the result to the umbrella repo.
+///
+/// The submodule's git dir is kept in `.git/modules` unless `delete_git_dir`
+/// is set. Refuses to remove a subrepo with uncommitted changes or with commits
+/// not on any remote unless `force` is set.
+pub fn rm_purge(
+    wok_config: &mut config::Config,
+    umbrella: &repo::Repo,
+    config_path: &path::Path,
+    submodule_path: &path::Path,
+    delete_git_dir: bool,
+    force: bool,
+) -> Result<()> {
+    let submodule = umbrella
+        .git_repo
+        .find_submodule(&submodule_path.to_string_lossy())
+        .with_context(|| {
+            format!("Cannot find submodule at `{}`", submodule_path.display())
+        })?;
+    let name = submodule
+        .name()
+        .context("Invalid submodule name")?
+        .to_owned();
+
+    if !force && let Ok(subrepo_git) = submodule.open() {
+        if !status::is_repo_clean(&subrepo_git, None)? {
+            bail!(
+                "Subrepo at `{}` has uncommitted changes; use --force to remove it anyway",
+                submodule_path.display()
+            );
+        }
+        if has_unpushed_commits(&subrepo_git)? {
+            bail!(
+                "Subrepo at `{}` has commits not pushed to any remote; use --force to \
+                 remove it anyway",
+                submodule_path.display()
+            );
+        }
+    }
+    drop(submodule);
+
+    // Move a git dir inside the work tree to `.git/modules` first, otherwise
+    // removing the work tree would take the history with it
+    for args in [
+        ["submodule", "absorbgitdirs", "--"],
+        ["submodule", "deinit", "--force"],
+        ["rm", "--force", "--"],
+    ] {
+        assemble::run_git(
+            &umbrella.work_dir,
+            args.iter()
+                .map(OsStr::new)
+                .chain([submodule_path.as_os_str()]),
+        )
+        .with_context(|| {
+            format!("Cannot remove submodule at `{}`", submodule_path.display())
+        })?;
+    }
+
+    let git_dir = umbrella.git_repo.path().join("modules").join(&name);
+    if delete_git_dir && git_dir.exists() {
+        fs::remove_dir_all(&git_dir).with_context(|| {
+            format!("Cannot delete git dir at `{}`", git_dir.display())
+        })?;
+    }
+
+    wok_config.remove_repo(submodule_path);
+    super::commit_with_config(
+        wok_config,
+        umbrella,
+        config_path,
+        &format!("Remove subrepo {}", submodule_path.display()),
+    )?;
+
+    println!("Purged subrepo at `{}`", submodule_path.display());
+    Ok(())
+}
+
+/// Whether any local branch or a detached HEAD has commits that no remote
+/// tracking branch contains.
+fn has_unpushed_commits(git_repo: &git2::Repository) -> Result<bool> {
+    let mut revwalk = git_repo.revwalk()?;
+    revwalk.push_glob("refs/heads")?;
+    if git_repo.head_detached()? {
+        revwalk.push_head()?;
+    }
+    revwalk.hide_glob("refs/remotes")?;
+
+    Ok(revwalk.next().is_some())
+}
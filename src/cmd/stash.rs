@@ -0,0 +1,197 @@
+use anyhow::*;
+use clap::ValueEnum;
+use std::io::Write;
+use std::path;
+use std::result::Result::Ok;
+
+use crate::repo;
+
+/// What to do with the stashes made by `--autostash`.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum StashAction {
+    /// List the stashes in every subrepo
+    List,
+    /// Apply and drop the newest stash in every subrepo
+    Pop,
+    /// Drop the newest stash in every subrepo
+    Drop,
+}
+
+/// Manage the stashes that `--autostash` left behind across the workspace.
+pub fn stash<W: Write>(
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+    action: StashAction,
+) -> Result<()> {
+    let mut found = false;
+    let mut failed = 0;
+
+    for subrepo in &umbrella.subrepos {
+        let label = subrepo
+            .work_dir
+            .strip_prefix(&umbrella.work_dir)
+            .unwrap_or(&subrepo.work_dir)
+            .display()
+            .to_string();
+        let stashes = subrepo.wok_stashes()?;
+
+        match action {
+            StashAction::List => {
+                for stash in &stashes {
+                    writeln!(
+                        stdout,
+                        "- '{}': stash@{{{}}}: {}",
+                        label, stash.index, stash.message
+                    )?;
+                }
+            },
+            StashAction::Pop => {
+                if let Some(stash) = stashes.first() {
+                    match subrepo.pop_stash(stash.index) {
+                        Ok(conflicts) if conflicts.is_empty() => writeln!(
+                            stdout,
+                            "- '{}': applied and dropped stash@{{{}}}",
+                            label, stash.index
+                        )?,
+                        Ok(conflicts) => {
+                            failed += 1;
+                            writeln!(
+                                stdout,
+                                "- '{}': stash@{{{}}} conflicts in {}; resolve them and \
+                                 run `wok stash drop`",
+                                label,
+                                stash.index,
+                                quoted(&conflicts)
+                            )?;
+                        },
+                        Err(e) => {
+                            failed += 1;
+                            writeln!(stdout, "- '{}': {:#}", label, e)?;
+                        },
+                    }
+                }
+            },
+            StashAction::Drop => {
+                if let Some(stash) = stashes.first() {
+                    match subrepo.drop_stash(stash.index) {
+                        Ok(()) => writeln!(
+                            stdout,
+                            "- '{}': dropped stash@{{{}}}",
+                            label, stash.index
+                        )?,
+                        Err(e) => {
+                            failed += 1;
+                            writeln!(stdout, "- '{}': {:#}", label, e)?;
+                        },
+                    }
+                }
+            },
+        }
+
+        found |= !stashes.is_empty();
+    }
+
+    if !found {
+        writeln!(stdout, "No stashes made by wok")?;
+    }
+    if failed > 0 {
+        let verb = match action {
+            StashAction::Drop => "drop",
+            _ => "apply",
+        };
+        bail!("Cannot {} the stashes of {} repositories", verb, failed);
+    }
+
+    Ok(())
+}
+
+/// What happened to the changes stashed around an operation.
+pub(crate) enum Autostash {
+    Restored,
+    /// The changes were re-applied with conflicts in these paths, and are
+    /// still stashed
+    Conflicts(Vec<path::PathBuf>),
+    /// The changes could not be re-applied and are still stashed
+    Kept(String),
+}
+
+impl Autostash {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Autostash::Restored => "restored",
+            Autostash::Conflicts(_) => "conflicts",
+            Autostash::Kept(_) => "kept",
+        }
+    }
+}
+
+/// Run `operation` on `repo`, stashing uncommitted changes first and
+/// re-applying them afterwards if `enabled`. Returns what happened to the
+/// stash, if one was made.
+pub(crate) fn with_autostash<T>(
+    repo: &repo::Repo,
+    enabled: bool,
+    description: &str,
+    operation: impl FnOnce() -> Result<T>,
+) -> Result<(T, Option<Autostash>)> {
+    if !enabled {
+        return Ok((operation()?, None));
+    }
+
+    let stash_oid = match repo.autostash(description)? {
+        Some(oid) => oid,
+        None => return Ok((operation()?, None)),
+    };
+
+    let result = operation();
+    let autostash = match repo.unstash(stash_oid) {
+        Ok(conflicts) if conflicts.is_empty() => Autostash::Restored,
+        Ok(conflicts) => Autostash::Conflicts(conflicts),
+        Err(e) => Autostash::Kept(format!("{:#}", e)),
+    };
+
+    match (result, autostash) {
+        (Ok(value), autostash) => Ok((value, Some(autostash))),
+        (Err(e), Autostash::Restored) => Err(e),
+        (Err(e), _) => Err(anyhow!(
+            "{:#}; stashed changes were kept, see `wok stash list`",
+            e
+        )),
+    }
+}
+
+/// Report what happened to the changes stashed in the repo at `path`.
+pub(crate) fn write_autostash<W: Write>(
+    stdout: &mut W,
+    path: &path::Path,
+    autostash: &Autostash,
+) -> Result<()> {
+    match autostash {
+        Autostash::Restored => {
+            writeln!(stdout, "- '{}': re-applied stashed changes", path.display())?
+        },
+        Autostash::Conflicts(conflicts) => writeln!(
+            stdout,
+            "- '{}': stashed changes conflict in {}; resolve them and run `wok \
+             stash drop`",
+            path.display(),
+            quoted(conflicts)
+        )?,
+        Autostash::Kept(reason) => writeln!(
+            stdout,
+            "- '{}': kept stashed changes - {}; run `wok stash pop` once the repo \
+             is clean",
+            path.display(),
+            reason
+        )?,
+    }
+    Ok(())
+}
+
+fn quoted(paths: &[path::PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("'{}'", path.display()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -1,23 +1,56 @@
 use anyhow::*;
+use serde::Serialize;
+use std::fmt;
 use std::io::Write;
 
-use crate::{config, repo};
+use crate::{config, output, repo};
 
 pub fn status<W: Write>(
     wok_config: &mut config::Config,
     umbrella: &repo::Repo,
     stdout: &mut W,
     fetch: bool,
-) -> Result<()> {
+    check: bool,
+    jobs: usize,
+    groups: &[String],
+) -> Result<output::Report> {
+    let mut report = output::Report::new("status");
+
     // Fetch from remotes if requested
     if fetch {
         umbrella.fetch()?;
-        for config_repo in &wok_config.repos {
-            if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
+    }
+
+    let repos_to_show =
+        wok_config.select_repos("status", &umbrella.head, true, groups, &[]);
+
+    let subrepos: Vec<(&config::Repo, &repo::Repo)> = repos_to_show
+        .iter()
+        .filter_map(|config_repo| {
+            umbrella
+                .get_subrepo_by_path(&config_repo.path)
+                .map(|subrepo| (config_repo, subrepo))
+        })
+        .collect();
+
+    let subrepo_statuses =
+        super::parallel::for_each_repo(&subrepos, jobs, |_, subrepo| {
+            if fetch {
                 subrepo.fetch()?;
             }
-        }
-    }
+
+            let clean = is_repo_clean(&subrepo.git_repo, None)?;
+            let remote_status = get_remote_status(subrepo, &subrepo.head)?;
+            let head_oid = subrepo
+                .git_repo
+                .head()
+                .ok()
+                .and_then(|head| head.peel_to_commit().ok())
+                .map(|commit| commit.id());
+            Ok((clean, remote_status, head_oid))
+        })
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
 
     // Check if umbrella repo is clean
     let umbrella_clean = is_repo_clean(&umbrella.git_repo, Some(&wok_config.repos))?;
@@ -25,85 +58,271 @@ pub fn status<W: Write>(
     let clean_status = if umbrella_clean { "all clean" } else { "dirty" };
 
     // Get remote status for umbrella
-    let remote_status = get_remote_status_string(umbrella, &umbrella.head)?;
+    let remote_status = get_remote_status(umbrella, &umbrella.head)?;
 
     writeln!(
         stdout,
         "{} (umbrella) on branch '{}', {}{}",
-        umbrella_emoji, &umbrella.head, clean_status, remote_status
+        umbrella_emoji,
+        &umbrella.head,
+        clean_status,
+        format_remote_status(remote_status.as_ref())
     )?;
 
+    report.set_umbrella("branch", &umbrella.head);
+    report.set_umbrella("clean", umbrella_clean);
+    report.set_umbrella("remote", &remote_status);
+
+    let mut all_clean = umbrella_clean;
+    let mut violations = Vec::new();
+
+    if check {
+        let umbrella_violations =
+            check_repo(umbrella_clean, remote_status.as_ref(), None, None);
+        report.set_umbrella("violations", violation_kinds(&umbrella_violations));
+        violations.extend(
+            umbrella_violations
+                .into_iter()
+                .map(|violation| (String::from("umbrella"), violation)),
+        );
+    }
+
+    // Gitlinks are compared against the committed umbrella state
+    let umbrella_tree = umbrella
+        .git_repo
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_tree().ok());
+
     // Show status for each configured subrepo
-    for config_repo in &wok_config.repos {
-        if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
-            let subrepo_clean = is_repo_clean(&subrepo.git_repo, None)?;
-            let subrepo_emoji = if subrepo_clean { "✓" } else { "✗" };
-            let subrepo_clean_status =
-                if subrepo_clean { "all clean" } else { "dirty" };
-            let subrepo_remote_status =
-                get_remote_status_string(subrepo, &subrepo.head)?;
-
-            writeln!(
-                stdout,
-                "{} '{}' on branch '{}', {}{}",
-                subrepo_emoji,
-                config_repo.path.display(),
-                &subrepo.head,
-                subrepo_clean_status,
-                subrepo_remote_status
-            )?;
+    for ((config_repo, subrepo), (subrepo_clean, subrepo_remote_status, head_oid)) in
+        subrepos.iter().zip(subrepo_statuses)
+    {
+        let subrepo_emoji = if subrepo_clean { "✓" } else { "✗" };
+        let subrepo_clean_status = if subrepo_clean { "all clean" } else { "dirty" };
+
+        writeln!(
+            stdout,
+            "{} '{}' on branch '{}', {}{}",
+            subrepo_emoji,
+            config_repo.path.display(),
+            &subrepo.head,
+            subrepo_clean_status,
+            format_remote_status(subrepo_remote_status.as_ref())
+        )?;
+
+        report.set(&config_repo.path, "branch", &subrepo.head);
+        report.set(&config_repo.path, "clean", subrepo_clean);
+        report.set(&config_repo.path, "remote", &subrepo_remote_status);
+        all_clean &= subrepo_clean;
+
+        if check {
+            let gitlink_oid = umbrella_tree
+                .as_ref()
+                .and_then(|tree| tree.get_path(&config_repo.path).ok())
+                .filter(|entry| entry.kind() == Some(git2::ObjectType::Commit))
+                .map(|entry| entry.id());
+            let gitlink = match (head_oid, gitlink_oid) {
+                (Some(head_oid), Some(gitlink_oid)) => Some((head_oid, gitlink_oid)),
+                _ => None,
+            };
+            let repo_violations = check_repo(
+                subrepo_clean,
+                subrepo_remote_status.as_ref(),
+                Some((&subrepo.head, &config_repo.head)),
+                gitlink,
+            );
+            report.set(
+                &config_repo.path,
+                "violations",
+                violation_kinds(&repo_violations),
+            );
+            violations.extend(
+                repo_violations.into_iter().map(|violation| {
+                    (config_repo.path.display().to_string(), violation)
+                }),
+            );
         }
     }
 
-    Ok(())
+    report.summarize("repos", subrepos.len());
+    report.summarize("clean", all_clean);
+
+    if check {
+        if violations.is_empty() {
+            writeln!(stdout, "Check passed")?;
+        } else {
+            writeln!(stdout, "Check failed:")?;
+            for (label, violation) in &violations {
+                writeln!(stdout, "- '{}': {}", label, violation)?;
+            }
+        }
+
+        report.exit_code = violations
+            .iter()
+            .fold(0, |code, (_, violation)| code | violation.exit_code());
+        report.summarize("violations", violations.len());
+    }
+
+    Ok(report)
 }
 
-fn get_remote_status_string(
+/// A reason for `status --check` to fail.
+#[derive(Debug, Clone, PartialEq)]
+enum Violation {
+    Dirty,
+    WrongBranch { current: String, configured: String },
+    BehindRemote(String),
+    GitlinkMismatch { head: git2::Oid, gitlink: git2::Oid },
+}
+
+impl Violation {
+    /// Exit code bit for the violation. Codes of several violations are
+    /// combined, leaving `1` for ordinary errors.
+    fn exit_code(&self) -> i32 {
+        match self {
+            Violation::Dirty => 2,
+            Violation::WrongBranch { .. } => 4,
+            Violation::BehindRemote(_) => 8,
+            Violation::GitlinkMismatch { .. } => 16,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Violation::Dirty => "dirty",
+            Violation::WrongBranch { .. } => "wrong_branch",
+            Violation::BehindRemote(_) => "behind_remote",
+            Violation::GitlinkMismatch { .. } => "gitlink_mismatch",
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Dirty => write!(f, "has uncommitted changes"),
+            Violation::WrongBranch {
+                current,
+                configured,
+            } => write!(
+                f,
+                "on branch '{}' instead of configured head '{}'",
+                current, configured
+            ),
+            Violation::BehindRemote(remote_status) => {
+                write!(f, "{}", remote_status.trim_start_matches(", "))
+            },
+            Violation::GitlinkMismatch { head, gitlink } => write!(
+                f,
+                "HEAD {} differs from {} locked in the umbrella",
+                &head.to_string()[..8],
+                &gitlink.to_string()[..8]
+            ),
+        }
+    }
+}
+
+fn check_repo(
+    clean: bool,
+    remote_status: Option<&RemoteStatus>,
+    branches: Option<(&str, &str)>,
+    gitlink: Option<(git2::Oid, git2::Oid)>,
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    if !clean {
+        violations.push(Violation::Dirty);
+    }
+
+    if let Some((current, configured)) = branches
+        && current != configured
+    {
+        violations.push(Violation::WrongBranch {
+            current: current.to_string(),
+            configured: configured.to_string(),
+        });
+    }
+
+    if let Some(status) = remote_status
+        && matches!(
+            status.comparison,
+            repo::RemoteComparison::Behind(_) | repo::RemoteComparison::Diverged(_, _)
+        )
+    {
+        violations.push(Violation::BehindRemote(format_remote_status(Some(status))));
+    }
+
+    if let Some((head, gitlink)) = gitlink
+        && head != gitlink
+    {
+        violations.push(Violation::GitlinkMismatch { head, gitlink });
+    }
+
+    violations
+}
+
+fn violation_kinds(violations: &[Violation]) -> Vec<&'static str> {
+    violations.iter().map(Violation::kind).collect()
+}
+
+/// Comparison of a branch with its upstream, e.g. `origin/main`.
+#[derive(Serialize)]
+struct RemoteStatus {
+    upstream: String,
+    #[serde(flatten)]
+    comparison: repo::RemoteComparison,
+}
+
+fn get_remote_status(
     repo_obj: &repo::Repo,
     branch_name: &str,
-) -> Result<String> {
+) -> Result<Option<RemoteStatus>> {
     let tracking = match repo_obj.tracking_branch(branch_name)? {
         Some(tracking) => tracking,
-        None => return Ok(String::new()), // No tracking branch, no status to show
+        None => return Ok(None), // No tracking branch, no status to show
     };
 
     match repo_obj.get_remote_comparison(branch_name)? {
-        Some(repo::RemoteComparison::UpToDate) => Ok(format!(
-            ", up to date with '{}'",
-            tracking.remote_ref.replace("refs/remotes/", "")
-        )),
-        Some(repo::RemoteComparison::Ahead(count)) => {
-            let commits = if count == 1 { "commit" } else { "commits" };
-            Ok(format!(
-                ", ahead of '{}' by {} {}",
-                tracking.remote_ref.replace("refs/remotes/", ""),
-                count,
-                commits
-            ))
+        // Remote branch doesn't exist, don't show anything
+        Some(repo::RemoteComparison::NoRemote) | None => Ok(None),
+        Some(comparison) => Ok(Some(RemoteStatus {
+            upstream: tracking.remote_ref.replace("refs/remotes/", ""),
+            comparison,
+        })),
+    }
+}
+
+fn format_remote_status(remote_status: Option<&RemoteStatus>) -> String {
+    let RemoteStatus {
+        upstream,
+        comparison,
+    } = match remote_status {
+        Some(remote_status) => remote_status,
+        None => return String::new(),
+    };
+
+    match comparison {
+        repo::RemoteComparison::UpToDate => {
+            format!(", up to date with '{}'", upstream)
         },
-        Some(repo::RemoteComparison::Behind(count)) => {
-            let commits = if count == 1 { "commit" } else { "commits" };
-            Ok(format!(
-                ", behind '{}' by {} {}",
-                tracking.remote_ref.replace("refs/remotes/", ""),
-                count,
-                commits
-            ))
+        repo::RemoteComparison::Ahead(count) => {
+            let commits = if *count == 1 { "commit" } else { "commits" };
+            format!(", ahead of '{}' by {} {}", upstream, count, commits)
         },
-        Some(repo::RemoteComparison::Diverged(ahead, behind)) => Ok(format!(
-            ", diverged from '{}' ({} ahead, {} behind)",
-            tracking.remote_ref.replace("refs/remotes/", ""),
-            ahead,
-            behind
-        )),
-        Some(repo::RemoteComparison::NoRemote) => {
-            Ok(String::new()) // Remote branch doesn't exist, don't show anything
+        repo::RemoteComparison::Behind(count) => {
+            let commits = if *count == 1 { "commit" } else { "commits" };
+            format!(", behind '{}' by {} {}", upstream, count, commits)
         },
-        None => Ok(String::new()), // No tracking branch
+        repo::RemoteComparison::Diverged(ahead, behind) => format!(
+            ", diverged from '{}' ({} ahead, {} behind)",
+            upstream, ahead, behind
+        ),
+        repo::RemoteComparison::NoRemote => String::new(),
     }
 }
 
-fn is_repo_clean(
+pub(crate) fn is_repo_clean(
     git_repo: &git2::Repository,
     config_repos: Option<&[crate::config::Repo]>,
 ) -> Result<bool> {
@@ -1,20 +1,66 @@
 use anyhow::*;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::io::Write;
 use std::result::Result::Ok;
 
-use crate::{config, repo};
+use super::{dry_run, stash};
+use crate::{config, journal, output, repo};
+
+/// How `switch` switches the repos, and which ones.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SwitchOptions<'a> {
+    /// Create the branch in repos that don't have it
+    pub create: bool,
+    /// Commit or remote branch to create the branch from
+    pub from: Option<&'a str>,
+    /// Fetch a remote `from` before creating the branch
+    pub fetch: bool,
+    /// Switch all configured repos rather than those on the umbrella's branch
+    pub all: bool,
+    /// Branch to switch to, the umbrella's branch if not given
+    pub branch: Option<&'a str>,
+    pub repos: &'a [std::path::PathBuf],
+    pub groups: &'a [String],
+    pub autostash: bool,
+    /// Roll every repo back if one of them fails to switch
+    pub atomic: bool,
+    pub dry_run: bool,
+}
 
 pub fn switch<W: Write>(
     wok_config: &mut config::Config,
     umbrella: &repo::Repo,
     stdout: &mut W,
-    create: bool,
-    all: bool,
-    branch_name: Option<&str>,
-    target_repos: &[std::path::PathBuf],
-) -> Result<bool> {
-    let mut config_updated = false;
+    options: &SwitchOptions,
+) -> Result<output::Report> {
+    let SwitchOptions {
+        create,
+        from,
+        fetch,
+        all,
+        branch: branch_name,
+        repos: target_repos,
+        groups,
+        autostash,
+        atomic,
+        dry_run,
+    } = *options;
+    let mut report = output::Report::new("switch");
+    let atomic = atomic || wok_config.atomic_switch;
+    // A rollback would check out over the changes re-applied from the stashes
+    if atomic && autostash {
+        bail!("--autostash cannot be combined with an atomic switch");
+    }
+    // Repos that were switched, along with their original state and whether
+    // the branch was created, to roll them back in atomic mode
+    let mut switched = Vec::new();
+    // The wok file is only updated once all repos are done
+    let mut switched_paths = Vec::new();
     let mut submodule_changed = false;
+    let mut planned = HashMap::new();
+    let would = if dry_run { "would " } else { "" };
+    let from_note = from.map_or(String::new(), |base| format!(" from '{}'", base));
 
     // Determine the target branch
     let target_branch = match branch_name {
@@ -23,38 +69,12 @@ pub fn switch<W: Write>(
     };
 
     // Determine which repos to switch
-    let repos_to_switch: Vec<config::Repo> = if all {
-        // Switch all configured repos, skipping those opted out unless explicitly targeted
-        wok_config
-            .repos
-            .iter()
-            .filter(|config_repo| {
-                !config_repo.is_skipped_for("switch")
-                    || target_repos.contains(&config_repo.path)
-            })
-            .cloned()
-            .collect()
-    } else if !target_repos.is_empty() {
-        // Switch only specified repos
-        wok_config
-            .repos
-            .iter()
-            .filter(|config_repo| target_repos.contains(&config_repo.path))
-            .cloned()
-            .collect()
-    } else {
-        // Switch repos that match the current main repo branch
-        wok_config
-            .repos
-            .iter()
-            .filter(|config_repo| config_repo.head == umbrella.head)
-            .cloned()
-            .collect()
-    };
+    let repos_to_switch: Vec<config::Repo> =
+        wok_config.select_repos("switch", &umbrella.head, all, groups, target_repos);
 
     if repos_to_switch.is_empty() {
         writeln!(stdout, "No repositories to switch")?;
-        return Ok(config_updated);
+        return Ok(report);
     }
 
     writeln!(
@@ -67,17 +87,53 @@ pub fn switch<W: Write>(
     // Switch each repo
     for config_repo in &repos_to_switch {
         if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
-            match switch_repo(subrepo, &target_branch, create) {
-                Ok(result) => {
-                    config_updated |= wok_config
-                        .set_repo_head(config_repo.path.as_path(), &target_branch);
+            report.set(&config_repo.path, "branch", &target_branch);
+
+            let original =
+                journal::RepoState::capture(&config_repo.path, &subrepo.git_repo).ok();
+            let switch_result = if dry_run {
+                plan_switch(subrepo, &target_branch, create, from).map(
+                    |(result, oid)| {
+                        planned.insert(config_repo.path.clone(), oid);
+                        (result, None)
+                    },
+                )
+            } else {
+                // Changes are only stashed when the repo would actually switch
+                let autostash = autostash && !repo_on_branch(subrepo, &target_branch)?;
+                stash::with_autostash(
+                    subrepo,
+                    autostash,
+                    &format!("switch to '{}'", target_branch),
+                    || switch_repo(subrepo, &target_branch, create, from, fetch),
+                )
+            };
+
+            match switch_result {
+                Ok((result, stashed)) => {
+                    if let Some(original) = original {
+                        switched.push((
+                            subrepo,
+                            original,
+                            matches!(
+                                result,
+                                SwitchResult::Created | SwitchResult::Tracked(_)
+                            ),
+                        ));
+                    }
+                    if !dry_run {
+                        switched_paths.push(config_repo.path.clone());
+                    }
+                    report.set(&config_repo.path, "result", &result);
 
                     match result {
                         SwitchResult::Switched => {
                             writeln!(
                                 stdout,
-                                "- '{}': switched to '{}'",
+                                "- '{}': {}switch{} to '{}'",
                                 config_repo.path.display(),
+                                would,
+                                if dry_run { "" } else { "ed" },
                                 target_branch
                             )?;
                             submodule_changed = true;
@@ -85,9 +141,28 @@ pub fn switch<W: Write>(
                         SwitchResult::Created => {
                             writeln!(
                                 stdout,
-                                "- '{}': created and switched to '{}'",
+                                "- '{}': {} to '{}'{}",
                                 config_repo.path.display(),
-                                target_branch
+                                if dry_run {
+                                    "would create and switch"
+                                } else {
+                                    "created and switched"
+                                },
+                                target_branch,
+                                from_note
+                            )?;
+                            submodule_changed = true;
+                        },
+                        SwitchResult::Tracked(upstream) => {
+                            report.set(&config_repo.path, "upstream", &upstream);
+                            writeln!(
+                                stdout,
+                                "- '{}': {}switch{} to '{}' tracking '{}'",
+                                config_repo.path.display(),
+                                would,
+                                if dry_run { "" } else { "ed" },
+                                target_branch,
+                                upstream
                             )?;
                             submodule_changed = true;
                         },
@@ -100,8 +175,14 @@ pub fn switch<W: Write>(
                             )?;
                         },
                     };
+
+                    if let Some(stashed) = stashed {
+                        report.set(&config_repo.path, "autostash", stashed.name());
+                        stash::write_autostash(stdout, &config_repo.path, &stashed)?;
+                    }
                 },
                 Err(e) => {
+                    report.set(&config_repo.path, "error", e.to_string());
                     writeln!(
                         stdout,
                         "- '{}': failed to switch to '{}' - {}",
@@ -109,12 +190,52 @@ pub fn switch<W: Write>(
                         target_branch,
                         e
                     )?;
+
+                    if atomic {
+                        if let Some(original) = original {
+                            switched.push((subrepo, original, false));
+                        }
+                        if !dry_run {
+                            roll_back(stdout, &switched, &target_branch)?;
+                        }
+                        bail!(
+                            "Cannot switch `{}` to '{}'; no repositories were switched",
+                            config_repo.path.display(),
+                            target_branch
+                        );
+                    }
                 },
             }
         }
     }
 
-    if submodule_changed {
+    for path in &switched_paths {
+        report.config_updated |= wok_config.set_repo_head(path, &target_branch);
+    }
+
+    if dry_run {
+        let changes = dry_run::gitlink_changes(umbrella, &planned, false)?;
+        if changes.is_empty() {
+            writeln!(stdout, "No submodule changes detected; would skip lock")?;
+        } else {
+            let mut changed_submodules = Vec::new();
+            for config_repo in &repos_to_switch {
+                if changes.contains(&config_repo.path)
+                    && planned.contains_key(&config_repo.path)
+                {
+                    changed_submodules.push((
+                        config_repo.path.to_string_lossy().to_string(),
+                        target_branch.clone(),
+                    ));
+                }
+            }
+            dry_run::write_commit_message(
+                stdout,
+                &format_switch_commit_message(&changed_submodules, &target_branch),
+            )?;
+        }
+        report.summarize("dry_run", true);
+    } else if submodule_changed {
         // Perform lock operation on switched repos
         writeln!(stdout, "Locking submodule state...")?;
         lock_switched_repos(umbrella, &repos_to_switch, &target_branch)?;
@@ -133,56 +254,312 @@ pub fn switch<W: Write>(
         )?;
     }
 
-    Ok(config_updated)
+    report.summarize("branch", &target_branch);
+    report.summarize("repos", repos_to_switch.len());
+    report.summarize("locked", submodule_changed);
+
+    Ok(report)
 }
 
 #[derive(Debug, Clone, PartialEq)]
 enum SwitchResult {
     Switched,
     Created,
+    /// The branch was created from the given remote branch, which it tracks
+    Tracked(String),
     AlreadyOnBranch,
 }
 
+impl Serialize for SwitchResult {
+    // Reports carry the upstream of a tracked branch in a field of its own
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(match self {
+            SwitchResult::Switched => "switched",
+            SwitchResult::Created => "created",
+            SwitchResult::Tracked(_) => "tracked",
+            SwitchResult::AlreadyOnBranch => "already_on_branch",
+        })
+    }
+}
+
 fn switch_repo(
     repo: &repo::Repo,
     branch_name: &str,
     create: bool,
+    from: Option<&str>,
+    fetch: bool,
 ) -> Result<SwitchResult> {
+    check_base_applies(repo, branch_name, from)?;
+
     // Check if we're already on the target branch
     if repo_on_branch(repo, branch_name)? {
         return Ok(SwitchResult::AlreadyOnBranch);
     }
 
-    // Try to switch to the branch
-    match repo.switch(branch_name) {
-        Ok(_) => Ok(SwitchResult::Switched),
-        Err(_) => {
-            if create {
-                // Try to create the branch
-                create_and_switch_branch(repo, branch_name)?;
-                Ok(SwitchResult::Created)
-            } else {
-                Err(anyhow!(
-                    "Branch '{}' does not exist and --create not specified",
-                    branch_name
-                ))
-            }
-        },
+    if fetch {
+        for remote_name in repo.git_repo.remotes()?.iter().flatten() {
+            repo.fetch_remote(remote_name)?;
+        }
+    }
+
+    // Switch to the branch if it exists
+    if repo
+        .git_repo
+        .resolve_reference_from_short_name(branch_name)
+        .is_ok()
+    {
+        repo.switch(branch_name)?;
+        return Ok(SwitchResult::Switched);
+    }
+
+    // A branch someone else pushed is checked out rather than created from
+    // scratch, unless an explicit base was given
+    if from.is_none()
+        && let Some(upstream) = remote_branch(repo, branch_name)?
+    {
+        let base = resolve_base(repo, &upstream, false)?;
+        create_and_switch_branch(repo, branch_name, Some(&base))?;
+        return Ok(SwitchResult::Tracked(upstream));
+    }
+
+    if create {
+        // Try to create the branch
+        let base = from
+            .map(|from| resolve_base(repo, from, true))
+            .transpose()?;
+        create_and_switch_branch(repo, branch_name, base.as_ref())?;
+        Ok(SwitchResult::Created)
+    } else {
+        Err(anyhow!(
+            "Branch '{}' does not exist and --create not specified",
+            branch_name
+        ))
+    }
+}
+
+/// Work out what `switch_repo` would do without touching the repo, along with
+/// the commit the subrepo would end up on.
+fn plan_switch(
+    repo: &repo::Repo,
+    branch_name: &str,
+    create: bool,
+    from: Option<&str>,
+) -> Result<(SwitchResult, git2::Oid)> {
+    check_base_applies(repo, branch_name, from)?;
+    let head_oid = repo.git_repo.head()?.peel_to_commit()?.id();
+
+    if repo_on_branch(repo, branch_name)? {
+        return Ok((SwitchResult::AlreadyOnBranch, head_oid));
+    }
+
+    if let Ok(commit) = repo
+        .git_repo
+        .resolve_reference_from_short_name(branch_name)
+        .and_then(|reference| reference.peel_to_commit())
+    {
+        return Ok((SwitchResult::Switched, commit.id()));
+    }
+
+    if from.is_none()
+        && let Some(upstream) = remote_branch(repo, branch_name)?
+    {
+        let oid = resolve_base(repo, &upstream, false)?.oid;
+        return Ok((SwitchResult::Tracked(upstream), oid));
+    }
+
+    match from {
+        _ if !create => Err(anyhow!(
+            "Branch '{}' does not exist and --create not specified",
+            branch_name
+        )),
+        Some(from) => Ok((SwitchResult::Created, resolve_base(repo, from, false)?.oid)),
+        None => Ok((SwitchResult::Created, head_oid)),
+    }
+}
+
+/// Refuse a `from` base for a branch that already exists, rather than
+/// switching to the branch and ignoring the base.
+fn check_base_applies(
+    repo: &repo::Repo,
+    branch_name: &str,
+    from: Option<&str>,
+) -> Result<()> {
+    if let Some(from) = from
+        && repo
+            .git_repo
+            .find_branch(branch_name, git2::BranchType::Local)
+            .is_ok()
+    {
+        bail!(
+            "Branch '{}' already exists, so it can't be created from '{}'",
+            branch_name,
+            from
+        );
+    }
+    Ok(())
+}
+
+/// Put the `switched` repos back to their original state, newest first, and
+/// delete the branches that were created for the switch.
+fn roll_back<W: Write>(
+    stdout: &mut W,
+    switched: &[(&repo::Repo, journal::RepoState, bool)],
+    branch_name: &str,
+) -> Result<()> {
+    for (repo, original, created) in switched.iter().rev() {
+        let current = journal::RepoState::capture(&original.path, &repo.git_repo).ok();
+        if current.is_some_and(|current| current.same_head(original)) {
+            continue;
+        }
+
+        // The tree has to be restored before HEAD moves back, as a safe
+        // checkout of HEAD would leave the switched files in place
+        let commit = repo.git_repo.find_commit(original.oid()?)?;
+        repo.git_repo
+            .checkout_tree(
+                commit.as_object(),
+                Some(git2::build::CheckoutBuilder::new().force()),
+            )
+            .with_context(|| {
+                format!("Cannot roll back repo at `{}`", original.path.display())
+            })?;
+        if original.is_detached() {
+            repo.git_repo.set_head_detached(commit.id())?;
+        } else {
+            repo.git_repo.set_head(&original.head)?;
+        }
+
+        if *created {
+            repo.git_repo
+                .find_branch(branch_name, git2::BranchType::Local)?
+                .delete()?;
+        }
+
+        writeln!(
+            stdout,
+            "- '{}': rolled back to '{}'",
+            original.path.display(),
+            original.branch()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Commit a created branch starts from, and the remote branch it tracks.
+struct Base {
+    oid: git2::Oid,
+    upstream: Option<String>,
+}
+
+/// Resolve `from` to the commit to create a branch from. A remote branch such
+/// as `origin/main` is fetched first if `fetch` is set, and becomes the
+/// upstream of the new branch. Anything else (a local branch, a tag or a
+/// commit) is used as is.
+fn resolve_base(repo: &repo::Repo, from: &str, fetch: bool) -> Result<Base> {
+    let remote_name = repo
+        .git_repo
+        .remotes()?
+        .iter()
+        .flatten()
+        .find(|remote| {
+            from.strip_prefix(remote)
+                .is_some_and(|branch| branch.starts_with('/'))
+        })
+        .map(str::to_owned);
+
+    if let Some(remote_name) = remote_name {
+        if fetch {
+            repo.fetch_remote(&remote_name)?;
+        }
+        if let Ok(reference) = repo
+            .git_repo
+            .find_reference(&format!("refs/remotes/{}", from))
+        {
+            return Ok(Base {
+                oid: reference.peel_to_commit()?.id(),
+                upstream: Some(from.to_owned()),
+            });
+        }
     }
+
+    let commit = repo
+        .git_repo
+        .revparse_single(from)
+        .and_then(|object| object.peel_to_commit())
+        .with_context(|| {
+            format!(
+                "Cannot find '{}' in repo at `{}`",
+                from,
+                repo.work_dir.display()
+            )
+        })?;
+    Ok(Base {
+        oid: commit.id(),
+        upstream: None,
+    })
 }
 
-fn create_and_switch_branch(repo: &repo::Repo, branch_name: &str) -> Result<()> {
-    // Get the current commit
-    let head = repo.git_repo.head()?;
-    let current_commit = head.peel_to_commit()?;
+/// Remote-tracking branch for `branch_name` to create the local branch from,
+/// like `git switch` does. When several remotes have the branch, the default
+/// remote wins.
+fn remote_branch(repo: &repo::Repo, branch_name: &str) -> Result<Option<String>> {
+    let mut candidates = vec![];
+    for remote_name in repo.git_repo.remotes()?.iter().flatten() {
+        let upstream = format!("{}/{}", remote_name, branch_name);
+        if repo
+            .git_repo
+            .find_reference(&format!("refs/remotes/{}", upstream))
+            .is_ok()
+        {
+            candidates.push((remote_name.to_owned(), upstream));
+        }
+    }
+
+    if candidates.len() > 1 {
+        let default_remote = repo.get_remote_name_for_branch(branch_name)?;
+        return match candidates
+            .into_iter()
+            .find(|(remote_name, _)| *remote_name == default_remote)
+        {
+            Some((_, upstream)) => Ok(Some(upstream)),
+            None => Err(anyhow!(
+                "Branch '{}' exists on several remotes; pick one with --create \
+                 --from <REMOTE>/{}",
+                branch_name,
+                branch_name
+            )),
+        };
+    }
+
+    Ok(candidates.pop().map(|(_, upstream)| upstream))
+}
+
+fn create_and_switch_branch(
+    repo: &repo::Repo,
+    branch_name: &str,
+    base: Option<&Base>,
+) -> Result<()> {
+    // Start from the base if there is one, or from the current commit
+    let commit = match base {
+        Some(base) => repo.git_repo.find_commit(base.oid)?,
+        None => repo.git_repo.head()?.peel_to_commit()?,
+    };
 
     // Create the new branch
-    let _branch_ref = repo.git_repo.branch(branch_name, &current_commit, false)?;
+    let mut branch = repo.git_repo.branch(branch_name, &commit, false)?;
+    if let Some(upstream) = base.and_then(|base| base.upstream.as_deref()) {
+        branch.set_upstream(Some(upstream))?;
+    }
 
-    // Switch to the new branch
+    // Switch to the new branch, updating the work tree before HEAD so that
+    // the files of the current commit are replaced
+    repo.git_repo.checkout_tree(commit.as_object(), None)?;
     repo.git_repo
         .set_head(&format!("refs/heads/{}", branch_name))?;
-    repo.git_repo.checkout_head(None)?;
 
     Ok(())
 }
@@ -331,15 +708,23 @@ fn build_switch_commit_message(
         }
     }
 
-    // Build the commit message
+    let message = format_switch_commit_message(&changed_submodules, target_branch);
+    std::result::Result::Ok((message, changed_submodules))
+}
+
+/// Format the switch commit message from the switched submodules and their branch.
+fn format_switch_commit_message(
+    changed_submodules: &[(String, String)],
+    target_branch: &str,
+) -> String {
     let mut message = String::from("Switch and lock submodule state");
 
     if !changed_submodules.is_empty() {
         message.push_str(&format!("\n\nSwitched to '{}':", target_branch));
-        for (name, branch) in &changed_submodules {
+        for (name, branch) in changed_submodules {
             message.push_str(&format!("\n- {}: {}", name, branch));
         }
     }
 
-    std::result::Result::Ok((message, changed_submodules))
+    message
 }
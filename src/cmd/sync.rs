@@ -0,0 +1,214 @@
+use anyhow::*;
+use git2::build::CheckoutBuilder;
+use std::io::Write;
+use std::result::Result::Ok;
+
+use crate::{config, repo};
+
+/// Check out the subrepos at the commits locked in the umbrella repo, or with
+/// `locked` at the branches and commits recorded in the lock file.
+pub fn sync<W: Write>(
+    wok_config: &mut config::Config,
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+    locked: bool,
+) -> Result<()> {
+    let lock_state = if locked {
+        Some(wok_config.lock.as_ref().context(
+            "No lock file found next to the wok file; create one with `wok lock --file`",
+        )?)
+    } else {
+        None
+    };
+    let umbrella_tree = match lock_state {
+        Some(_) => None,
+        None => Some(umbrella.git_repo.head()?.peel_to_tree()?),
+    };
+
+    writeln!(stdout, "Syncing repositories to locked commits...")?;
+
+    let mut refused = 0;
+
+    for config_repo in &wok_config.repos {
+        if config_repo.is_skipped_for("sync") {
+            continue;
+        }
+
+        let subrepo = match umbrella.get_subrepo_by_path(&config_repo.path) {
+            Some(subrepo) => subrepo,
+            None => continue,
+        };
+
+        let target = match (lock_state, &umbrella_tree) {
+            (Some(lock), _) => match lock.get(&config_repo.path) {
+                Some(locked_repo) => {
+                    Some((locked_repo.branch.clone(), locked_repo.oid()?))
+                },
+                None => None,
+            },
+            (None, Some(umbrella_tree)) => {
+                match umbrella_tree.get_path(&config_repo.path) {
+                    Ok(entry) if entry.kind() == Some(git2::ObjectType::Commit) => {
+                        Some((config_repo.head.clone(), entry.id()))
+                    },
+                    _ => None,
+                }
+            },
+            (None, None) => None,
+        };
+
+        let (branch, locked_oid) = match target {
+            Some(target) => target,
+            None => {
+                writeln!(
+                    stdout,
+                    "- '{}': not locked in the {}, skipping",
+                    config_repo.path.display(),
+                    if locked { "lock file" } else { "umbrella" }
+                )?;
+                continue;
+            },
+        };
+
+        let short_oid = &locked_oid.to_string()[..8];
+
+        match sync_repo(subrepo, &branch, locked_oid)? {
+            SyncResult::Synced => {
+                writeln!(
+                    stdout,
+                    "- '{}': synced '{}' to {}",
+                    config_repo.path.display(),
+                    branch,
+                    short_oid
+                )?;
+            },
+            SyncResult::UpToDate => {
+                writeln!(
+                    stdout,
+                    "- '{}': already at {} on '{}'",
+                    config_repo.path.display(),
+                    short_oid,
+                    branch
+                )?;
+            },
+            SyncResult::Dirty => {
+                refused += 1;
+                writeln!(
+                    stdout,
+                    "- '{}': has uncommitted changes, refusing to sync",
+                    config_repo.path.display()
+                )?;
+            },
+            SyncResult::LocalCommits => {
+                refused += 1;
+                writeln!(
+                    stdout,
+                    "- '{}': '{}' has commits not contained in {}, refusing to sync",
+                    config_repo.path.display(),
+                    branch,
+                    short_oid
+                )?;
+            },
+            SyncResult::MissingCommit => {
+                refused += 1;
+                writeln!(
+                    stdout,
+                    "- '{}': locked commit {} is not available, even after fetching",
+                    config_repo.path.display(),
+                    short_oid
+                )?;
+            },
+        }
+    }
+
+    if refused > 0 {
+        bail!(
+            "Cannot sync {} repositories; commit, stash or push local changes first",
+            refused
+        );
+    }
+
+    writeln!(stdout, "Synced repositories to locked commits")?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SyncResult {
+    Synced,
+    UpToDate,
+    Dirty,
+    LocalCommits,
+    MissingCommit,
+}
+
+fn sync_repo(
+    repo: &repo::Repo,
+    branch_name: &str,
+    locked_oid: git2::Oid,
+) -> Result<SyncResult> {
+    let branch_ref = format!("refs/heads/{}", branch_name);
+
+    // The locked commit may come from a teammate and not be fetched yet
+    if repo.git_repo.find_commit(locked_oid).is_err() {
+        repo.fetch()?;
+        if repo.git_repo.find_commit(locked_oid).is_err() {
+            return Ok(SyncResult::MissingCommit);
+        }
+    }
+
+    let branch_oid = repo.git_repo.refname_to_id(&branch_ref).ok();
+    let on_branch = !repo.git_repo.head_detached()?
+        && repo.git_repo.head()?.name() == Some(branch_ref.as_str());
+
+    if branch_oid == Some(locked_oid) && on_branch {
+        return Ok(SyncResult::UpToDate);
+    }
+
+    if !super::status::is_repo_clean(&repo.git_repo, None)? {
+        return Ok(SyncResult::Dirty);
+    }
+
+    if let Some(branch_oid) = branch_oid
+        && would_lose_commits(repo, branch_name, branch_oid, locked_oid)?
+    {
+        return Ok(SyncResult::LocalCommits);
+    }
+
+    repo.git_repo.reference(
+        &branch_ref,
+        locked_oid,
+        true,
+        &format!("wok sync: move '{}' to {}", branch_name, locked_oid),
+    )?;
+    repo.git_repo.set_head(&branch_ref)?;
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force();
+    repo.git_repo.checkout_head(Some(&mut checkout))?;
+
+    Ok(SyncResult::Synced)
+}
+
+/// Commits on the branch are safe to leave behind when the locked commit
+/// contains them or when the remote tracking branch still has them.
+fn would_lose_commits(
+    repo: &repo::Repo,
+    branch_name: &str,
+    branch_oid: git2::Oid,
+    locked_oid: git2::Oid,
+) -> Result<bool> {
+    if branch_oid == locked_oid
+        || repo.git_repo.graph_descendant_of(locked_oid, branch_oid)?
+    {
+        return Ok(false);
+    }
+
+    if let Some(tracking) = repo.tracking_branch(branch_name)?
+        && let Ok(remote_oid) = repo.git_repo.refname_to_id(&tracking.remote_ref)
+        && (remote_oid == branch_oid
+            || repo.git_repo.graph_descendant_of(remote_oid, branch_oid)?)
+    {
+        return Ok(false);
+    }
+
+    Ok(true)
+}
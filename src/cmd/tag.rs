@@ -1,63 +1,95 @@
 use anyhow::*;
+use clap::ValueEnum;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::io::Write;
 use std::panic::{self, AssertUnwindSafe};
+use std::path;
 use std::result::Result::Ok;
 
-use crate::{config, repo};
+use crate::{config, output, repo};
+
+/// How `tag`, `tag_delete` and `tag_bump` treat the tags, and in which repos.
+#[derive(Debug, Clone, Copy)]
+pub struct TagOptions<'a> {
+    /// Sign the created tags
+    pub sign: bool,
+    /// Message of the created tags, which makes them annotated
+    pub message: Option<&'a str>,
+    /// Push the tags to the remotes too
+    pub push: bool,
+    /// Act on all configured repos rather than those on the umbrella's branch
+    pub all: bool,
+    pub include_umbrella: bool,
+    pub repos: &'a [path::PathBuf],
+    /// Number of repos processed at once
+    pub jobs: usize,
+    pub groups: &'a [String],
+    pub dry_run: bool,
+}
+
+impl Default for TagOptions<'_> {
+    fn default() -> Self {
+        TagOptions {
+            sign: false,
+            message: None,
+            push: false,
+            all: false,
+            include_umbrella: true,
+            repos: &[],
+            jobs: 1,
+            groups: &[],
+            dry_run: false,
+        }
+    }
+}
 
-#[allow(clippy::too_many_arguments)]
 pub fn tag<W: Write>(
     wok_config: &mut config::Config,
     umbrella: &repo::Repo,
     stdout: &mut W,
     tag_name: Option<&str>,
-    sign: bool,
-    message: Option<&str>,
-    push: bool,
-    all: bool,
-    include_umbrella: bool,
-    target_repos: &[std::path::PathBuf],
-) -> Result<()> {
+    filter: &TagFilter,
+    options: &TagOptions,
+) -> Result<output::Report> {
+    let TagOptions {
+        sign,
+        message,
+        push,
+        all,
+        include_umbrella,
+        repos: target_repos,
+        jobs,
+        groups,
+        dry_run,
+    } = *options;
+    let mut report = output::Report::new("tag");
+    let tag_kind = tag_kind(sign, message);
+    let prefix = wok_config.tag_prefix.clone().unwrap_or_default();
+    // Tags that don't exist yet are still pushed when planning a dry run
+    let planned_tag = tag_name.filter(|_| dry_run);
+    let mut failed = 0;
+
     // Determine which repos to tag
-    let repos_to_tag: Vec<config::Repo> = if all {
-        // Tag all configured repos, skipping those opted out unless explicitly targeted
-        wok_config
-            .repos
-            .iter()
-            .filter(|config_repo| {
-                !config_repo.is_skipped_for("tag")
-                    || target_repos.contains(&config_repo.path)
-            })
-            .cloned()
-            .collect()
-    } else if !target_repos.is_empty() {
-        // Tag only specified repos
-        wok_config
-            .repos
-            .iter()
-            .filter(|config_repo| target_repos.contains(&config_repo.path))
-            .cloned()
-            .collect()
-    } else {
-        // Tag repos that match the current main repo branch
-        wok_config
-            .repos
-            .iter()
-            .filter(|config_repo| {
-                config_repo.head == umbrella.head && !config_repo.is_skipped_for("tag")
-            })
-            .cloned()
-            .collect()
-    };
+    let repos_to_tag =
+        select_tag_repos(wok_config, umbrella, all, groups, target_repos);
 
     let total_targets = repos_to_tag.len() + usize::from(include_umbrella);
 
     if total_targets == 0 {
         writeln!(stdout, "No repositories to tag")?;
-        return Ok(());
+        return Ok(report);
     }
 
+    let subrepos: Vec<(&config::Repo, &repo::Repo)> = repos_to_tag
+        .iter()
+        .filter_map(|config_repo| {
+            umbrella
+                .get_subrepo_by_path(&config_repo.path)
+                .map(|subrepo| (config_repo, subrepo))
+        })
+        .collect();
+
     match tag_name {
         Some(name) => {
             // Create new tag
@@ -68,61 +100,42 @@ pub fn tag<W: Write>(
             )?;
 
             if include_umbrella {
-                match create_tag(umbrella, name, sign, message) {
-                    Ok(result) => match result {
-                        TagResult::Created => {
-                            writeln!(stdout, "- 'umbrella': created tag '{}'", name)?;
-                        },
-                        TagResult::AlreadyExists => {
-                            writeln!(
-                                stdout,
-                                "- 'umbrella': tag '{}' already exists",
-                                name
-                            )?;
-                        },
-                    },
+                let tag_result = create_tag(umbrella, name, sign, message, dry_run);
+
+                report.set_umbrella("tag", name);
+                match &tag_result {
+                    Ok(result) => report.set_umbrella("result", result),
                     Err(e) => {
-                        writeln!(
-                            stdout,
-                            "- 'umbrella': failed to create tag '{}' - {}",
-                            name, e
-                        )?;
+                        failed += 1;
+                        report.set_umbrella("error", e.to_string());
                     },
                 }
+
+                write_tag_result(stdout, "umbrella", name, tag_kind, &tag_result)?;
             }
 
-            for config_repo in &repos_to_tag {
-                if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
-                    match create_tag(subrepo, name, sign, message) {
-                        Ok(result) => match result {
-                            TagResult::Created => {
-                                writeln!(
-                                    stdout,
-                                    "- '{}': created tag '{}'",
-                                    config_repo.path.display(),
-                                    name
-                                )?;
-                            },
-                            TagResult::AlreadyExists => {
-                                writeln!(
-                                    stdout,
-                                    "- '{}': tag '{}' already exists",
-                                    config_repo.path.display(),
-                                    name
-                                )?;
-                            },
-                        },
-                        Err(e) => {
-                            writeln!(
-                                stdout,
-                                "- '{}': failed to create tag '{}' - {}",
-                                config_repo.path.display(),
-                                name,
-                                e
-                            )?;
-                        },
-                    }
+            let tag_results =
+                super::parallel::for_each_repo(&subrepos, jobs, |_, subrepo| {
+                    create_tag(subrepo, name, sign, message, dry_run)
+                });
+
+            for ((config_repo, _), tag_result) in subrepos.iter().zip(tag_results) {
+                report.set(&config_repo.path, "tag", name);
+                match &tag_result {
+                    Ok(result) => report.set(&config_repo.path, "result", result),
+                    Err(e) => {
+                        failed += 1;
+                        report.set(&config_repo.path, "error", e.to_string());
+                    },
                 }
+
+                write_tag_result(
+                    stdout,
+                    &config_repo.path.display().to_string(),
+                    name,
+                    tag_kind,
+                    &tag_result,
+                )?;
             }
         },
         None => {
@@ -130,119 +143,691 @@ pub fn tag<W: Write>(
             writeln!(stdout, "Listing tags in {} repositories...", total_targets)?;
 
             if include_umbrella {
-                match list_tags(umbrella) {
-                    Ok(tags) => {
-                        if tags.is_empty() {
-                            writeln!(stdout, "- 'umbrella': no tags found")?;
-                        } else {
-                            writeln!(stdout, "- 'umbrella': {}", tags.join(", "))?;
-                        }
-                    },
+                let tag_list = list_tags(umbrella, filter, &prefix);
+
+                match &tag_list {
+                    Ok(tags) => report.set_umbrella("tags", tags),
                     Err(e) => {
-                        writeln!(stdout, "- 'umbrella': failed to list tags - {}", e)?;
+                        failed += 1;
+                        report.set_umbrella("error", e.to_string());
                     },
                 }
+
+                write_tag_list(stdout, "umbrella", &tag_list)?;
             }
 
-            for config_repo in &repos_to_tag {
-                if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
-                    match list_tags(subrepo) {
-                        Ok(tags) => {
-                            if tags.is_empty() {
-                                writeln!(
-                                    stdout,
-                                    "- '{}': no tags found",
-                                    config_repo.path.display()
-                                )?;
-                            } else {
-                                writeln!(
-                                    stdout,
-                                    "- '{}': {}",
-                                    config_repo.path.display(),
-                                    tags.join(", ")
-                                )?;
-                            }
-                        },
-                        Err(e) => {
-                            writeln!(
-                                stdout,
-                                "- '{}': failed to list tags - {}",
-                                config_repo.path.display(),
-                                e
-                            )?;
-                        },
-                    }
+            let tag_lists =
+                super::parallel::for_each_repo(&subrepos, jobs, |_, subrepo| {
+                    list_tags(subrepo, filter, &prefix)
+                });
+
+            for ((config_repo, _), tag_list) in subrepos.iter().zip(tag_lists) {
+                match &tag_list {
+                    Ok(tags) => report.set(&config_repo.path, "tags", tags),
+                    Err(e) => {
+                        failed += 1;
+                        report.set(&config_repo.path, "error", e.to_string());
+                    },
                 }
+
+                write_tag_list(
+                    stdout,
+                    &config_repo.path.display().to_string(),
+                    &tag_list,
+                )?;
             }
         },
     }
 
     // Push tags if requested
     if push {
-        writeln!(stdout, "Pushing tags to remotes...")?;
-
-        if include_umbrella {
-            match push_tags(umbrella) {
-                Ok(PushResult::Pushed) => {
-                    writeln!(stdout, "- 'umbrella': pushed tags")?;
-                },
-                Ok(PushResult::Skipped) => {
-                    writeln!(stdout, "- 'umbrella': no tags to push")?;
-                },
-                Err(e) => {
-                    writeln!(stdout, "- 'umbrella': failed to push tags - {}", e)?;
-                },
-            }
+        let subrepos: Vec<_> = subrepos
+            .iter()
+            .map(|(config_repo, subrepo)| ((*config_repo, planned_tag), *subrepo))
+            .collect();
+        failed += push_repos_tags(
+            stdout,
+            &mut report,
+            include_umbrella.then_some((umbrella, planned_tag)),
+            &subrepos,
+            jobs,
+            dry_run,
+        )?;
+    }
+
+    write_summary(stdout, &mut report, total_targets, failed)?;
+
+    report.summarize("tag", tag_name);
+    report.summarize("repos", total_targets);
+    report.summarize("failed", failed);
+    if dry_run {
+        report.summarize("dry_run", true);
+    }
+
+    Ok(report)
+}
+
+/// Which tags `tag` lists, and in which order.
+#[derive(Debug, Clone, Default)]
+pub struct TagFilter {
+    /// Glob the tag names have to match
+    pub pattern: Option<String>,
+    /// Revision the tags have to point at, resolved in each repo
+    pub points_at: Option<String>,
+    pub sort: TagSort,
+}
+
+/// Order of the tags listed by `tag`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TagSort {
+    /// By name
+    #[default]
+    Name,
+    /// By semantic version after the tag prefix, other tags first
+    Version,
+    /// By the date of the tag, or of its commit for lightweight tags
+    Date,
+}
+
+/// Delete a tag from each selected repo, and with `push` from the remote of
+/// its current branch too.
+pub fn tag_delete<W: Write>(
+    wok_config: &mut config::Config,
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+    tag_name: &str,
+    options: &TagOptions,
+) -> Result<output::Report> {
+    let TagOptions {
+        push,
+        all,
+        include_umbrella,
+        repos: target_repos,
+        jobs,
+        groups,
+        dry_run,
+        ..
+    } = *options;
+    let mut report = output::Report::new("tag");
+    let mut failed = 0;
+
+    let repos_to_tag =
+        select_tag_repos(wok_config, umbrella, all, groups, target_repos);
+
+    let total_targets = repos_to_tag.len() + usize::from(include_umbrella);
+
+    if total_targets == 0 {
+        writeln!(stdout, "No repositories to tag")?;
+        return Ok(report);
+    }
+
+    let subrepos: Vec<(&config::Repo, &repo::Repo)> = repos_to_tag
+        .iter()
+        .filter_map(|config_repo| {
+            umbrella
+                .get_subrepo_by_path(&config_repo.path)
+                .map(|subrepo| (config_repo, subrepo))
+        })
+        .collect();
+
+    writeln!(
+        stdout,
+        "Deleting tag '{}' in {} repositories...",
+        tag_name, total_targets
+    )?;
+
+    if include_umbrella {
+        let deletion = delete_tag(umbrella, tag_name, push, dry_run);
+
+        report.set_umbrella("tag", tag_name);
+        match &deletion {
+            Ok(deletion) => {
+                report.set_umbrella("result", deletion.result);
+                report.set_umbrella("remote", &deletion.remote);
+                if deletion.no_remote {
+                    report.set_umbrella("push", "no_remote");
+                }
+            },
+            Err(e) => {
+                failed += 1;
+                report.set_umbrella("error", e.to_string());
+            },
         }
 
-        for config_repo in &repos_to_tag {
-            if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
-                match push_tags(subrepo) {
-                    Ok(PushResult::Pushed) => {
-                        writeln!(
-                            stdout,
-                            "- '{}': pushed tags",
-                            config_repo.path.display()
-                        )?;
-                    },
-                    Ok(PushResult::Skipped) => {
-                        writeln!(
-                            stdout,
-                            "- '{}': no tags to push",
-                            config_repo.path.display()
-                        )?;
-                    },
-                    Err(e) => {
-                        writeln!(
-                            stdout,
-                            "- '{}': failed to push tags - {}",
-                            config_repo.path.display(),
-                            e
-                        )?;
-                    },
+        write_deletion(stdout, "umbrella", tag_name, &deletion)?;
+    }
+
+    let deletions = super::parallel::for_each_repo(&subrepos, jobs, |_, subrepo| {
+        delete_tag(subrepo, tag_name, push, dry_run)
+    });
+
+    for ((config_repo, _), deletion) in subrepos.iter().zip(deletions) {
+        report.set(&config_repo.path, "tag", tag_name);
+        match &deletion {
+            Ok(deletion) => {
+                report.set(&config_repo.path, "result", deletion.result);
+                report.set(&config_repo.path, "remote", &deletion.remote);
+                if deletion.no_remote {
+                    report.set(&config_repo.path, "push", "no_remote");
                 }
-            }
+            },
+            Err(e) => {
+                failed += 1;
+                report.set(&config_repo.path, "error", e.to_string());
+            },
         }
+
+        write_deletion(
+            stdout,
+            &config_repo.path.display().to_string(),
+            tag_name,
+            &deletion,
+        )?;
+    }
+
+    write_summary(stdout, &mut report, total_targets, failed)?;
+
+    report.summarize("tag", tag_name);
+    report.summarize("repos", total_targets);
+    report.summarize("failed", failed);
+    if dry_run {
+        report.summarize("dry_run", true);
+    }
+
+    Ok(report)
+}
+
+/// Part of the semantic version that `tag --bump` increments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BumpLevel {
+    Major,
+    Minor,
+    Patch,
+}
+
+/// Tag each selected repo with the version following its highest semver tag.
+/// The versions are worked out and shown for every repo before any tag is
+/// created.
+pub fn tag_bump<W: Write>(
+    wok_config: &mut config::Config,
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+    level: BumpLevel,
+    pre: Option<&str>,
+    options: &TagOptions,
+) -> Result<output::Report> {
+    let TagOptions {
+        sign,
+        message,
+        push,
+        all,
+        include_umbrella,
+        repos: target_repos,
+        jobs,
+        groups,
+        dry_run,
+    } = *options;
+    let mut report = output::Report::new("tag");
+    let tag_kind = tag_kind(sign, message);
+    let prefix = wok_config.tag_prefix.clone().unwrap_or_default();
+    let mut failed = 0;
+
+    let repos_to_tag =
+        select_tag_repos(wok_config, umbrella, all, groups, target_repos);
+
+    let total_targets = repos_to_tag.len() + usize::from(include_umbrella);
+
+    if total_targets == 0 {
+        writeln!(stdout, "No repositories to tag")?;
+        return Ok(report);
+    }
+
+    let subrepos: Vec<(&config::Repo, &repo::Repo)> = repos_to_tag
+        .iter()
+        .filter_map(|config_repo| {
+            umbrella
+                .get_subrepo_by_path(&config_repo.path)
+                .map(|subrepo| (config_repo, subrepo))
+        })
+        .collect();
+
+    // Work out every version before creating any tag
+    let mut bumps = Vec::new();
+    if include_umbrella {
+        let (current, next) = next_version(umbrella, &prefix, level, pre)?;
+        bumps.push((None, umbrella, current, next));
+    }
+    for (config_repo, subrepo) in &subrepos {
+        let (current, next) =
+            next_version(subrepo, &prefix, level, pre).with_context(|| {
+                format!(
+                    "Cannot bump the version of '{}'",
+                    config_repo.path.display()
+                )
+            })?;
+        bumps.push((Some(*config_repo), *subrepo, current, next));
     }
 
     writeln!(
         stdout,
-        "Successfully processed {} repositories",
+        "Bumping {} versions in {} repositories...",
+        level_name(level),
         total_targets
     )?;
+    let mut rows = vec![[
+        String::from("repo"),
+        String::from("current"),
+        String::from("next"),
+    ]];
+    for (config_repo, _, current, next) in &bumps {
+        rows.push([
+            repo_label(*config_repo),
+            current.clone().unwrap_or_else(|| String::from("-")),
+            next.clone(),
+        ]);
+    }
+    let widths: Vec<usize> = (0..3)
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(stdout, "{}", line.trim_end())?;
+    }
+
+    for (config_repo, repo, current, next) in &bumps {
+        let tag_result = create_tag(repo, next, sign, message, dry_run);
+        match config_repo {
+            Some(config_repo) => {
+                report.set(&config_repo.path, "previous", current);
+                report.set(&config_repo.path, "tag", next);
+            },
+            None => {
+                report.set_umbrella("previous", current);
+                report.set_umbrella("tag", next);
+            },
+        }
+        match (&tag_result, config_repo) {
+            (Ok(result), Some(config_repo)) => {
+                report.set(&config_repo.path, "result", result)
+            },
+            (Ok(result), None) => report.set_umbrella("result", result),
+            (Err(e), Some(config_repo)) => {
+                failed += 1;
+                report.set(&config_repo.path, "error", e.to_string());
+            },
+            (Err(e), None) => {
+                failed += 1;
+                report.set_umbrella("error", e.to_string());
+            },
+        }
+
+        write_tag_result(
+            stdout,
+            &repo_label(*config_repo),
+            next,
+            tag_kind,
+            &tag_result,
+        )?;
+    }
+
+    if push {
+        // Tags that don't exist yet are still pushed when planning a dry run
+        let planned_tag = |config_repo: Option<&config::Repo>| {
+            bumps
+                .iter()
+                .find(|(bumped, ..)| {
+                    bumped.map(|bumped| &bumped.path)
+                        == config_repo.map(|repo| &repo.path)
+                })
+                .map(|(_, _, _, next)| next.as_str())
+                .filter(|_| dry_run)
+        };
+        let subrepos: Vec<_> = subrepos
+            .iter()
+            .map(|(config_repo, subrepo)| {
+                ((*config_repo, planned_tag(Some(config_repo))), *subrepo)
+            })
+            .collect();
+        failed += push_repos_tags(
+            stdout,
+            &mut report,
+            include_umbrella.then(|| (umbrella, planned_tag(None))),
+            &subrepos,
+            jobs,
+            dry_run,
+        )?;
+    }
+
+    write_summary(stdout, &mut report, total_targets, failed)?;
+
+    report.summarize("bump", level);
+    report.summarize("repos", total_targets);
+    report.summarize("failed", failed);
+    if dry_run {
+        report.summarize("dry_run", true);
+    }
+
+    Ok(report)
+}
+
+/// Close the output of a tag command, failing it if any repo failed.
+fn write_summary<W: Write>(
+    stdout: &mut W,
+    report: &mut output::Report,
+    total_targets: usize,
+    failed: usize,
+) -> Result<()> {
+    if failed > 0 {
+        report.exit_code = 1;
+        writeln!(
+            stdout,
+            "Processed {} repositories, {} failed",
+            total_targets, failed
+        )?;
+    } else {
+        writeln!(
+            stdout,
+            "Successfully processed {} repositories",
+            total_targets
+        )?;
+    }
     Ok(())
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// The highest semver tag with `prefix` in `repo`, if any, and the tag of the
+/// version following it.
+fn next_version(
+    repo: &repo::Repo,
+    prefix: &str,
+    level: BumpLevel,
+    pre: Option<&str>,
+) -> Result<(Option<String>, String)> {
+    let current = list_tags(repo, &TagFilter::default(), prefix)?
+        .into_iter()
+        .filter_map(|tag| {
+            let version = semver::Version::parse(tag.strip_prefix(prefix)?).ok()?;
+            Some((version, tag))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b));
+
+    let version = current
+        .as_ref()
+        .map(|(version, _)| version.clone())
+        .unwrap_or_else(|| semver::Version::new(0, 0, 0));
+    let next = bump_version(&version, level, pre)?;
+
+    Ok((current.map(|(_, tag)| tag), format!("{}{}", prefix, next)))
+}
+
+/// Increment `version` at `level`. A prerelease is first released at its own
+/// version when that is what `level` leads to, e.g. a patch bump of
+/// `1.2.4-rc.1` gives `1.2.4`. With `pre` the result is a prerelease instead:
+/// `1.2.3` gives `1.2.4-rc.1`, and `1.2.4-rc.1` goes on to `1.2.4-rc.2`. A
+/// prerelease that would sort before `version`, such as `1.2.4-alpha.1` after
+/// `1.2.4-rc.2`, is refused.
+fn bump_version(
+    version: &semver::Version,
+    level: BumpLevel,
+    pre: Option<&str>,
+) -> Result<semver::Version> {
+    let prerelease = !version.pre.is_empty();
+    let mut next = semver::Version::new(version.major, version.minor, version.patch);
+    match level {
+        BumpLevel::Major
+            if !(prerelease && version.minor == 0 && version.patch == 0) =>
+        {
+            next = semver::Version::new(version.major + 1, 0, 0);
+        },
+        BumpLevel::Minor if !(prerelease && version.patch == 0) => {
+            next = semver::Version::new(version.major, version.minor + 1, 0);
+        },
+        BumpLevel::Patch if !prerelease => {
+            next.patch += 1;
+        },
+        _ => {},
+    }
+
+    let Some(pre) = pre else {
+        return Ok(next);
+    };
+
+    // Count up the prerelease of the same version and identifier
+    let number = version
+        .pre
+        .as_str()
+        .strip_prefix(pre)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .and_then(|number| number.parse::<u64>().ok())
+        .filter(|_| {
+            prerelease
+                && (next.major, next.minor, next.patch)
+                    == (version.major, version.minor, version.patch)
+        })
+        .map_or(1, |number| number + 1);
+    next.pre = semver::Prerelease::new(&format!("{}.{}", pre, number))
+        .with_context(|| format!("Invalid prerelease identifier '{}'", pre))?;
+    if next <= *version {
+        bail!(
+            "Version {} would not come after {}; use a later prerelease identifier \
+             than '{}' or release {} first",
+            next,
+            version,
+            pre,
+            semver::Version::new(version.major, version.minor, version.patch)
+        );
+    }
+    Ok(next)
+}
+
+/// Select the repos to tag. Unlike other commands, tag also leaves repos
+/// opted out via `skip_for` out of the repos matching the umbrella's head.
+fn select_tag_repos(
+    wok_config: &config::Config,
+    umbrella: &repo::Repo,
+    all: bool,
+    groups: &[String],
+    target_repos: &[path::PathBuf],
+) -> Vec<config::Repo> {
+    let mut repos =
+        wok_config.select_repos("tag", &umbrella.head, all, groups, target_repos);
+    if !all && groups.is_empty() && target_repos.is_empty() {
+        repos.retain(|config_repo| !config_repo.is_skipped_for("tag"));
+    }
+    repos
+}
+
+fn level_name(level: BumpLevel) -> &'static str {
+    match level {
+        BumpLevel::Major => "major",
+        BumpLevel::Minor => "minor",
+        BumpLevel::Patch => "patch",
+    }
+}
+
+fn repo_label(config_repo: Option<&config::Repo>) -> String {
+    config_repo
+        .map(|config_repo| config_repo.path.display().to_string())
+        .unwrap_or_else(|| String::from("umbrella"))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 enum TagResult {
     Created,
     AlreadyExists,
+    Planned,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum DeleteResult {
+    Deleted,
+    NotFound,
+    Planned,
+}
+
+/// Where a tag was deleted, or would be in a dry run.
+struct TagDeletion {
+    result: DeleteResult,
+    /// Whether the repo had the tag
+    local: bool,
+    /// Remote the tag was deleted from
+    remote: Option<String>,
+    /// Whether the tag was to be deleted on the remote too, but the repo has
+    /// none
+    no_remote: bool,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 enum PushResult {
     Pushed,
     Skipped,
+    Planned,
+}
+
+fn tag_kind(sign: bool, message: Option<&str>) -> &'static str {
+    if sign {
+        " (signed)"
+    } else if message.is_some() {
+        " (annotated)"
+    } else {
+        ""
+    }
+}
+
+fn write_tag_result<W: Write>(
+    stdout: &mut W,
+    label: &str,
+    name: &str,
+    tag_kind: &str,
+    tag_result: &Result<TagResult>,
+) -> Result<()> {
+    match tag_result {
+        Ok(TagResult::Created) => {
+            writeln!(stdout, "- '{}': created tag '{}'", label, name)?;
+        },
+        Ok(TagResult::AlreadyExists) => {
+            writeln!(stdout, "- '{}': tag '{}' already exists", label, name)?;
+        },
+        Ok(TagResult::Planned) => {
+            writeln!(
+                stdout,
+                "- '{}': would create tag '{}'{}",
+                label, name, tag_kind
+            )?;
+        },
+        Err(e) => {
+            writeln!(
+                stdout,
+                "- '{}': failed to create tag '{}' - {}",
+                label, name, e
+            )?;
+        },
+    }
+    Ok(())
+}
+
+/// Push the missing tags of the umbrella, if given, and of the subrepos. Each
+/// repo comes with the tag it is planned to get in a dry run. Returns the
+/// number of repos that failed to push.
+fn push_repos_tags<W: Write>(
+    stdout: &mut W,
+    report: &mut output::Report,
+    umbrella: Option<(&repo::Repo, Option<&str>)>,
+    subrepos: &[((&config::Repo, Option<&str>), &repo::Repo)],
+    jobs: usize,
+    dry_run: bool,
+) -> Result<usize> {
+    let mut failed = 0;
+    writeln!(stdout, "Pushing tags to remotes...")?;
+
+    if let Some((umbrella, planned_tag)) = umbrella {
+        let push_result = push_tags(umbrella, planned_tag, dry_run);
+
+        match &push_result {
+            Ok((result, _)) => report.set_umbrella("push", result),
+            Err(e) => {
+                failed += 1;
+                report.set_umbrella("push_error", e.to_string());
+            },
+        }
+
+        match push_result {
+            Ok((PushResult::Pushed, _)) => {
+                writeln!(stdout, "- 'umbrella': pushed tags")?;
+            },
+            Ok((PushResult::Planned, refspecs)) => {
+                writeln!(stdout, "- 'umbrella': would push {}", quoted(&refspecs))?;
+            },
+            Ok((PushResult::Skipped, _)) => {
+                writeln!(stdout, "- 'umbrella': no tags to push")?;
+            },
+            Err(e) => {
+                writeln!(stdout, "- 'umbrella': failed to push tags - {}", e)?;
+            },
+        }
+    }
+
+    let push_results =
+        super::parallel::for_each_repo(subrepos, jobs, |(_, planned_tag), subrepo| {
+            push_tags(subrepo, *planned_tag, dry_run)
+        });
+
+    for (((config_repo, _), _), push_result) in subrepos.iter().zip(push_results) {
+        match &push_result {
+            Ok((result, _)) => report.set(&config_repo.path, "push", result),
+            Err(e) => {
+                failed += 1;
+                report.set(&config_repo.path, "push_error", e.to_string());
+            },
+        }
+
+        match push_result {
+            Ok((PushResult::Pushed, _)) => {
+                writeln!(stdout, "- '{}': pushed tags", config_repo.path.display())?;
+            },
+            Ok((PushResult::Planned, refspecs)) => {
+                writeln!(
+                    stdout,
+                    "- '{}': would push {}",
+                    config_repo.path.display(),
+                    quoted(&refspecs)
+                )?;
+            },
+            Ok((PushResult::Skipped, _)) => {
+                writeln!(
+                    stdout,
+                    "- '{}': no tags to push",
+                    config_repo.path.display()
+                )?;
+            },
+            Err(e) => {
+                writeln!(
+                    stdout,
+                    "- '{}': failed to push tags - {}",
+                    config_repo.path.display(),
+                    e
+                )?;
+            },
+        }
+    }
+
+    Ok(failed)
+}
+
+fn quoted(refspecs: &[String]) -> String {
+    refspecs
+        .iter()
+        .map(|refspec| format!("'{}'", refspec))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 fn create_tag(
@@ -250,6 +835,7 @@ fn create_tag(
     tag_name: &str,
     sign: bool,
     message: Option<&str>,
+    dry_run: bool,
 ) -> Result<TagResult> {
     // Check if tag already exists by trying to find it
     if repo
@@ -260,6 +846,10 @@ fn create_tag(
         return Ok(TagResult::AlreadyExists);
     }
 
+    if dry_run {
+        return Ok(TagResult::Planned);
+    }
+
     // Get the current HEAD commit
     let head = repo.git_repo.head()?;
     let commit = head.peel_to_commit()?;
@@ -286,23 +876,213 @@ fn create_tag(
     Ok(TagResult::Created)
 }
 
-fn list_tags(repo: &repo::Repo) -> Result<Vec<String>> {
+fn write_tag_list<W: Write>(
+    stdout: &mut W,
+    label: &str,
+    tag_list: &Result<Vec<String>>,
+) -> Result<()> {
+    match tag_list {
+        Ok(tags) if tags.is_empty() => {
+            writeln!(stdout, "- '{}': no tags found", label)?;
+        },
+        Ok(tags) => {
+            writeln!(stdout, "- '{}':", label)?;
+            for tag in tags {
+                writeln!(stdout, "    - '{}'", tag)?;
+            }
+        },
+        Err(e) => {
+            writeln!(stdout, "- '{}': failed to list tags - {}", label, e)?;
+        },
+    }
+    Ok(())
+}
+
+fn write_deletion<W: Write>(
+    stdout: &mut W,
+    label: &str,
+    tag_name: &str,
+    deletion: &Result<TagDeletion>,
+) -> Result<()> {
+    let deletion = match deletion {
+        Ok(deletion) => deletion,
+        Err(e) => {
+            writeln!(
+                stdout,
+                "- '{}': failed to delete tag '{}' - {}",
+                label, tag_name, e
+            )?;
+            return Ok(());
+        },
+    };
+
+    let mut places = match (deletion.local, &deletion.remote) {
+        (true, Some(remote)) => format!(" here and on '{}'", remote),
+        (false, Some(remote)) => format!(" on '{}'", remote),
+        _ => String::new(),
+    };
+    if deletion.no_remote {
+        places.push_str(" (no remote configured)");
+    }
+    match deletion.result {
+        DeleteResult::Deleted => {
+            writeln!(
+                stdout,
+                "- '{}': deleted tag '{}'{}",
+                label, tag_name, places
+            )?;
+        },
+        DeleteResult::NotFound => {
+            writeln!(
+                stdout,
+                "- '{}': tag '{}' not found{}",
+                label, tag_name, places
+            )?;
+        },
+        DeleteResult::Planned => {
+            writeln!(
+                stdout,
+                "- '{}': would delete tag '{}'{}",
+                label, tag_name, places
+            )?;
+        },
+    }
+    Ok(())
+}
+
+/// The tags of `repo` that pass `filter`, in its order. Versions are compared
+/// after `prefix`.
+fn list_tags(
+    repo: &repo::Repo,
+    filter: &TagFilter,
+    prefix: &str,
+) -> Result<Vec<String>> {
+    let points_at = match &filter.points_at {
+        Some(rev) => Some(
+            repo.git_repo
+                .revparse_single(rev)
+                .and_then(|object| object.peel_to_commit())
+                .with_context(|| {
+                    format!(
+                        "Cannot find revision '{}' in repo at `{}`",
+                        rev,
+                        repo.work_dir.display()
+                    )
+                })?
+                .id(),
+        ),
+        None => None,
+    };
+
     let mut tags = Vec::new();
 
-    // Get all tag references
-    let tag_names = repo.git_repo.tag_names(None)?;
+    // Get the tag references matching the pattern
+    let tag_names = repo.git_repo.tag_names(filter.pattern.as_deref())?;
 
     for tag_name in tag_names.iter().flatten() {
+        if let Some(points_at) = points_at
+            && tag_commit(repo, tag_name) != Some(points_at)
+        {
+            continue;
+        }
         tags.push(tag_name.to_string());
     }
 
-    // Sort tags for consistent output
+    // Sort tags for consistent output, keeping the names in order among tags
+    // that compare equal
     tags.sort();
+    match filter.sort {
+        TagSort::Name => {},
+        TagSort::Version => tags.sort_by_cached_key(|tag| {
+            tag.strip_prefix(prefix)
+                .and_then(|version| semver::Version::parse(version).ok())
+        }),
+        TagSort::Date => tags.sort_by_cached_key(|tag| tag_time(repo, tag)),
+    }
 
     Ok(tags)
 }
 
-fn push_tags(repo: &repo::Repo) -> Result<PushResult> {
+fn tag_commit(repo: &repo::Repo, tag_name: &str) -> Option<git2::Oid> {
+    let object = repo
+        .git_repo
+        .revparse_single(&format!("refs/tags/{}", tag_name))
+        .ok()?;
+    Some(object.peel_to_commit().ok()?.id())
+}
+
+/// When the tag was made, or when its commit was for a lightweight tag.
+fn tag_time(repo: &repo::Repo, tag_name: &str) -> Option<i64> {
+    let object = repo
+        .git_repo
+        .revparse_single(&format!("refs/tags/{}", tag_name))
+        .ok()?;
+    if let Some(tagger) = object.as_tag().and_then(|tag| tag.tagger()) {
+        return Some(tagger.when().seconds());
+    }
+    Some(object.peel_to_commit().ok()?.time().seconds())
+}
+
+/// Delete `tag_name` in `repo`, and with `push` on the remote of the current
+/// branch if the remote has it. With `dry_run` nothing is deleted.
+fn delete_tag(
+    repo: &repo::Repo,
+    tag_name: &str,
+    push: bool,
+    dry_run: bool,
+) -> Result<TagDeletion> {
+    let refname = format!("refs/tags/{}", tag_name);
+    let local = repo.git_repo.find_reference(&refname).ok();
+
+    let mut remote = None;
+    let mut no_remote = false;
+    if push {
+        match tag_remote(repo) {
+            Ok(mut tag_remote) => {
+                if remote_tags(repo, &mut tag_remote)?.contains_key(&refname) {
+                    remote = Some(tag_remote);
+                }
+            },
+            // The local tag is still deleted
+            Err(_) => no_remote = true,
+        }
+    }
+
+    let mut deletion = TagDeletion {
+        result: DeleteResult::Planned,
+        local: local.is_some(),
+        remote: remote
+            .as_ref()
+            .and_then(|remote| remote.name().map(String::from)),
+        no_remote,
+    };
+    if local.is_none() && remote.is_none() {
+        deletion.result = DeleteResult::NotFound;
+        return Ok(deletion);
+    }
+    if dry_run {
+        return Ok(deletion);
+    }
+
+    if let Some(mut remote) = remote {
+        let mut push_options = git2::PushOptions::new();
+        push_options.remote_callbacks(repo.remote_callbacks()?);
+        let push_result =
+            remote.push(&[format!(":{}", refname)], Some(&mut push_options));
+        let disconnect_result = remote.disconnect();
+        push_result?;
+        disconnect_result?;
+    }
+    if let Some(mut reference) = local {
+        reference.delete()?;
+    }
+
+    deletion.result = DeleteResult::Deleted;
+    Ok(deletion)
+}
+
+/// The remote of the branch checked out in `repo`, where its tags are pushed.
+fn tag_remote(repo: &repo::Repo) -> Result<git2::Remote<'_>> {
     // Get the remote name for the current branch
     let head_ref = repo.git_repo.head()?;
     let branch_name = head_ref.shorthand().with_context(|| {
@@ -315,20 +1095,17 @@ fn push_tags(repo: &repo::Repo) -> Result<PushResult> {
     let remote_name = repo.get_remote_name_for_branch(branch_name)?;
 
     // Check if remote exists
-    let mut remote = match repo.git_repo.find_remote(&remote_name) {
-        Ok(remote) => remote,
-        Err(_) => {
-            return Err(anyhow!("No remote '{}' configured", remote_name));
-        },
-    };
-
-    // Collect explicit tag refspecs; libgit2 does not expand wildcards automatically.
-    let tag_names = repo.git_repo.tag_names(None)?;
-    if tag_names.is_empty() {
-        return Ok(PushResult::Skipped);
+    match repo.git_repo.find_remote(&remote_name) {
+        Ok(remote) => Ok(remote),
+        Err(_) => Err(anyhow!("No remote '{}' configured", remote_name)),
     }
+}
 
-    // Discover which tags already exist on the remote so we avoid redundant pushes.
+/// The tags on `remote`, with the objects they point to.
+fn remote_tags(
+    repo: &repo::Repo,
+    remote: &mut git2::Remote,
+) -> Result<HashMap<String, git2::Oid>> {
     let connection = remote.connect_auth(
         git2::Direction::Push,
         Some(repo.remote_callbacks()?),
@@ -350,11 +1127,46 @@ fn push_tags(repo: &repo::Repo) -> Result<PushResult> {
             Ok(Err(err)) => return Err(err.into()),
             Err(_) => HashMap::new(),
         };
-    drop(connection);
+    Ok(remote_tags)
+}
+
+/// Push the tags missing from the remote, returning the refspecs that were
+/// pushed. With `dry_run` nothing is pushed, and `planned_tag` counts as a tag
+/// even if it doesn't exist yet.
+fn push_tags(
+    repo: &repo::Repo,
+    planned_tag: Option<&str>,
+    dry_run: bool,
+) -> Result<(PushResult, Vec<String>)> {
+    let mut remote = tag_remote(repo)?;
+
+    // Collect explicit tag refspecs; libgit2 does not expand wildcards automatically.
+    let mut tag_names: Vec<String> = repo
+        .git_repo
+        .tag_names(None)?
+        .iter()
+        .flatten()
+        .map(String::from)
+        .collect();
+    let planned_tag =
+        planned_tag.filter(|name| !tag_names.iter().any(|tag| tag == name));
+    if let Some(name) = planned_tag {
+        tag_names.push(name.to_string());
+    }
+    if tag_names.is_empty() {
+        return Ok((PushResult::Skipped, Vec::new()));
+    }
+
+    // Discover which tags already exist on the remote so we avoid redundant pushes.
+    let remote_tags = remote_tags(repo, &mut remote)?;
 
     let mut refspecs: Vec<String> = Vec::new();
-    for tag_name in tag_names.iter().flatten() {
+    for tag_name in &tag_names {
         let refname = format!("refs/tags/{tag_name}");
+        if planned_tag == Some(tag_name.as_str()) {
+            refspecs.push(format!("{refname}:{refname}"));
+            continue;
+        }
         let reference = repo.git_repo.find_reference(&refname)?;
         let target_oid = reference.target().with_context(|| {
             format!("Tag '{}' does not point to an object", tag_name)
@@ -369,7 +1181,11 @@ fn push_tags(repo: &repo::Repo) -> Result<PushResult> {
     }
 
     if refspecs.is_empty() {
-        return Ok(PushResult::Skipped);
+        return Ok((PushResult::Skipped, refspecs));
+    }
+
+    if dry_run {
+        return Ok((PushResult::Planned, refspecs));
     }
 
     let refspec_refs: Vec<&str> =
@@ -382,5 +1198,5 @@ fn push_tags(repo: &repo::Repo) -> Result<PushResult> {
     push_result?;
     disconnect_result?;
 
-    Ok(PushResult::Pushed)
+    Ok((PushResult::Pushed, refspecs))
 }
@@ -2,9 +2,14 @@ use anyhow::*;
 use std::io::Write;
 use std::result::Result::Ok;
 
-use crate::repo;
+use crate::{output, repo};
+
+pub fn test_auth<W: Write>(
+    repo: &repo::Repo,
+    stdout: &mut W,
+) -> Result<output::Report> {
+    let mut report = output::Report::new("test-auth");
 
-pub fn test_auth<W: Write>(repo: &repo::Repo, stdout: &mut W) -> Result<()> {
     writeln!(stdout, "Testing authentication for repository...")?;
     writeln!(stdout, "Repository: {}", repo.work_dir.display())?;
     writeln!(stdout)?;
@@ -16,12 +21,15 @@ pub fn test_auth<W: Write>(repo: &repo::Repo, stdout: &mut W) -> Result<()> {
         .ok_or_else(|| anyhow!("Cannot get branch name"))?;
 
     writeln!(stdout, "Current branch: {}", branch_name)?;
+    report.set_umbrella("branch", branch_name);
 
     // Get tracking branch
     if let Some(tracking) = repo.tracking_branch(branch_name)? {
         writeln!(stdout, "Remote: {}", tracking.remote)?;
         writeln!(stdout, "Remote ref: {}", tracking.remote_ref)?;
         writeln!(stdout)?;
+        report.set_umbrella("remote", &tracking.remote);
+        report.set_umbrella("remote_ref", &tracking.remote_ref);
 
         // Try to find the remote
         match repo.git_repo.find_remote(&tracking.remote) {
@@ -40,10 +48,14 @@ pub fn test_auth<W: Write>(repo: &repo::Repo, stdout: &mut W) -> Result<()> {
                         writeln!(stdout, "? Connection successful!")?;
                         writeln!(stdout)?;
                         writeln!(stdout, "Available remote heads:")?;
+                        let mut heads = Vec::new();
                         for head in connection.list()?.iter() {
                             writeln!(stdout, "  - {}", head.name())?;
+                            heads.push(head.name().to_string());
                         }
                         drop(connection);
+                        report.set_umbrella("heads", heads);
+                        report.summarize("connected", true);
                     },
                     Err(e) => {
                         writeln!(stdout)?;
@@ -82,7 +94,8 @@ pub fn test_auth<W: Write>(repo: &repo::Repo, stdout: &mut W) -> Result<()> {
             "  git branch --set-upstream-to=<remote>/<branch> {}",
             branch_name
         )?;
+        report.summarize("connected", false);
     }
 
-    Ok(())
+    Ok(report)
 }
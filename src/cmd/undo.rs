@@ -0,0 +1,272 @@
+use anyhow::*;
+use std::io::Write;
+use std::result::Result::Ok;
+use std::{fs, path};
+
+use crate::{journal, repo};
+
+/// Restore the workspace to the state recorded in the last journal entry and
+/// drop the entry. Nothing is changed unless every repo can be restored.
+pub fn undo<W: Write>(
+    umbrella: &repo::Repo,
+    config_path: &path::Path,
+    stdout: &mut W,
+) -> Result<()> {
+    let journal = journal::Journal::open(umbrella);
+    let (id, entry) = match journal.last()? {
+        Some(last) => last,
+        None => bail!("Nothing to undo"),
+    };
+
+    let config_rel_path = config_path.strip_prefix(&umbrella.work_dir).ok();
+
+    // Check every repo before touching any of them
+    let mut restores = vec![];
+    for state in &entry.repos {
+        let subrepo_dir = umbrella.work_dir.join(&state.path);
+        let git_repo = match git2::Repository::open(&subrepo_dir) {
+            Ok(git_repo) => git_repo,
+            Err(_) => {
+                writeln!(
+                    stdout,
+                    "- '{}': no longer a repo, skipping",
+                    state.path.display()
+                )?;
+                continue;
+            },
+        };
+        check_restorable(&git_repo, state, &entry.command, None)?;
+        restores.push((git_repo, state));
+    }
+    let umbrella_repo = git2::Repository::open(&umbrella.work_dir)?;
+    check_restorable(
+        &umbrella_repo,
+        &entry.umbrella,
+        &entry.command,
+        config_rel_path,
+    )?;
+
+    writeln!(
+        stdout,
+        "Undoing `{}` recorded at {}",
+        entry.command, entry.recorded_at
+    )?;
+
+    for (git_repo, state) in &restores {
+        let renamed = renamed_branches(git_repo, state)?;
+        let restored_refs = restore_refs(git_repo, state)?;
+        for restored_ref in &restored_refs {
+            writeln!(
+                stdout,
+                "- '{}': restored {}",
+                state.path.display(),
+                restored_ref
+            )?;
+        }
+
+        if restore(git_repo, state)? {
+            writeln!(
+                stdout,
+                "- '{}': restored '{}' at {}",
+                state.path.display(),
+                state.branch(),
+                &state.commit[..8]
+            )?;
+        } else if restored_refs.is_empty() && renamed.is_empty() {
+            writeln!(
+                stdout,
+                "- '{}': already at '{}' {}",
+                state.path.display(),
+                state.branch(),
+                &state.commit[..8]
+            )?;
+        }
+
+        // Only once HEAD is back on the recorded branch can the new name go
+        for name in &renamed {
+            git_repo
+                .find_branch(name, git2::BranchType::Local)?
+                .delete()?;
+            writeln!(
+                stdout,
+                "- '{}': deleted branch '{}'",
+                state.path.display(),
+                name
+            )?;
+        }
+    }
+
+    if restore(&umbrella_repo, &entry.umbrella)? {
+        writeln!(
+            stdout,
+            "- 'umbrella': restored '{}' at {}",
+            entry.umbrella.branch(),
+            &entry.umbrella.commit[..8]
+        )?;
+    }
+
+    fs::write(config_path, &entry.config).with_context(|| {
+        format!("Cannot restore wok file at `{}`", config_path.display())
+    })?;
+    journal.remove(id)?;
+
+    writeln!(stdout, "Undid `{}`", entry.command)?;
+    Ok(())
+}
+
+/// List the journal entries that can be undone, newest first.
+pub fn history<W: Write>(umbrella: &repo::Repo, stdout: &mut W) -> Result<()> {
+    let entries = journal::Journal::open(umbrella).entries()?;
+
+    if entries.is_empty() {
+        writeln!(stdout, "No operations recorded")?;
+        return Ok(());
+    }
+
+    for (id, entry) in entries.iter().rev() {
+        writeln!(
+            stdout,
+            "- {}: `{}` at {} ({} repos)",
+            id,
+            entry.command,
+            entry.recorded_at,
+            entry.repos.len()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Make sure `git_repo` can be put back to `state` without losing uncommitted
+/// changes. Changes to submodules and to `ignored_path` are restored as well,
+/// so they don't count.
+fn check_restorable(
+    git_repo: &git2::Repository,
+    state: &journal::RepoState,
+    command: &str,
+    ignored_path: Option<&path::Path>,
+) -> Result<()> {
+    let label = if state.path == path::Path::new(".") {
+        String::from("the umbrella repo")
+    } else {
+        format!("subrepo `{}`", state.path.display())
+    };
+
+    let missing_branches = state
+        .branches
+        .iter()
+        .filter(|(name, _)| {
+            git_repo.find_branch(name, git2::BranchType::Local).is_err()
+        })
+        .map(|(_, commit)| commit);
+    for commit in std::iter::once(&state.commit).chain(missing_branches) {
+        git_repo
+            .find_commit(git2::Oid::from_str(commit)?)
+            .with_context(|| {
+                format!(
+                    "Cannot undo `{}`: commit {} is missing from {}",
+                    command, commit, label
+                )
+            })?;
+    }
+
+    let mut status_options = git2::StatusOptions::new();
+    status_options
+        .include_untracked(false)
+        .exclude_submodules(true);
+    let dirty = git_repo
+        .statuses(Some(&mut status_options))?
+        .iter()
+        .any(|entry| entry.path().map(path::Path::new) != ignored_path);
+    if dirty {
+        bail!(
+            "Cannot undo `{}`: {} has uncommitted changes",
+            command,
+            label
+        );
+    }
+
+    Ok(())
+}
+
+/// Put HEAD and the work tree of `git_repo` back to `state`, moving the
+/// recorded branch if needed. Returns whether anything changed.
+fn restore(git_repo: &git2::Repository, state: &journal::RepoState) -> Result<bool> {
+    let oid = state.oid()?;
+    let current = journal::RepoState::capture(&state.path, git_repo).ok();
+    if current.is_some_and(|current| current.same_head(state)) {
+        return Ok(false);
+    }
+
+    let commit = git_repo.find_commit(oid)?;
+    if state.is_detached() {
+        git_repo.set_head_detached(oid)?;
+    } else {
+        git_repo.reference(&state.head, oid, true, "wok undo")?;
+        git_repo.set_head(&state.head)?;
+    }
+    git_repo.reset(commit.as_object(), git2::ResetType::Hard, None)?;
+
+    Ok(true)
+}
+
+/// Branches of `git_repo` that a recorded branch was renamed to, e.g. by `wok
+/// branch -m`: new branches still on the commit of a recorded branch that is
+/// gone.
+fn renamed_branches(
+    git_repo: &git2::Repository,
+    state: &journal::RepoState,
+) -> Result<Vec<String>> {
+    let current = journal::RepoState::capture(&state.path, git_repo)?;
+    let renamed_commits: Vec<&String> = state
+        .branches
+        .iter()
+        .filter(|(name, _)| !current.branches.contains_key(*name))
+        .map(|(_, commit)| commit)
+        .collect();
+
+    Ok(current
+        .branches
+        .into_iter()
+        .filter(|(name, commit)| {
+            !state.branches.contains_key(name) && renamed_commits.contains(&commit)
+        })
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Recreate the branches and the wok stashes of `state` that are gone from
+/// `git_repo`, e.g. after `wok branch --delete` or `wok stash drop`. Returns
+/// what was restored.
+fn restore_refs(
+    git_repo: &git2::Repository,
+    state: &journal::RepoState,
+) -> Result<Vec<String>> {
+    let mut restored = vec![];
+
+    for (name, commit) in &state.branches {
+        if git_repo.find_branch(name, git2::BranchType::Local).is_err() {
+            let commit = git_repo.find_commit(git2::Oid::from_str(commit)?)?;
+            git_repo.branch(name, &commit, false)?;
+            restored.push(format!("branch '{}'", name));
+        }
+    }
+
+    let current = journal::RepoState::capture(&state.path, git_repo)?;
+    // Stashes are pushed oldest first so that they keep their order
+    for stash in state.stashes.iter().rev() {
+        if !current.stashes.contains(stash) {
+            // The stash reflog goes away with the last stash
+            git_repo.reference_ensure_log("refs/stash")?;
+            git_repo.reference(
+                "refs/stash",
+                git2::Oid::from_str(&stash.commit)?,
+                true,
+                &stash.message,
+            )?;
+            restored.push(format!("stash '{}'", stash.message));
+        }
+    }
+
+    Ok(restored)
+}
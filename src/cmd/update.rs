@@ -1,62 +1,378 @@
 use anyhow::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::Write;
+use std::result::Result::Ok;
+use std::{fs, path};
 
-use crate::{config, repo};
+use super::{dry_run, stash};
+use crate::{config, journal, output, repo};
+
+/// How `update` updates the repos, and which ones.
+#[derive(Debug, Clone, Copy)]
+pub struct UpdateOptions<'a> {
+    /// Stage the submodule changes without committing them
+    pub no_commit: bool,
+    /// Update the umbrella too, before the subrepos
+    pub include_umbrella: bool,
+    /// Number of repos fetched at once
+    pub jobs: usize,
+    pub groups: &'a [String],
+    /// Strategy for diverged branches, overriding the wok file and git config
+    pub pull: Option<config::PullStrategy>,
+    pub autostash: bool,
+    /// Revision to pin the given repos to
+    pub to: Option<&'a str>,
+    pub repos: &'a [path::PathBuf],
+    pub dry_run: bool,
+}
+
+impl Default for UpdateOptions<'_> {
+    fn default() -> Self {
+        UpdateOptions {
+            no_commit: false,
+            include_umbrella: true,
+            jobs: 1,
+            groups: &[],
+            pull: None,
+            autostash: false,
+            to: None,
+            repos: &[],
+            dry_run: false,
+        }
+    }
+}
 
 pub fn update<W: Write>(
     wok_config: &mut config::Config,
     umbrella: &repo::Repo,
     stdout: &mut W,
-    no_commit: bool,
-    include_umbrella: bool,
-) -> Result<()> {
+    options: &UpdateOptions,
+) -> Result<output::Report> {
+    let UpdateOptions {
+        no_commit,
+        include_umbrella,
+        jobs,
+        groups,
+        pull,
+        autostash,
+        to,
+        repos: target_repos,
+        dry_run,
+    } = *options;
+    let mut report = output::Report::new("update");
+
+    if PendingUpdate::load(umbrella)?.is_some() {
+        bail!(
+            "An update stopped on conflicts is in progress; run `wok update \
+             --continue` or `wok update --abort` first"
+        );
+    }
+
+    if dry_run {
+        plan_update(wok_config, umbrella, stdout, &mut report, options)?;
+        return Ok(report);
+    }
+
     writeln!(stdout, "Updating repositories...")?;
 
     let mut saw_subrepo_updates = false;
     let mut saw_conflicts = false;
     let mut updated_repos = Vec::new(); // Track updated repos
+    // Where the repos were, to abort an update that stops on conflicts
+    let mut orig_heads = Vec::new();
 
     if include_umbrella {
-        let (_, conflicts) = update_repo(umbrella, &umbrella.head, "umbrella", stdout)?;
-        saw_conflicts |= conflicts;
+        orig_heads.push(OrigHead::capture(
+            path::Path::new("."),
+            umbrella,
+            &umbrella.head,
+            None,
+        )?);
+        let merge_result = update_repo(umbrella, &umbrella.head, pull)?;
+        let (_, conflicts) =
+            report_update(stdout, umbrella, &umbrella.head, "umbrella", &merge_result)?;
+        if conflicts {
+            saw_conflicts = true;
+            report.set_umbrella("conflicts", umbrella.conflicted_paths()?);
+        }
+
+        report.set_umbrella("branch", &umbrella.head);
+        report.set_umbrella("result", &merge_result);
+        report.set_umbrella("commit", get_current_commit_hash(&umbrella.git_repo)?);
     }
 
+    let repos_to_update = wok_config.select_repos(
+        "update",
+        &umbrella.head,
+        target_repos.is_empty(),
+        groups,
+        target_repos,
+    );
+
+    let subrepos: Vec<(&config::Repo, &repo::Repo)> = repos_to_update
+        .iter()
+        .filter_map(|config_repo| {
+            umbrella
+                .get_subrepo_by_path(&config_repo.path)
+                .map(|subrepo| (config_repo, subrepo))
+        })
+        .collect();
+
     // Step 1: Update each repo with fetch and merge
-    for config_repo in &wok_config.repos {
-        if config_repo.is_skipped_for("update") {
-            continue;
+    let merge_results =
+        super::parallel::for_each_repo(&subrepos, jobs, |config_repo, subrepo| {
+            let pin = to.or(config_repo.pin());
+            let orig_head =
+                OrigHead::capture(&config_repo.path, subrepo, &config_repo.head, pin)?;
+            let result = stash::with_autostash(
+                subrepo,
+                autostash,
+                &format!("update of '{}'", pin.unwrap_or(&config_repo.head)),
+                || match pin {
+                    Some(rev) => subrepo.pin(&config_repo.head, rev),
+                    None => update_repo(
+                        subrepo,
+                        &config_repo.head,
+                        pull.or(config_repo.pull),
+                    ),
+                },
+            )?;
+            Ok((orig_head, result))
+        });
+
+    // An error in one repo is only raised once the others are recorded, so
+    // that their conflicts can still be continued or aborted
+    let mut first_error = None;
+    for ((config_repo, subrepo), merge_result) in subrepos.iter().zip(merge_results) {
+        let label = config_repo.path.display().to_string();
+        let (mut orig_head, (merge_result, stashed)) = match merge_result {
+            Ok(merge_result) => merge_result,
+            Err(e) => {
+                first_error.get_or_insert(e);
+                continue;
+            },
+        };
+        orig_head.autostashed = matches!(
+            stashed,
+            Some(stash::Autostash::Restored | stash::Autostash::Conflicts(_))
+        );
+        let (updated, conflicts) =
+            report_update(stdout, subrepo, orig_head.target(), &label, &merge_result)?;
+        if conflicts {
+            report.set(&config_repo.path, "conflicts", subrepo.conflicted_paths()?);
+        }
+        if let Some(stashed) = &stashed {
+            report.set(&config_repo.path, "autostash", stashed.name());
+            stash::write_autostash(stdout, &config_repo.path, stashed)?;
+        }
+        saw_subrepo_updates |= updated;
+        saw_conflicts |= conflicts;
+
+        report.set(&config_repo.path, "branch", &config_repo.head);
+        if let Some(pin) = &orig_head.pin {
+            report.set(&config_repo.path, "pin", pin);
+        }
+        report.set(&config_repo.path, "result", &merge_result);
+        report.set(
+            &config_repo.path,
+            "commit",
+            get_current_commit_hash(&subrepo.git_repo)?,
+        );
+
+        // Track updated repos
+        if updated {
+            let commit_hash = get_current_commit_hash(&subrepo.git_repo)?;
+            updated_repos.push((
+                config_repo.path.to_string_lossy().to_string(),
+                orig_head.describe_update(&commit_hash),
+            ));
+        }
+        orig_heads.push(orig_head);
+    }
+
+    if saw_conflicts {
+        PendingUpdate {
+            no_commit,
+            repos: orig_heads,
         }
+        .save(umbrella)?;
+    }
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+
+    finish_update(
+        umbrella,
+        stdout,
+        &mut report,
+        no_commit,
+        saw_conflicts,
+        saw_subrepo_updates,
+        &updated_repos,
+    )?;
+    Ok(report)
+}
+
+/// Resume an update that stopped on conflicts: conclude the merges and rebases
+/// once their conflicts are resolved and staged, then commit the submodule
+/// state like `update` does.
+pub fn update_continue<W: Write>(
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+) -> Result<output::Report> {
+    let mut report = output::Report::new("update");
+    let pending = PendingUpdate::load(umbrella)?.ok_or_else(|| {
+        anyhow!("No update stopped on conflicts; nothing to continue")
+    })?;
 
-        if let Some(subrepo) = umbrella.get_subrepo_by_path(&config_repo.path) {
-            let label = config_repo.path.display().to_string();
-            let (updated, conflicts) =
-                update_repo(subrepo, &config_repo.head, &label, stdout)?;
-            saw_subrepo_updates |= updated;
-            saw_conflicts |= conflicts;
-
-            // Track updated repos
-            if updated {
-                let commit_hash = get_current_commit_hash(&subrepo.git_repo)?;
-                updated_repos.push((
-                    config_repo.path.to_string_lossy().to_string(),
-                    config_repo.head.clone(),
-                    commit_hash,
-                ));
+    writeln!(stdout, "Continuing update...")?;
+
+    let mut saw_conflicts = false;
+    let mut updated_repos = Vec::new();
+    for orig_head in &pending.repos {
+        let (repo, label) = pending_repo(umbrella, orig_head)?;
+
+        if let Some(merge_result) = repo.continue_merge()? {
+            let (_, conflicts) =
+                report_update(stdout, repo, orig_head.target(), &label, &merge_result)?;
+            if conflicts {
+                saw_conflicts = true;
+                report_pending(
+                    &mut report,
+                    orig_head,
+                    "conflicts",
+                    repo.conflicted_paths()?,
+                );
             }
+            report_pending(&mut report, orig_head, "result", &merge_result);
+        }
+
+        let commit_hash = get_current_commit_hash(&repo.git_repo)?;
+        report_pending(&mut report, orig_head, "branch", &orig_head.branch);
+        report_pending(&mut report, orig_head, "commit", &commit_hash);
+        if !orig_head.is_umbrella() && orig_head.moved_to(&commit_hash) {
+            updated_repos.push((
+                orig_head.head.path.to_string_lossy().to_string(),
+                orig_head.describe_update(&commit_hash),
+            ));
         }
     }
 
-    // Step 2: Stage all submodule changes in umbrella repo
-    let staged_changes = stage_submodule_changes(&umbrella.git_repo)?;
+    if !saw_conflicts {
+        PendingUpdate::remove(umbrella)?;
+    }
+
+    finish_update(
+        umbrella,
+        stdout,
+        &mut report,
+        pending.no_commit,
+        saw_conflicts,
+        !updated_repos.is_empty(),
+        &updated_repos,
+    )?;
+    Ok(report)
+}
+
+/// Abort an update that stopped on conflicts, putting every repo it touched
+/// back on the branch and commit it had before.
+pub fn update_abort<W: Write>(
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+) -> Result<output::Report> {
+    let mut report = output::Report::new("update");
+    let pending = PendingUpdate::load(umbrella)?
+        .ok_or_else(|| anyhow!("No update stopped on conflicts; nothing to abort"))?;
+
+    writeln!(stdout, "Aborting update...")?;
+
+    let mut kept = 0;
+    for orig_head in &pending.repos {
+        let (repo, label) = pending_repo(umbrella, orig_head)?;
+        report_pending(&mut report, orig_head, "branch", orig_head.head.branch());
+
+        if !orig_head.was_moved(repo)? {
+            writeln!(
+                stdout,
+                "- '{}': left '{}' as it is",
+                label,
+                orig_head.head.branch()
+            )?;
+            report_pending(&mut report, orig_head, "result", "unchanged");
+            continue;
+        }
+
+        if orig_head.autostashed && has_changes(repo)? {
+            kept += 1;
+            writeln!(
+                stdout,
+                "- '{}': not restoring '{}' - it has changes re-applied by \
+                 --autostash; commit or stash them and run `wok update --abort` again",
+                label,
+                orig_head.head.branch()
+            )?;
+            report_pending(&mut report, orig_head, "result", "kept");
+            continue;
+        }
+
+        orig_head.restore(repo)?;
+
+        writeln!(
+            stdout,
+            "- '{}': restored '{}' at {}",
+            label,
+            orig_head.head.branch(),
+            &orig_head.head.commit[..8]
+        )?;
+        report_pending(&mut report, orig_head, "commit", &orig_head.head.commit);
+        report_pending(&mut report, orig_head, "result", "restored");
+    }
+
+    report.summarize("kept", kept);
+    if kept > 0 {
+        report.exit_code = 1;
+        writeln!(stdout, "Update not fully aborted")?;
+        return Ok(report);
+    }
+
+    PendingUpdate::remove(umbrella)?;
+    writeln!(stdout, "Aborted update")?;
+    Ok(report)
+}
+
+/// Stage the submodule changes of an update in the umbrella repo and commit
+/// them, unless the update stopped on conflicts or `no_commit` is set.
+fn finish_update<W: Write>(
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+    report: &mut output::Report,
+    no_commit: bool,
+    saw_conflicts: bool,
+    saw_subrepo_updates: bool,
+    updated_repos: &[(String, String)],
+) -> Result<()> {
+    report.summarize("updated", updated_repos.len());
+    report.summarize("conflicts", saw_conflicts);
+    report.summarize("committed", false);
 
     if saw_conflicts {
+        report.exit_code = 1;
         writeln!(
             stdout,
             "Skipped committing umbrella repo due to merge conflicts"
         )?;
+        writeln!(
+            stdout,
+            "Resolve and stage the conflicts, then run `wok update --continue`, or \
+             run `wok update --abort` to restore the repos"
+        )?;
         return Ok(());
     }
 
+    // Stage all submodule changes in umbrella repo
+    let staged_changes = stage_submodule_changes(&umbrella.git_repo)?;
+
     if no_commit {
         if staged_changes || saw_subrepo_updates {
             writeln!(
@@ -69,30 +385,502 @@ pub fn update<W: Write>(
         return Ok(());
     }
 
-    // Step 3: Commit the updated submodule state
+    // Commit the updated submodule state
     if !staged_changes {
         writeln!(stdout, "No submodule updates detected; nothing to commit")?;
         return Ok(());
     }
 
-    commit_submodule_updates(&umbrella.git_repo, &updated_repos)?;
+    commit_submodule_updates(&umbrella.git_repo, updated_repos)?;
 
     writeln!(stdout, "Updated submodule state committed")?;
+    report.summarize("committed", true);
+    Ok(())
+}
+
+/// Update that stopped on conflicts, kept in the umbrella's git dir until it
+/// is continued or aborted.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct PendingUpdate {
+    no_commit: bool,
+    #[serde(default, rename = "repo")]
+    repos: Vec<OrigHead>,
+}
+
+/// Where a repo was before `update` switched it to `branch` and moved it, or
+/// pinned it to a revision.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct OrigHead {
+    branch: String,
+    /// Commit `branch` pointed to
+    commit: String,
+    /// Revision the repo is being pinned to instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pin: Option<String>,
+    /// Whether `--autostash` re-applied uncommitted changes after the update
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    autostashed: bool,
+    head: journal::RepoState,
+}
+
+impl PendingUpdate {
+    fn path(umbrella: &repo::Repo) -> path::PathBuf {
+        umbrella.git_repo.path().join("wok").join("update.toml")
+    }
+
+    fn load(umbrella: &repo::Repo) -> Result<Option<Self>> {
+        let pending_path = Self::path(umbrella);
+        if !pending_path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&pending_path).with_context(|| {
+            format!("Cannot read pending update at `{}`", pending_path.display())
+        })?;
+        toml::from_str(&contents).map(Some).with_context(|| {
+            format!(
+                "Cannot parse pending update at `{}`",
+                pending_path.display()
+            )
+        })
+    }
+
+    fn save(&self, umbrella: &repo::Repo) -> Result<()> {
+        let pending_path = Self::path(umbrella);
+        if let Some(dir) = pending_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&pending_path, toml::to_string(self)?).with_context(|| {
+            format!(
+                "Cannot write pending update at `{}`",
+                pending_path.display()
+            )
+        })
+    }
+
+    fn remove(umbrella: &repo::Repo) -> Result<()> {
+        let pending_path = Self::path(umbrella);
+        fs::remove_file(&pending_path).with_context(|| {
+            format!(
+                "Cannot remove pending update at `{}`",
+                pending_path.display()
+            )
+        })
+    }
+}
+
+impl OrigHead {
+    fn capture(
+        path: &path::Path,
+        repo: &repo::Repo,
+        branch_name: &str,
+        pin: Option<&str>,
+    ) -> Result<Self> {
+        let commit = repo
+            .git_repo
+            .resolve_reference_from_short_name(branch_name)?
+            .peel_to_commit()?;
+        Ok(OrigHead {
+            branch: branch_name.to_owned(),
+            commit: commit.id().to_string(),
+            pin: pin.map(String::from),
+            autostashed: false,
+            head: journal::RepoState::capture(path, &repo.git_repo)?,
+        })
+    }
+
+    fn is_umbrella(&self) -> bool {
+        self.head.path == path::Path::new(".")
+    }
+
+    /// The branch or the pinned revision the repo is updated to.
+    fn target(&self) -> &str {
+        self.pin.as_deref().unwrap_or(&self.branch)
+    }
+
+    /// Whether the update moved the repo to `commit_hash`.
+    fn moved_to(&self, commit_hash: &str) -> bool {
+        match self.pin {
+            Some(_) => commit_hash != self.head.commit,
+            None => commit_hash != self.commit,
+        }
+    }
+
+    /// What the repo was updated to, for the umbrella commit message.
+    fn describe_update(&self, commit_hash: &str) -> String {
+        let short_hash = &commit_hash[..std::cmp::min(8, commit_hash.len())];
+        match &self.pin {
+            Some(pin) => format!("pinned to {} ({})", pin, short_hash),
+            None => format!("{} to {}", self.branch, short_hash),
+        }
+    }
+
+    /// Whether the update left the repo mid-merge or mid-rebase, or moved its
+    /// HEAD or `branch`.
+    fn was_moved(&self, repo: &repo::Repo) -> Result<bool> {
+        if repo.git_repo.state() != git2::RepositoryState::Clean {
+            return Ok(true);
+        }
+        let branch_commit = repo
+            .git_repo
+            .resolve_reference_from_short_name(&self.branch)?
+            .peel_to_commit()?
+            .id();
+        let head = journal::RepoState::capture(&self.head.path, &repo.git_repo)?;
+        Ok(branch_commit.to_string() != self.commit || !head.same_head(&self.head))
+    }
+
+    /// Cancel the merge or rebase in progress, move `branch` back and check
+    /// out what HEAD pointed to. Like `git reset --merge`, only the files the
+    /// update touched are restored, so unrelated changes in the work tree are
+    /// kept.
+    fn restore(&self, repo: &repo::Repo) -> Result<()> {
+        let head_commit = repo.git_repo.find_commit(self.head.oid()?)?;
+        let head_tree = head_commit.tree()?;
+
+        // Files the update changed, committed or not, and the conflicted ones
+        let mut touched = repo.conflicted_paths()?;
+        let diff = repo.git_repo.diff_tree_to_index(
+            Some(&head_tree),
+            Some(&repo.git_repo.index()?),
+            None,
+        )?;
+        for delta in diff.deltas() {
+            touched.extend(delta.old_file().path().map(path::Path::to_path_buf));
+            touched.extend(delta.new_file().path().map(path::Path::to_path_buf));
+        }
+
+        repo.abort_merge()?;
+
+        let branch_oid = git2::Oid::from_str(&self.commit)?;
+        repo.git_repo.reference(
+            &format!("refs/heads/{}", self.branch),
+            branch_oid,
+            true,
+            "wok update --abort",
+        )?;
+        if self.head.is_detached() {
+            repo.git_repo.set_head_detached(head_commit.id())?;
+        } else {
+            repo.git_repo.set_head(&self.head.head)?;
+        }
+        repo.git_repo
+            .reset(head_commit.as_object(), git2::ResetType::Mixed, None)?;
+
+        if !touched.is_empty() {
+            let mut checkout = git2::build::CheckoutBuilder::new();
+            checkout
+                .force()
+                .remove_untracked(true)
+                .disable_pathspec_match(true);
+            for path in &touched {
+                checkout.path(path);
+            }
+            repo.git_repo
+                .checkout_tree(head_tree.as_object(), Some(&mut checkout))?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the work tree or the index of `repo` has uncommitted changes to
+/// tracked files.
+fn has_changes(repo: &repo::Repo) -> Result<bool> {
+    let mut options = git2::StatusOptions::new();
+    options.include_untracked(false);
+    Ok(!repo.git_repo.statuses(Some(&mut options))?.is_empty())
+}
+
+/// The repo a pending update entry is about, with its label for the output.
+fn pending_repo<'a>(
+    umbrella: &'a repo::Repo,
+    orig_head: &OrigHead,
+) -> Result<(&'a repo::Repo, String)> {
+    if orig_head.is_umbrella() {
+        return Ok((umbrella, String::from("umbrella")));
+    }
+    let subrepo = umbrella
+        .get_subrepo_by_path(&orig_head.head.path)
+        .with_context(|| {
+            format!(
+                "Cannot find subrepo `{}` of the pending update",
+                orig_head.head.path.display()
+            )
+        })?;
+    Ok((subrepo, orig_head.head.path.display().to_string()))
+}
+
+fn report_pending<V: Serialize>(
+    report: &mut output::Report,
+    orig_head: &OrigHead,
+    field: &str,
+    value: V,
+) {
+    if orig_head.is_umbrella() {
+        report.set_umbrella(field, value);
+    } else {
+        report.set(&orig_head.head.path, field, value);
+    }
+}
+
+/// Print what `update` would do, fetching the remotes but leaving the branches,
+/// the work trees and the umbrella index untouched.
+fn plan_update<W: Write>(
+    wok_config: &config::Config,
+    umbrella: &repo::Repo,
+    stdout: &mut W,
+    report: &mut output::Report,
+    options: &UpdateOptions,
+) -> Result<()> {
+    let UpdateOptions {
+        no_commit,
+        include_umbrella,
+        jobs,
+        groups,
+        pull,
+        to,
+        repos: target_repos,
+        ..
+    } = *options;
+    writeln!(stdout, "Planning repository updates...")?;
+    report.summarize("dry_run", true);
+
+    let mut saw_conflicts = false;
+
+    if include_umbrella {
+        let (merge_result, remote_oid) = umbrella.plan_merge(&umbrella.head, pull)?;
+        saw_conflicts |= report_plan(
+            stdout,
+            "umbrella",
+            &umbrella.head,
+            &merge_result,
+            remote_oid,
+        )?;
+
+        report.set_umbrella("branch", &umbrella.head);
+        report.set_umbrella("result", &merge_result);
+    }
+
+    let repos_to_update = wok_config.select_repos(
+        "update",
+        &umbrella.head,
+        target_repos.is_empty(),
+        groups,
+        target_repos,
+    );
+
+    let subrepos: Vec<(&config::Repo, &repo::Repo)> = repos_to_update
+        .iter()
+        .filter_map(|config_repo| {
+            umbrella
+                .get_subrepo_by_path(&config_repo.path)
+                .map(|subrepo| (config_repo, subrepo))
+        })
+        .collect();
+
+    let plans = super::parallel::for_each_repo(
+        &subrepos,
+        jobs,
+        |config_repo, subrepo| match to.or(config_repo.pin()) {
+            Some(rev) => subrepo.plan_pin(&config_repo.head, rev),
+            None => subrepo.plan_merge(&config_repo.head, pull.or(config_repo.pull)),
+        },
+    );
+
+    let mut planned = HashMap::new();
+    let mut rewritten = Vec::new();
+    let mut updated_repos = HashMap::new();
+    for ((config_repo, subrepo), plan) in subrepos.iter().zip(plans) {
+        let label = config_repo.path.display().to_string();
+        let (merge_result, remote_oid) = plan?;
+        let pin = to.or(config_repo.pin());
+        saw_conflicts |= report_plan(
+            stdout,
+            &label,
+            pin.unwrap_or(&config_repo.head),
+            &merge_result,
+            remote_oid,
+        )?;
+
+        report.set(&config_repo.path, "branch", &config_repo.head);
+        if let Some(pin) = pin {
+            report.set(&config_repo.path, "pin", pin);
+        }
+        report.set(&config_repo.path, "result", &merge_result);
+
+        let branch_oid = subrepo
+            .git_repo
+            .resolve_reference_from_short_name(&config_repo.head)?
+            .peel_to_commit()?
+            .id();
+        let short_hash = |oid: git2::Oid| oid.to_string()[..8].to_string();
+        match (&merge_result, remote_oid) {
+            (repo::MergeResult::Pinned, Some(oid)) => {
+                planned.insert(config_repo.path.clone(), oid);
+                updated_repos.insert(
+                    config_repo.path.clone(),
+                    format!(
+                        "pinned to {} ({})",
+                        pin.unwrap_or_default(),
+                        short_hash(oid)
+                    ),
+                );
+            },
+            (repo::MergeResult::UpToDate, Some(oid)) if pin.is_some() => {
+                planned.insert(config_repo.path.clone(), oid);
+            },
+            (repo::MergeResult::FastForward, Some(oid)) => {
+                planned.insert(config_repo.path.clone(), oid);
+                updated_repos.insert(
+                    config_repo.path.clone(),
+                    format!("{} to {}", config_repo.head, short_hash(oid)),
+                );
+            },
+            (repo::MergeResult::Merged | repo::MergeResult::Rebased, Some(oid)) => {
+                rewritten.push(config_repo.path.clone());
+                updated_repos.insert(
+                    config_repo.path.clone(),
+                    format!("{} with {}", config_repo.head, short_hash(oid)),
+                );
+            },
+            _ => {
+                planned.insert(config_repo.path.clone(), branch_oid);
+            },
+        }
+    }
+
+    let mut changes = dry_run::gitlink_changes(umbrella, &planned, false)?;
+    changes.extend(rewritten);
+
+    report.summarize("updated", updated_repos.len());
+    report.summarize("conflicts", saw_conflicts);
+    report.summarize("committed", false);
+
+    if saw_conflicts {
+        writeln!(
+            stdout,
+            "Would skip committing umbrella repo due to merge conflicts"
+        )?;
+        return Ok(());
+    }
+
+    if changes.is_empty() {
+        writeln!(stdout, "No submodule updates detected; nothing to commit")?;
+        return Ok(());
+    }
+
+    if no_commit {
+        writeln!(
+            stdout,
+            "Changes would be staged; commit skipped because --no-commit was provided"
+        )?;
+        return Ok(());
+    }
+
+    let changed_submodules: Vec<(String, String)> = changes
+        .iter()
+        .filter_map(|path| {
+            updated_repos
+                .get(path)
+                .map(|info| (path.to_string_lossy().to_string(), info.clone()))
+        })
+        .collect();
+    dry_run::write_commit_message(
+        stdout,
+        &format_update_commit_message(&changed_submodules),
+    )?;
+    report.summarize("committed", true);
     Ok(())
 }
 
-fn update_repo<W: Write>(
+/// Print the planned update of a repo and return whether it would conflict.
+fn report_plan<W: Write>(
+    stdout: &mut W,
+    label: &str,
+    branch_name: &str,
+    merge_result: &repo::MergeResult,
+    remote_oid: Option<git2::Oid>,
+) -> Result<bool> {
+    let remote_commit = remote_oid
+        .map(|oid| oid.to_string()[..8].to_string())
+        .unwrap_or_default();
+
+    match merge_result {
+        repo::MergeResult::UpToDate => {
+            writeln!(
+                stdout,
+                "- '{}': already up to date on '{}'",
+                label, branch_name
+            )?;
+        },
+        repo::MergeResult::FastForward => {
+            writeln!(
+                stdout,
+                "- '{}': would fast-forward '{}' to {}",
+                label, branch_name, remote_commit
+            )?;
+        },
+        repo::MergeResult::Merged => {
+            writeln!(
+                stdout,
+                "- '{}': would merge {} into '{}'",
+                label, remote_commit, branch_name
+            )?;
+        },
+        repo::MergeResult::Rebased => {
+            writeln!(
+                stdout,
+                "- '{}': would rebase '{}' onto {}",
+                label, branch_name, remote_commit
+            )?;
+        },
+        repo::MergeResult::Conflicts => {
+            writeln!(
+                stdout,
+                "- '{}': merging {} into '{}' would conflict, manual resolution required",
+                label, remote_commit, branch_name
+            )?;
+            return Ok(true);
+        },
+        repo::MergeResult::Diverged => {
+            writeln!(
+                stdout,
+                "- '{}': '{}' has diverged from {}, would not fast-forward it",
+                label, branch_name, remote_commit
+            )?;
+        },
+        repo::MergeResult::Pinned => {
+            writeln!(
+                stdout,
+                "- '{}': would pin to '{}' ({})",
+                label, branch_name, remote_commit
+            )?;
+        },
+    }
+
+    Ok(false)
+}
+
+fn update_repo(
     repo: &repo::Repo,
     branch_name: &str,
-    label: &str,
-    stdout: &mut W,
-) -> Result<(bool, bool)> {
+    pull: Option<config::PullStrategy>,
+) -> Result<repo::MergeResult> {
     // Switch to the desired branch first
     repo.switch(branch_name)?;
 
     // Attempt to merge with remote changes
-    let merge_result = repo.merge(branch_name)?;
+    repo.merge(branch_name, pull)
+}
 
+fn report_update<W: Write>(
+    stdout: &mut W,
+    repo: &repo::Repo,
+    branch_name: &str,
+    label: &str,
+    merge_result: &repo::MergeResult,
+) -> Result<(bool, bool)> {
     // Get the current commit hash for reporting
     let current_commit = get_current_commit_hash(&repo.git_repo)?;
     let short_commit = &current_commit[..std::cmp::min(8, current_commit.len())];
@@ -139,6 +927,25 @@ fn update_repo<W: Write>(
                 "- '{}': merge conflicts in '{}' ({}), manual resolution required",
                 label, branch_name, short_commit
             )?;
+            for path in repo.conflicted_paths()? {
+                writeln!(stdout, "    - '{}'", path.display())?;
+            }
+        },
+        repo::MergeResult::Diverged => {
+            writeln!(
+                stdout,
+                "- '{}': '{}' has diverged from its remote branch, not fast-forwarding \
+                 ({})",
+                label, branch_name, short_commit
+            )?;
+        },
+        repo::MergeResult::Pinned => {
+            updated = true;
+            writeln!(
+                stdout,
+                "- '{}': pinned to '{}' ({})",
+                label, branch_name, short_commit
+            )?;
         },
     }
 
@@ -179,7 +986,7 @@ fn stage_submodule_changes(git_repo: &git2::Repository) -> Result<bool> {
 
 fn commit_submodule_updates(
     git_repo: &git2::Repository,
-    updated_repos: &[(String, String, String)], // (name, branch, commit_hash)
+    updated_repos: &[(String, String)], // (name, what it was updated to)
 ) -> Result<()> {
     let signature = git_repo.signature()?;
     let tree_id = git_repo.index()?.write_tree()?;
@@ -210,7 +1017,7 @@ fn build_update_commit_message(
     git_repo: &git2::Repository,
     parent_tree: &git2::Tree,
     index_tree: &git2::Tree,
-    updated_repos: &[(String, String, String)], // (name, branch, commit_hash)
+    updated_repos: &[(String, String)], // (name, what it was updated to)
 ) -> Result<String> {
     // Get diff between parent tree and staged index
     let diff = git_repo.diff_tree_to_tree(Some(parent_tree), Some(index_tree), None)?;
@@ -220,7 +1027,7 @@ fn build_update_commit_message(
     // Build a map of updated repos for quick lookup
     let updated_map: std::collections::HashMap<_, _> = updated_repos
         .iter()
-        .map(|(name, branch, hash)| (name.clone(), (branch.clone(), hash.clone())))
+        .map(|(name, info)| (name.clone(), info.clone()))
         .collect();
 
     // Iterate through changed files in the diff
@@ -233,12 +1040,8 @@ fn build_update_commit_message(
                     let submodule_name = submodule.path().to_string_lossy().to_string();
 
                     // Check if this was one of the updated repos
-                    if let Some((branch, hash)) = updated_map.get(&submodule_name) {
-                        let short_hash = &hash[..std::cmp::min(8, hash.len())];
-                        changed_submodules.push((
-                            submodule_name,
-                            format!("{} to {}", branch, short_hash),
-                        ));
+                    if let Some(info) = updated_map.get(&submodule_name) {
+                        changed_submodules.push((submodule_name, info.clone()));
                     }
                 },
                 std::result::Result::Err(_) => continue,
@@ -246,15 +1049,21 @@ fn build_update_commit_message(
         }
     }
 
-    // Build the commit message
+    let message = format_update_commit_message(&changed_submodules);
+    std::result::Result::Ok(message)
+}
+
+/// Format the update commit message from the updated submodules and where they
+/// were updated to.
+fn format_update_commit_message(changed_submodules: &[(String, String)]) -> String {
     let mut message = String::from("Update submodules to latest");
 
     if !changed_submodules.is_empty() {
         message.push_str("\n\nUpdated submodules:");
-        for (name, info) in &changed_submodules {
+        for (name, info) in changed_submodules {
             message.push_str(&format!("\n- {}: {}", name, info));
         }
     }
 
-    std::result::Result::Ok(message)
+    message
 }
@@ -1,8 +1,10 @@
 use anyhow::*;
 use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{fs, path};
 
 const CONFIG_CURRENT_VERSION: &str = "1.0";
+const LOCK_CURRENT_VERSION: &str = "1.0";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
@@ -11,6 +13,31 @@ pub struct Repo {
     pub head: String,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub skip_for: Vec<String>,
+    /// Named groups the repo belongs to, for selecting it with `--group`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    /// How `update` pulls remote changes, overriding the git config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull: Option<PullStrategy>,
+    /// Revision `update` checks the repo out at instead of its branch tip.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// Tag `update` checks the repo out at instead of its branch tip.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+/// How remote changes are brought into a branch that has diverged from its
+/// remote-tracking branch.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PullStrategy {
+    /// Only fast-forward, leaving diverged branches as they are
+    #[serde(rename = "ff-only")]
+    FastForwardOnly,
+    #[serde(rename = "rebase")]
+    Rebase,
+    #[serde(rename = "merge")]
+    Merge,
 }
 
 /// Config schema for `wok.toml`
@@ -21,15 +48,58 @@ pub struct Repo {
 #[serde(deny_unknown_fields)]
 pub struct Config {
     pub version: String,
+    /// Number of repos processed concurrently by commands that support it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+    /// Make `switch` roll back every repo when one of them fails to switch.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub atomic_switch: bool,
+    /// Prefix of the version tags made by `tag --bump`, e.g. `v`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_prefix: Option<String>,
     #[serde(rename = "repo")]
     pub repos: Vec<Repo>,
+    /// Lock file found next to the wok file, if any.
+    #[serde(skip)]
+    pub lock: Option<Lock>,
+    /// Repos of the lock file that are not in the wok file, which were left
+    /// out of `lock` when loading.
+    #[serde(skip)]
+    pub stale_locked: Vec<path::PathBuf>,
+}
+
+/// Exact state of the subrepos recorded by `wok lock --file`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Lock {
+    pub version: String,
+    #[serde(default, rename = "repo")]
+    pub repos: Vec<LockedRepo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LockedRepo {
+    pub path: path::PathBuf,
+    pub branch: String,
+    pub commit: String,
+    /// URL of the remote the branch tracks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// When the repo was locked at this commit.
+    pub locked_at: toml::value::Datetime,
 }
 
 impl Config {
     pub fn new() -> Self {
         Config {
             version: String::from(CONFIG_CURRENT_VERSION),
+            jobs: None,
+            atomic_switch: false,
+            tag_prefix: None,
             repos: vec![],
+            lock: None,
+            stale_locked: vec![],
         }
     }
 
@@ -44,6 +114,10 @@ impl Config {
             path: path::PathBuf::from(path),
             head: String::from(head),
             skip_for: vec![],
+            groups: vec![],
+            pull: None,
+            rev: None,
+            tag: None,
         });
         true
     }
@@ -59,6 +133,9 @@ impl Config {
             removed = true;
             false
         });
+        if let Some(lock) = &mut self.lock {
+            lock.repos.retain(|locked_repo| locked_repo.path != path);
+        }
         removed
     }
 
@@ -90,6 +167,17 @@ impl Config {
         let mut config: Config = toml::from_str(&Self::read(config_path)?)
             .context("Cannot parse the wok file")?;
 
+        if let Some(repo) = config
+            .repos
+            .iter()
+            .find(|repo| repo.rev.is_some() && repo.tag.is_some())
+        {
+            bail!(
+                "Repo `{}` is pinned with both `rev` and `tag`; keep only one of them",
+                repo.path.display()
+            );
+        }
+
         // Migrate from 1.0-experimental to 1.0
         if config.version == "1.0-experimental" {
             config.version = String::from("1.0");
@@ -98,6 +186,16 @@ impl Config {
                 .context("Cannot save migrated wok file")?;
         }
 
+        let lock_path = Lock::path_for(config_path);
+        if lock_path.exists() {
+            let mut lock = Lock::load(&lock_path)?;
+            lock.validate().with_context(|| {
+                format!("Invalid lock file `{}`", lock_path.display())
+            })?;
+            config.stale_locked = lock.prune(&config);
+            config.lock = Some(lock);
+        }
+
         Ok(config)
     }
 
@@ -106,9 +204,13 @@ impl Config {
         fs::read_to_string(config_path).context("Cannot read the wok file")
     }
 
-    /// Saves the workspace config to a file.
+    /// Saves the workspace config to a file, along with the lock file if there
+    /// is one, so that removed repos are dropped from it too.
     pub fn save(&self, config_path: &path::Path) -> Result<()> {
         fs::write(config_path, self.dump()?).context("Cannot save the wok file")?;
+        if let Some(lock) = &self.lock {
+            lock.save(&Lock::path_for(config_path))?;
+        }
         Ok(())
     }
 
@@ -117,6 +219,45 @@ impl Config {
         Ok(toml::to_string(self).context("Cannot serialize config")?)
     }
 
+    /// Selects the repos a command acts on.
+    ///
+    /// With `groups` the members of any of the groups are selected, with `all`
+    /// every configured repo except those opted out of `command` via
+    /// `skip_for`, and otherwise the repos whose head matches the umbrella's
+    /// head, unless `target_repos` are given. Explicitly targeted repos are
+    /// always selected.
+    pub fn select_repos(
+        &self,
+        command: &str,
+        umbrella_head: &str,
+        all: bool,
+        groups: &[String],
+        target_repos: &[path::PathBuf],
+    ) -> Vec<Repo> {
+        self.repos
+            .iter()
+            .filter(|repo| {
+                if target_repos.contains(&repo.path) {
+                    return true;
+                }
+
+                if !groups.is_empty() {
+                    groups.iter().any(|group| repo.in_group(group))
+                } else if all {
+                    !repo.is_skipped_for(command)
+                } else {
+                    target_repos.is_empty() && repo.head == umbrella_head
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Returns whether any configured repo belongs to the `group`.
+    pub fn has_group(&self, group: &str) -> bool {
+        self.repos.iter().any(|repo| repo.in_group(group))
+    }
+
     fn has_repo_path(&self, path: &path::Path) -> bool {
         assert!(!path.is_absolute());
         self.repos.iter().any(|r| r.path == path)
@@ -129,6 +270,101 @@ impl Repo {
             .iter()
             .any(|skip| skip.eq_ignore_ascii_case(command))
     }
+
+    pub fn in_group(&self, group: &str) -> bool {
+        self.groups.iter().any(|name| name == group)
+    }
+
+    /// Revision the repo is pinned to with `tag` or `rev`, if any.
+    pub fn pin(&self) -> Option<&str> {
+        self.tag.as_deref().or(self.rev.as_deref())
+    }
+}
+
+impl Lock {
+    pub fn new() -> Self {
+        Lock {
+            version: String::from(LOCK_CURRENT_VERSION),
+            repos: vec![],
+        }
+    }
+
+    /// Path of the lock file belonging to the wok file at `config_path`, e.g.
+    /// `wok.lock` for `wok.toml`.
+    pub fn path_for(config_path: &path::Path) -> path::PathBuf {
+        config_path.with_extension("lock")
+    }
+
+    /// Loads the lock from a file at the `lock_path`.
+    pub fn load(lock_path: &path::Path) -> Result<Lock> {
+        let lock: Lock = toml::from_str(
+            &fs::read_to_string(lock_path).context("Cannot read the lock file")?,
+        )
+        .context("Cannot parse the lock file")?;
+
+        if lock.version != LOCK_CURRENT_VERSION {
+            bail!("Unsupported lock file version `{}`", lock.version);
+        }
+
+        Ok(lock)
+    }
+
+    /// Saves the lock to a file.
+    pub fn save(&self, lock_path: &path::Path) -> Result<()> {
+        fs::write(lock_path, self.dump()?).context("Cannot save the lock file")?;
+        Ok(())
+    }
+
+    /// Returns the lock as TOML string.
+    pub fn dump(&self) -> Result<String> {
+        Ok(toml::to_string(self).context("Cannot serialize lock")?)
+    }
+
+    pub fn get(&self, path: &path::Path) -> Option<&LockedRepo> {
+        self.repos
+            .iter()
+            .find(|locked_repo| locked_repo.path == path)
+    }
+
+    /// Checks that every repo is locked at a valid commit id.
+    pub fn validate(&self) -> Result<()> {
+        for locked_repo in &self.repos {
+            locked_repo.oid()?;
+        }
+        Ok(())
+    }
+
+    /// Drops the locked repos that are not in the wok `config` any more and
+    /// returns their paths.
+    pub fn prune(&mut self, config: &Config) -> Vec<path::PathBuf> {
+        let mut stale = vec![];
+        self.repos.retain(|locked_repo| {
+            if config.has_repo_path(&locked_repo.path) {
+                return true;
+            }
+            stale.push(locked_repo.path.clone());
+            false
+        });
+        stale
+    }
+}
+
+impl LockedRepo {
+    pub fn oid(&self) -> Result<git2::Oid> {
+        git2::Oid::from_str(&self.commit).with_context(|| {
+            format!(
+                "Invalid commit `{}` locked for `{}`",
+                self.commit,
+                self.path.display()
+            )
+        })
+    }
+}
+
+impl Default for Lock {
+    fn default() -> Self {
+        Lock::new()
+    }
 }
 
 impl Default for Config {
@@ -136,3 +372,78 @@ impl Default for Config {
         Config::new()
     }
 }
+
+/// Convert `time` to a UTC TOML datetime with second precision.
+pub(crate) fn timestamp(time: SystemTime) -> Result<toml::value::Datetime> {
+    let seconds = time.duration_since(UNIX_EPOCH)?.as_secs();
+    let (days, seconds_of_day) = ((seconds / 86_400) as i64, seconds % 86_400);
+
+    // Civil date from days since the epoch, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    Ok(toml::value::Datetime {
+        date: Some(toml::value::Date {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        }),
+        time: Some(toml::value::Time {
+            hour: (seconds_of_day / 3_600) as u8,
+            minute: (seconds_of_day % 3_600 / 60) as u8,
+            second: (seconds_of_day % 60) as u8,
+            nanosecond: 0,
+        }),
+        offset: Some(toml::value::Offset::Z),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn timestamp_at(seconds: u64) -> String {
+        timestamp(UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn timestamp_starts_at_epoch() {
+        assert_eq!(timestamp_at(0), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn timestamp_handles_leap_years() {
+        assert_eq!(timestamp_at(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(timestamp_at(951_868_800), "2000-03-01T00:00:00Z");
+        assert_eq!(timestamp_at(1_709_209_845), "2024-02-29T12:30:45Z");
+        // Centuries are only leap years every 400 years
+        assert_eq!(timestamp_at(4_107_542_399), "2100-02-28T23:59:59Z");
+        assert_eq!(timestamp_at(4_107_542_400), "2100-03-01T00:00:00Z");
+    }
+
+    #[test]
+    fn timestamp_rolls_over_month_and_year() {
+        assert_eq!(timestamp_at(1_704_067_199), "2023-12-31T23:59:59Z");
+        assert_eq!(timestamp_at(1_704_067_200), "2024-01-01T00:00:00Z");
+        assert_eq!(timestamp_at(1_706_745_599), "2024-01-31T23:59:59Z");
+        assert_eq!(timestamp_at(1_706_745_600), "2024-02-01T00:00:00Z");
+    }
+}
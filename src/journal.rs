@@ -0,0 +1,273 @@
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::result::Result::Ok;
+use std::time::SystemTime;
+use std::{fs, path};
+
+use crate::{config, repo};
+
+/// Number of journal entries kept; older ones are pruned when recording.
+pub const JOURNAL_LIMIT: usize = 50;
+
+/// State of the workspace recorded before a mutating command ran.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    /// Command line the entry was recorded for, without the binary name.
+    pub command: String,
+    pub recorded_at: toml::value::Datetime,
+    /// Previous contents of the wok file.
+    pub config: String,
+    pub umbrella: RepoState,
+    #[serde(default, rename = "repo")]
+    pub repos: Vec<RepoState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RepoState {
+    pub path: path::PathBuf,
+    /// Full name of the ref HEAD pointed to, or `HEAD` if it was detached.
+    pub head: String,
+    pub commit: String,
+    /// Commits of the local branches, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub branches: BTreeMap<String, String>,
+    /// Stashes made by `--autostash`, newest first.
+    #[serde(default, rename = "stash", skip_serializing_if = "Vec::is_empty")]
+    pub stashes: Vec<StashState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StashState {
+    pub message: String,
+    pub commit: String,
+}
+
+impl RepoState {
+    pub fn capture(path: &path::Path, git_repo: &git2::Repository) -> Result<Self> {
+        let head = git_repo.head().with_context(|| {
+            format!("Cannot find the head of repo at `{}`", path.display())
+        })?;
+        let commit = head.peel_to_commit()?.id().to_string();
+        let head = if git_repo.head_detached()? {
+            String::from("HEAD")
+        } else {
+            head.name()
+                .with_context(|| {
+                    format!(
+                        "Cannot resolve the head reference for repo at `{}`",
+                        path.display()
+                    )
+                })?
+                .to_owned()
+        };
+
+        let mut branches = BTreeMap::new();
+        for branch in git_repo.branches(Some(git2::BranchType::Local))? {
+            let (branch, _) = branch?;
+            if let (Some(name), Some(oid)) = (branch.name()?, branch.get().target()) {
+                branches.insert(name.to_owned(), oid.to_string());
+            }
+        }
+
+        // The stash reflog lists the stashes newest first
+        let mut stashes = vec![];
+        if let Ok(reflog) = git_repo.reflog("refs/stash") {
+            for reflog_entry in reflog.iter() {
+                let message = reflog_entry.message().unwrap_or_default();
+                if message.contains(repo::STASH_PREFIX) {
+                    stashes.push(StashState {
+                        message: message.to_owned(),
+                        commit: reflog_entry.id_new().to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(RepoState {
+            path: path.to_path_buf(),
+            head,
+            commit,
+            branches,
+            stashes,
+        })
+    }
+
+    /// Whether `other` has HEAD at the same ref and commit.
+    pub fn same_head(&self, other: &RepoState) -> bool {
+        self.head == other.head && self.commit == other.commit
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self.head == "HEAD"
+    }
+
+    /// Short name of the recorded branch, or `HEAD` if it was detached.
+    pub fn branch(&self) -> &str {
+        self.head.strip_prefix("refs/heads/").unwrap_or(&self.head)
+    }
+
+    pub fn oid(&self) -> Result<git2::Oid> {
+        git2::Oid::from_str(&self.commit).with_context(|| {
+            format!(
+                "Invalid commit `{}` recorded for `{}`",
+                self.commit,
+                self.path.display()
+            )
+        })
+    }
+}
+
+impl Entry {
+    /// Capture the current state of the umbrella, its subrepos and the wok file.
+    pub fn capture(
+        umbrella_dir: &path::Path,
+        config_path: &path::Path,
+        command: &str,
+    ) -> Result<Self> {
+        let git_repo = git2::Repository::open(umbrella_dir).with_context(|| {
+            format!("Cannot open repo at `{}`", umbrella_dir.display())
+        })?;
+        let umbrella = RepoState::capture(path::Path::new("."), &git_repo)?;
+
+        let mut repos = vec![];
+        for submodule in git_repo.submodules()? {
+            // Uninitialized submodules have no state to restore
+            if let Ok(subrepo) = submodule.open()
+                && let Ok(state) = RepoState::capture(submodule.path(), &subrepo)
+            {
+                repos.push(state);
+            }
+        }
+
+        let config = fs::read_to_string(config_path).with_context(|| {
+            format!("Cannot read wok file at `{}`", config_path.display())
+        })?;
+
+        Ok(Entry {
+            command: String::from(command),
+            recorded_at: config::timestamp(SystemTime::now())?,
+            config,
+            umbrella,
+            repos,
+        })
+    }
+
+    /// Whether `other` records the same workspace state, regardless of when
+    /// and for which command.
+    pub fn same_state(&self, other: &Entry) -> bool {
+        self.config == other.config
+            && self.umbrella == other.umbrella
+            && self.repos == other.repos
+    }
+}
+
+/// Journal of workspace states kept in the umbrella's git dir, one file per
+/// entry.
+pub struct Journal {
+    dir: path::PathBuf,
+}
+
+impl Journal {
+    pub fn open(umbrella: &repo::Repo) -> Self {
+        Journal {
+            dir: umbrella.git_repo.path().join("wok").join("journal"),
+        }
+    }
+
+    /// Record the current workspace state before running `command` and return
+    /// the id of the new entry.
+    pub fn record(
+        &self,
+        umbrella: &repo::Repo,
+        config_path: &path::Path,
+        command: &str,
+    ) -> Result<u64> {
+        let entry = Entry::capture(&umbrella.work_dir, config_path, command)?;
+        let entries = self.entries()?;
+        let id = entries.last().map_or(1, |(id, _)| id + 1);
+
+        fs::create_dir_all(&self.dir).with_context(|| {
+            format!("Cannot create journal dir at `{}`", self.dir.display())
+        })?;
+        let entry_path = self.entry_path(id);
+        fs::write(&entry_path, toml::to_string(&entry)?).with_context(|| {
+            format!("Cannot write journal entry at `{}`", entry_path.display())
+        })?;
+
+        let excess = (entries.len() + 1).saturating_sub(JOURNAL_LIMIT);
+        for (old_id, _) in entries.iter().take(excess) {
+            self.remove(*old_id)?;
+        }
+
+        Ok(id)
+    }
+
+    /// Drop entry `id` if the workspace is still in the recorded state, so that
+    /// commands that changed nothing don't clutter the journal.
+    pub fn forget_if_unchanged(
+        &self,
+        id: u64,
+        umbrella: &repo::Repo,
+        config_path: &path::Path,
+    ) -> Result<()> {
+        let entry = self.load(id)?;
+        let current = Entry::capture(&umbrella.work_dir, config_path, &entry.command)?;
+        if entry.same_state(&current) {
+            self.remove(id)?;
+        }
+        Ok(())
+    }
+
+    /// All entries, oldest first.
+    pub fn entries(&self) -> Result<Vec<(u64, Entry)>> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut ids = vec![];
+        for dir_entry in fs::read_dir(&self.dir).with_context(|| {
+            format!("Cannot read journal dir at `{}`", self.dir.display())
+        })? {
+            let file_name = dir_entry?.file_name();
+            if let Some(id) = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".toml"))
+                .and_then(|stem| stem.parse::<u64>().ok())
+            {
+                ids.push(id);
+            }
+        }
+        ids.sort_unstable();
+
+        ids.into_iter().map(|id| Ok((id, self.load(id)?))).collect()
+    }
+
+    pub fn last(&self) -> Result<Option<(u64, Entry)>> {
+        Ok(self.entries()?.pop())
+    }
+
+    pub fn load(&self, id: u64) -> Result<Entry> {
+        let entry_path = self.entry_path(id);
+        let contents = fs::read_to_string(&entry_path).with_context(|| {
+            format!("Cannot read journal entry at `{}`", entry_path.display())
+        })?;
+        toml::from_str(&contents).with_context(|| {
+            format!("Cannot parse journal entry at `{}`", entry_path.display())
+        })
+    }
+
+    pub fn remove(&self, id: u64) -> Result<()> {
+        let entry_path = self.entry_path(id);
+        fs::remove_file(&entry_path).with_context(|| {
+            format!("Cannot remove journal entry at `{}`", entry_path.display())
+        })
+    }
+
+    fn entry_path(&self, id: u64) -> path::PathBuf {
+        self.dir.join(format!("{:06}.toml", id))
+    }
+}
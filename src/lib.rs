@@ -9,6 +9,8 @@
 
 pub mod cmd;
 pub mod config;
+pub mod journal;
+pub mod output;
 pub mod repo;
 
 pub const DEFAULT_CONFIG_NAME: &str = "wok.toml";
@@ -0,0 +1,149 @@
+use anyhow::*;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+
+/// Output format selected with the global `--format` option.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Human readable text
+    #[default]
+    Text,
+    /// A single JSON document per command
+    Json,
+    /// One JSON object per line for each repo, followed by a summary line
+    Ndjson,
+}
+
+/// Structured result of a command for machine-readable output.
+///
+/// Commands fill the report alongside their text output. The umbrella repo is
+/// recorded under the path `.`.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub command: String,
+    pub repos: Vec<RepoRecord>,
+    pub summary: Map<String, Value>,
+    /// Whether the command changed the Wokfile so that it has to be saved.
+    #[serde(skip)]
+    pub config_updated: bool,
+    /// Exit code the command asks for, `0` on success.
+    #[serde(skip)]
+    pub exit_code: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepoRecord {
+    pub path: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub umbrella: bool,
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+impl Report {
+    pub fn new(command: &str) -> Self {
+        Report {
+            command: String::from(command),
+            ..Default::default()
+        }
+    }
+
+    /// Set a field on the record for the subrepo at `path`, creating the record
+    /// on first use.
+    pub fn set<V: Serialize>(&mut self, path: &std::path::Path, field: &str, value: V) {
+        let path = path.to_string_lossy();
+        let index = match self
+            .repos
+            .iter()
+            .position(|record| !record.umbrella && record.path == path)
+        {
+            Some(index) => index,
+            None => {
+                self.repos.push(RepoRecord {
+                    path: path.into_owned(),
+                    umbrella: false,
+                    fields: Map::new(),
+                });
+                self.repos.len() - 1
+            },
+        };
+        self.repos[index].set(field, value);
+    }
+
+    /// Set a field on the umbrella repo record.
+    pub fn set_umbrella<V: Serialize>(&mut self, field: &str, value: V) {
+        let index = match self.repos.iter().position(|record| record.umbrella) {
+            Some(index) => index,
+            None => {
+                self.repos.push(RepoRecord {
+                    path: String::from("."),
+                    umbrella: true,
+                    fields: Map::new(),
+                });
+                self.repos.len() - 1
+            },
+        };
+        self.repos[index].set(field, value);
+    }
+
+    /// Set a field on the summary object.
+    pub fn summarize<V: Serialize>(&mut self, field: &str, value: V) {
+        self.summary.insert(String::from(field), to_value(value));
+    }
+}
+
+impl RepoRecord {
+    fn set<V: Serialize>(&mut self, field: &str, value: V) {
+        self.fields.insert(String::from(field), to_value(value));
+    }
+}
+
+fn to_value<V: Serialize>(value: V) -> Value {
+    // Reports only hold plain data, which always converts into a JSON value
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+/// Write `report` to `stdout` in the given format. Text output is written by
+/// the commands themselves, so nothing is written for [`Format::Text`].
+pub fn write_report<W: Write>(
+    stdout: &mut W,
+    format: Format,
+    report: &Report,
+) -> Result<()> {
+    match format {
+        Format::Text => {},
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *stdout, report)?;
+            writeln!(stdout)?;
+        },
+        Format::Ndjson => {
+            for record in &report.repos {
+                let mut line = Map::new();
+                line.insert(String::from("type"), Value::from("repo"));
+                line.insert(
+                    String::from("command"),
+                    Value::from(report.command.as_str()),
+                );
+                if let Value::Object(fields) = serde_json::to_value(record)? {
+                    line.extend(fields);
+                }
+                serde_json::to_writer(&mut *stdout, &line)?;
+                writeln!(stdout)?;
+            }
+
+            let mut line = Map::new();
+            line.insert(String::from("type"), Value::from("summary"));
+            line.insert(
+                String::from("command"),
+                Value::from(report.command.as_str()),
+            );
+            line.extend(report.summary.clone());
+            serde_json::to_writer(&mut *stdout, &line)?;
+            writeln!(stdout)?;
+        },
+    }
+
+    Ok(())
+}
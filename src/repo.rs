@@ -1,16 +1,24 @@
-use std::{fmt, path};
+use std::{fmt, fs, path};
 
 use anyhow::*;
 use git2::build::CheckoutBuilder;
+use serde::{Serialize, ser::SerializeStruct};
 use std::result::Result::Ok;
 
-#[derive(Debug, Clone, PartialEq)]
+use crate::config::PullStrategy;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MergeResult {
     UpToDate,
     FastForward,
     Merged,
     Rebased,
     Conflicts,
+    /// The branch can't be fast-forwarded and was left as it is
+    Diverged,
+    /// HEAD was detached at the revision the repo is pinned to
+    Pinned,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -22,6 +30,29 @@ pub enum RemoteComparison {
     NoRemote,
 }
 
+/// Serialized as `{"state": ..., "ahead": N, "behind": N}` so that consumers
+/// don't have to unpack the variant payloads.
+impl Serialize for RemoteComparison {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let (state, ahead, behind) = match self {
+            RemoteComparison::UpToDate => ("up_to_date", 0, 0),
+            RemoteComparison::Ahead(ahead) => ("ahead", *ahead, 0),
+            RemoteComparison::Behind(behind) => ("behind", 0, *behind),
+            RemoteComparison::Diverged(ahead, behind) => ("diverged", *ahead, *behind),
+            RemoteComparison::NoRemote => ("no_remote", 0, 0),
+        };
+
+        let mut state_struct = serializer.serialize_struct("RemoteComparison", 3)?;
+        state_struct.serialize_field("state", state)?;
+        state_struct.serialize_field("ahead", &ahead)?;
+        state_struct.serialize_field("behind", &behind)?;
+        state_struct.end()
+    }
+}
+
 pub struct Repo {
     pub git_repo: git2::Repository,
     pub work_dir: path::PathBuf,
@@ -37,29 +68,36 @@ impl Repo {
         let head = match head_name {
             Some(name) => String::from(name),
             None => {
-                if git_repo.head_detached().with_context(|| {
+                let detached = git_repo.head_detached().with_context(|| {
                     format!(
                         "Cannot determine head state for repo at `{}`",
                         work_dir.display()
                     )
-                })? {
+                })?;
+                if detached && let Some(branch_name) = rebased_branch(&git_repo) {
+                    // HEAD is only detached while the branch is being rebased
+                    branch_name
+                } else if detached && let Some(rev) = pinned_rev(&git_repo) {
+                    // Pinned repos are kept on a detached HEAD
+                    rev
+                } else if detached {
                     bail!(
                         "Cannot operate on a detached head for repo at `{}`",
                         work_dir.display()
                     )
+                } else {
+                    String::from(git_repo.head().with_context(|| {
+                        format!(
+                            "Cannot find the head branch for repo at `{}`. Is it detached?",
+                            work_dir.display()
+                        )
+                    })?.shorthand().with_context(|| {
+                        format!(
+                            "Cannot find a human readable representation of the head ref for repo at `{}`",
+                            work_dir.display(),
+                        )
+                    })?)
                 }
-
-                String::from(git_repo.head().with_context(|| {
-                    format!(
-                        "Cannot find the head branch for repo at `{}`. Is it detached?",
-                        work_dir.display()
-                    )
-                })?.shorthand().with_context(|| {
-                    format!(
-                        "Cannot find a human readable representation of the head ref for repo at `{}`",
-                        work_dir.display(),
-                    )
-                })?)
             },
         };
 
@@ -95,11 +133,107 @@ impl Repo {
     }
 
     pub fn switch(&self, head: &str) -> Result<()> {
-        self.git_repo.set_head(&self.resolve_reference(head)?)?;
-        self.git_repo.checkout_head(None)?;
+        let reference = self.resolve_reference(head)?;
+        let commit = self.git_repo.find_reference(&reference)?.peel_to_commit()?;
+
+        // Update the work tree before HEAD, so that the files that differ
+        // between the commits are replaced rather than left behind
+        self.checkout_commit(&commit, head)?;
+        self.git_repo.set_head(&reference)?;
+        self.unpin()?;
         Ok(())
     }
 
+    /// Check out `rev` on a detached HEAD, after fetching the remote that
+    /// `branch_name` tracks along with all of its tags. The revision is
+    /// recorded in the repo config, so that the repo still loads while
+    /// detached.
+    pub fn pin(&self, branch_name: &str, rev: &str) -> Result<MergeResult> {
+        self.fetch_tags(branch_name)?;
+        let commit = self.resolve_rev(rev)?;
+
+        let result = if self.is_pinned_at(&commit)? {
+            MergeResult::UpToDate
+        } else {
+            self.checkout_commit(&commit, rev)?;
+            self.git_repo.set_head_detached(commit.id())?;
+            MergeResult::Pinned
+        };
+
+        self.git_repo
+            .config()?
+            .open_level(git2::ConfigLevel::Local)?
+            .set_str(PIN_CONFIG_KEY, rev)?;
+        Ok(result)
+    }
+
+    /// Work out what `pin` would do without moving HEAD. Returns the result
+    /// along with the commit `rev` resolves to.
+    pub fn plan_pin(
+        &self,
+        branch_name: &str,
+        rev: &str,
+    ) -> Result<(MergeResult, Option<git2::Oid>)> {
+        self.fetch_tags(branch_name)?;
+        let commit = self.resolve_rev(rev)?;
+
+        let result = if self.is_pinned_at(&commit)? {
+            MergeResult::UpToDate
+        } else {
+            MergeResult::Pinned
+        };
+        Ok((result, Some(commit.id())))
+    }
+
+    fn is_pinned_at(&self, commit: &git2::Commit) -> Result<bool> {
+        Ok(self.git_repo.head_detached()?
+            && self.git_repo.head()?.peel_to_commit()?.id() == commit.id())
+    }
+
+    /// Forget the revision the repo was pinned to, once HEAD is back on a
+    /// branch.
+    fn unpin(&self) -> Result<()> {
+        let mut config = self
+            .git_repo
+            .config()?
+            .open_level(git2::ConfigLevel::Local)?;
+        match config.remove(PIN_CONFIG_KEY) {
+            Ok(()) => Ok(()),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn resolve_rev(&self, rev: &str) -> Result<git2::Commit<'_>> {
+        self.git_repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| {
+                format!(
+                    "Cannot find revision '{}' in repo at `{}`",
+                    rev,
+                    self.work_dir.display()
+                )
+            })
+    }
+
+    fn checkout_commit(&self, commit: &git2::Commit, name: &str) -> Result<()> {
+        self.git_repo
+            .checkout_tree(commit.as_object(), None)
+            .map_err(|err| {
+                if err.code() == git2::ErrorCode::Conflict {
+                    anyhow!(
+                        "Uncommitted changes in repo at `{}` would be overwritten by \
+                         checking out '{}'; commit or stash them, or use --autostash",
+                        self.work_dir.display(),
+                        name
+                    )
+                } else {
+                    err.into()
+                }
+            })
+    }
+
     pub fn fetch(&self) -> Result<()> {
         // Get the remote for the current branch
         let head_ref = self.git_repo.head()?;
@@ -110,6 +244,11 @@ impl Repo {
             )
         })?;
 
+        self.fetch_branch(branch_name)
+    }
+
+    /// Fetch the remote that `branch_name` tracks, if any.
+    pub fn fetch_branch(&self, branch_name: &str) -> Result<()> {
         let tracking = match self.tracking_branch(branch_name)? {
             Some(tracking) => tracking,
             None => {
@@ -118,11 +257,36 @@ impl Repo {
             },
         };
 
+        self.fetch_remote(&tracking.remote)
+    }
+
+    /// Fetch the remote that `branch_name` tracks along with all of its tags,
+    /// not only the ones pointing into the fetched branches.
+    pub fn fetch_tags(&self, branch_name: &str) -> Result<()> {
+        match self.tracking_branch(branch_name)? {
+            Some(tracking) => {
+                self.fetch_remote_impl(&tracking.remote, git2::AutotagOption::All)
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Fetch `remote_name` with its configured refspecs, if the remote exists.
+    pub fn fetch_remote(&self, remote_name: &str) -> Result<()> {
+        self.fetch_remote_impl(remote_name, git2::AutotagOption::Unspecified)
+    }
+
+    fn fetch_remote_impl(
+        &self,
+        remote_name: &str,
+        tags: git2::AutotagOption,
+    ) -> Result<()> {
         // Check if remote exists
-        match self.git_repo.find_remote(&tracking.remote) {
+        match self.git_repo.find_remote(remote_name) {
             Ok(mut remote) => {
                 let mut fetch_options = git2::FetchOptions::new();
                 fetch_options.remote_callbacks(self.remote_callbacks()?);
+                fetch_options.download_tags(tags);
 
                 remote
                     .fetch::<&str>(&[], Some(&mut fetch_options), None)
@@ -138,7 +302,7 @@ impl Repo {
                             \n\
                             Try running: git fetch --verbose\n\
                             Or check authentication with: git-wok test-auth",
-                            tracking.remote,
+                            remote_name,
                             self.work_dir.display()
                         )
                     })?;
@@ -152,19 +316,92 @@ impl Repo {
         Ok(())
     }
 
+    /// Stash the uncommitted changes to tracked files before `operation`, with
+    /// a message marking the stash as made by wok. Returns the stash commit,
+    /// or `None` if there was nothing to stash.
+    pub fn autostash(&self, operation: &str) -> Result<Option<git2::Oid>> {
+        let mut git_repo = git2::Repository::open(&self.work_dir)?;
+        let signature = git_repo
+            .signature()
+            .or_else(|_| git2::Signature::now("git-wok", "git-wok@localhost"))?;
+        let message = format!("{} before {}", STASH_PREFIX, operation);
+
+        match git_repo.stash_save(&signature, &message, None) {
+            Ok(oid) => Ok(Some(oid)),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| {
+                format!(
+                    "Cannot stash changes in repo at `{}`",
+                    self.work_dir.display()
+                )
+            }),
+        }
+    }
+
+    /// Re-apply the stash `stash_oid` and drop it, see `pop_stash`.
+    pub fn unstash(&self, stash_oid: git2::Oid) -> Result<Vec<path::PathBuf>> {
+        let index = self
+            .wok_stashes()?
+            .into_iter()
+            .find(|stash| stash.oid == stash_oid)
+            .map(|stash| stash.index)
+            .with_context(|| format!("Cannot find stash {}", stash_oid))?;
+        self.pop_stash(index)
+    }
+
+    /// Apply the stash at `index` and drop it. If applying it conflicts, the
+    /// stash is kept and the conflicting paths are returned.
+    pub fn pop_stash(&self, index: usize) -> Result<Vec<path::PathBuf>> {
+        let mut git_repo = git2::Repository::open(&self.work_dir)?;
+        git_repo.stash_apply(index, None).with_context(|| {
+            format!(
+                "Cannot apply stash@{{{}}} in repo at `{}`",
+                index,
+                self.work_dir.display()
+            )
+        })?;
+
+        let conflicts = index_conflicts(&git_repo.index()?)?;
+        if conflicts.is_empty() {
+            git_repo.stash_drop(index)?;
+        }
+        Ok(conflicts)
+    }
+
+    pub fn drop_stash(&self, index: usize) -> Result<()> {
+        let mut git_repo = git2::Repository::open(&self.work_dir)?;
+        git_repo.stash_drop(index)?;
+        Ok(())
+    }
+
+    /// Stashes made by `autostash`, newest first.
+    pub fn wok_stashes(&self) -> Result<Vec<Stash>> {
+        let mut git_repo = git2::Repository::open(&self.work_dir)?;
+        let mut stashes = vec![];
+        git_repo.stash_foreach(|index, message, oid| {
+            if message.contains(STASH_PREFIX) {
+                stashes.push(Stash {
+                    index,
+                    message: message.to_owned(),
+                    oid: *oid,
+                });
+            }
+            true
+        })?;
+        Ok(stashes)
+    }
+
     fn rebase(
         &self,
         _branch_name: &str,
         remote_commit: &git2::Commit,
     ) -> Result<MergeResult> {
-        let _local_commit = self.git_repo.head()?.peel_to_commit()?;
         let remote_oid = remote_commit.id();
 
         // Prepare annotated commit for rebase
         let remote_annotated = self.git_repo.find_annotated_commit(remote_oid)?;
 
         // Initialize rebase operation
-        let signature = self.git_repo.signature()?;
         let mut rebase = self.git_repo.rebase(
             None,                    // branch to rebase (None = HEAD)
             Some(&remote_annotated), // upstream
@@ -172,43 +409,97 @@ impl Repo {
             None,                    // options
         )?;
 
-        // Process each commit in the rebase
-        let mut has_conflicts = false;
-        while let Some(op) = rebase.next() {
-            match op {
-                Ok(_rebase_op) => {
-                    // Check for conflicts
-                    let index = self.git_repo.index()?;
-                    if index.has_conflicts() {
-                        has_conflicts = true;
-                        break;
-                    }
+        self.run_rebase(&mut rebase)
+    }
 
-                    // Commit the rebased changes
-                    if rebase.commit(None, &signature, None).is_err() {
-                        has_conflicts = true;
-                        break;
-                    }
-                },
-                Err(_) => {
-                    has_conflicts = true;
-                    break;
-                },
+    /// Apply the remaining operations of `rebase`, stopping at the first one
+    /// that conflicts.
+    fn run_rebase(&self, rebase: &mut git2::Rebase) -> Result<MergeResult> {
+        while let Some(op) = rebase.next() {
+            op?;
+            if self.git_repo.index()?.has_conflicts() {
+                // Leave repository in state with conflicts for user to resolve
+                return Ok(MergeResult::Conflicts);
             }
-        }
-
-        if has_conflicts {
-            // Leave repository in state with conflicts for user to resolve
-            return Ok(MergeResult::Conflicts);
+            self.commit_rebase_operation(rebase)?;
         }
 
         // Finish the rebase
-        rebase.finish(Some(&signature))?;
+        rebase.finish(Some(&self.git_repo.signature()?))?;
 
         Ok(MergeResult::Rebased)
     }
 
-    pub fn merge(&self, branch_name: &str) -> Result<MergeResult> {
+    /// Commit the current operation of `rebase`, skipping it if its changes
+    /// are already upstream.
+    fn commit_rebase_operation(&self, rebase: &mut git2::Rebase) -> Result<()> {
+        match rebase.commit(None, &self.git_repo.signature()?, None) {
+            Ok(_) => Ok(()),
+            Err(err) if err.code() == git2::ErrorCode::Applied => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Paths with unresolved conflicts in the index.
+    pub fn conflicted_paths(&self) -> Result<Vec<path::PathBuf>> {
+        let mut index = self.git_repo.index()?;
+        index.read(false)?;
+        index_conflicts(&index)
+    }
+
+    /// Conclude a merge or rebase that `merge` stopped on conflicts, once they
+    /// are resolved and staged. Returns `None` when neither is in progress.
+    pub fn continue_merge(&self) -> Result<Option<MergeResult>> {
+        match self.git_repo.state() {
+            git2::RepositoryState::Clean => Ok(None),
+            git2::RepositoryState::Merge => {
+                if !self.conflicted_paths()?.is_empty() {
+                    return Ok(Some(MergeResult::Conflicts));
+                }
+                self.commit_merge()?;
+                Ok(Some(MergeResult::Merged))
+            },
+            git2::RepositoryState::Rebase
+            | git2::RepositoryState::RebaseInteractive
+            | git2::RepositoryState::RebaseMerge => {
+                if !self.conflicted_paths()?.is_empty() {
+                    return Ok(Some(MergeResult::Conflicts));
+                }
+                let mut rebase = self.git_repo.open_rebase(None)?;
+                // The operation the rebase stopped at is committed first
+                if rebase.operation_current().is_some() {
+                    self.commit_rebase_operation(&mut rebase)?;
+                }
+                self.run_rebase(&mut rebase).map(Some)
+            },
+            state => bail!(
+                "Cannot continue, repo at `{}` is in the middle of {:?}",
+                self.work_dir.display(),
+                state
+            ),
+        }
+    }
+
+    /// Abort a merge or rebase left in progress by `merge`.
+    pub fn abort_merge(&self) -> Result<()> {
+        match self.git_repo.state() {
+            git2::RepositoryState::Rebase
+            | git2::RepositoryState::RebaseInteractive
+            | git2::RepositoryState::RebaseMerge => {
+                self.git_repo.open_rebase(None)?.abort()?;
+            },
+            _ => self.git_repo.cleanup_state()?,
+        }
+        Ok(())
+    }
+
+    /// Fetch and bring the remote-tracking branch of `branch_name` into it,
+    /// using `strategy` or else the one set in the git config.
+    pub fn merge(
+        &self,
+        branch_name: &str,
+        strategy: Option<PullStrategy>,
+    ) -> Result<MergeResult> {
         // First, fetch the latest changes
         self.fetch()?;
 
@@ -234,8 +525,12 @@ impl Repo {
         let remote_commit = self.git_repo.find_commit(remote_branch_oid)?;
         let local_commit = self.git_repo.head()?.peel_to_commit()?;
 
-        // Check if we're already up to date
-        if local_commit.id() == remote_commit.id() {
+        // Check if we're already up to date, or only have commits to push
+        if local_commit.id() == remote_commit.id()
+            || self
+                .git_repo
+                .graph_descendant_of(local_commit.id(), remote_commit.id())?
+        {
             return Ok(MergeResult::UpToDate);
         }
 
@@ -259,64 +554,149 @@ impl Repo {
             return Ok(MergeResult::FastForward);
         }
 
-        // Determine pull strategy from git config
-        let pull_strategy = self.get_pull_strategy(branch_name)?;
+        // Determine pull strategy, falling back to the git config
+        let pull_strategy = match strategy {
+            Some(strategy) => strategy,
+            None => self.get_pull_strategy(branch_name)?,
+        };
 
         match pull_strategy {
+            PullStrategy::FastForwardOnly => Ok(MergeResult::Diverged),
             PullStrategy::Rebase => {
                 // Perform rebase
                 self.rebase(branch_name, &remote_commit)
             },
             PullStrategy::Merge => {
                 // Perform merge (existing logic)
-                self.do_merge(branch_name, &local_commit, &remote_commit, &tracking)
+                self.do_merge(&remote_commit, &tracking)
             },
         }
     }
 
-    fn do_merge(
+    /// Work out what `merge` would do to `branch_name` without changing the
+    /// branch, the index or the work tree. The remote is still fetched so the
+    /// plan reflects its current state. Returns the result along with the commit
+    /// the branch would be merged with.
+    pub fn plan_merge(
         &self,
         branch_name: &str,
-        local_commit: &git2::Commit,
+        strategy: Option<PullStrategy>,
+    ) -> Result<(MergeResult, Option<git2::Oid>)> {
+        self.fetch_branch(branch_name)?;
+
+        let tracking = match self.tracking_branch(branch_name)? {
+            Some(tracking) => tracking,
+            None => return Ok((MergeResult::UpToDate, None)),
+        };
+
+        let remote_branch_oid = match self.git_repo.refname_to_id(&tracking.remote_ref)
+        {
+            Ok(oid) => oid,
+            Err(_) => return Ok((MergeResult::UpToDate, None)),
+        };
+
+        let remote_commit = self.git_repo.find_commit(remote_branch_oid)?;
+        let local_commit = self
+            .git_repo
+            .resolve_reference_from_short_name(branch_name)?
+            .peel_to_commit()?;
+
+        if local_commit.id() == remote_commit.id()
+            || self
+                .git_repo
+                .graph_descendant_of(local_commit.id(), remote_commit.id())?
+        {
+            return Ok((MergeResult::UpToDate, Some(remote_commit.id())));
+        }
+
+        if self
+            .git_repo
+            .graph_descendant_of(remote_commit.id(), local_commit.id())?
+        {
+            return Ok((MergeResult::FastForward, Some(remote_commit.id())));
+        }
+
+        let pull_strategy = match strategy {
+            Some(strategy) => strategy,
+            None => self.get_pull_strategy(branch_name)?,
+        };
+        let result = match pull_strategy {
+            PullStrategy::FastForwardOnly => MergeResult::Diverged,
+            PullStrategy::Rebase => MergeResult::Rebased,
+            PullStrategy::Merge => {
+                let merged =
+                    self.git_repo
+                        .merge_commits(&local_commit, &remote_commit, None)?;
+                if merged.has_conflicts() {
+                    MergeResult::Conflicts
+                } else {
+                    MergeResult::Merged
+                }
+            },
+        };
+
+        Ok((result, Some(remote_commit.id())))
+    }
+
+    fn do_merge(
+        &self,
         remote_commit: &git2::Commit,
         tracking: &TrackingBranch,
     ) -> Result<MergeResult> {
-        // Perform a merge
-        let mut merge_opts = git2::MergeOptions::new();
-        merge_opts.fail_on_conflict(false); // Don't fail on conflicts, we'll handle them
-
-        let _merge_result = self.git_repo.merge_commits(
-            local_commit,
-            remote_commit,
-            Some(&merge_opts),
+        // Merge into the index and the work tree, recording the merge state so
+        // that conflicts can be resolved and the merge concluded later
+        let remote_annotated =
+            self.git_repo.find_annotated_commit(remote_commit.id())?;
+        let mut checkout = CheckoutBuilder::new();
+        checkout.allow_conflicts(true).conflict_style_merge(true);
+        self.git_repo
+            .merge(&[&remote_annotated], None, Some(&mut checkout))?;
+        fs::write(
+            self.git_repo.path().join("MERGE_MSG"),
+            format!("Merge remote-tracking branch '{}'\n", tracking.remote_ref),
         )?;
 
-        // Check if there are conflicts by examining the index
-        let mut index = self.git_repo.index()?;
-        let has_conflicts = index.has_conflicts();
-
-        if !has_conflicts {
-            // No conflicts, merge was successful
-            let signature = self.git_repo.signature()?;
-            let tree_id = index.write_tree()?;
-            let tree = self.git_repo.find_tree(tree_id)?;
-
-            self.git_repo.commit(
-                Some(&format!("refs/heads/{}", branch_name)),
-                &signature,
-                &signature,
-                &format!("Merge remote-tracking branch '{}'", tracking.remote_ref),
-                &tree,
-                &[local_commit, remote_commit],
-            )?;
+        if self.git_repo.index()?.has_conflicts() {
+            // Leave repository in state with conflicts for user to resolve
+            return Ok(MergeResult::Conflicts);
+        }
 
-            self.git_repo.cleanup_state()?;
+        self.commit_merge()?;
+        Ok(MergeResult::Merged)
+    }
 
-            Ok(MergeResult::Merged)
-        } else {
-            // There are conflicts
-            Ok(MergeResult::Conflicts)
+    /// Commit the staged result of the merge in progress on HEAD.
+    fn commit_merge(&self) -> Result<()> {
+        let mut index = self.git_repo.index()?;
+        index.read(false)?;
+        let tree = self.git_repo.find_tree(index.write_tree()?)?;
+
+        let mut parents = vec![self.git_repo.head()?.peel_to_commit()?];
+        let mut merge_heads = vec![];
+        git2::Repository::open(&self.work_dir)?.mergehead_foreach(|oid| {
+            merge_heads.push(*oid);
+            true
+        })?;
+        for oid in merge_heads {
+            parents.push(self.git_repo.find_commit(oid)?);
         }
+        let message = self
+            .git_repo
+            .message()
+            .unwrap_or_else(|_| String::from("Merge remote-tracking branch"));
+
+        let signature = self.git_repo.signature()?;
+        self.git_repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message.trim_end(),
+            &tree,
+            &parents.iter().collect::<Vec<_>>(),
+        )?;
+        self.git_repo.cleanup_state()?;
+
+        Ok(())
     }
 
     pub fn get_remote_name_for_branch(&self, branch_name: &str) -> Result<String> {
@@ -383,154 +763,7 @@ impl Repo {
         &self,
         verbose: bool,
     ) -> Result<git2::RemoteCallbacks<'static>> {
-        let config = self.git_repo.config()?;
-
-        let mut callbacks = git2::RemoteCallbacks::new();
-        callbacks.credentials(move |url, username_from_url, allowed| {
-            if verbose {
-                eprintln!("DEBUG: Credential callback invoked");
-                eprintln!("  URL: {}", url);
-                eprintln!("  Username from URL: {:?}", username_from_url);
-                eprintln!("  Allowed types: {:?}", allowed);
-            }
-
-            // Try SSH key from agent (only if SSH_AUTH_SOCK is set)
-            if allowed.contains(git2::CredentialType::SSH_KEY) {
-                if let Some(username) = username_from_url {
-                    // Check if SSH agent is actually available
-                    if std::env::var("SSH_AUTH_SOCK").is_ok() {
-                        if verbose {
-                            eprintln!(
-                                "  Attempting: SSH key from agent for user '{}'",
-                                username
-                            );
-                        }
-                        match git2::Cred::ssh_key_from_agent(username) {
-                            Ok(cred) => {
-                                if verbose {
-                                    eprintln!("  SUCCESS: SSH key from agent");
-                                }
-                                return Ok(cred);
-                            },
-                            Err(e) => {
-                                if verbose {
-                                    eprintln!("  FAILED: SSH key from agent - {}", e);
-                                }
-                            },
-                        }
-                    } else if verbose {
-                        eprintln!(
-                            "  SKIPPED: SSH key from agent (SSH_AUTH_SOCK not set)"
-                        );
-                    }
-                } else if verbose {
-                    eprintln!("  SKIPPED: SSH key from agent (no username provided)");
-                }
-
-                // Try SSH key files directly
-                if let Some(username) = username_from_url
-                    && let Ok(home) = std::env::var("HOME")
-                {
-                    let key_paths = vec![
-                        format!("{}/.ssh/id_ed25519", home),
-                        format!("{}/.ssh/id_rsa", home),
-                        format!("{}/.ssh/id_ecdsa", home),
-                    ];
-
-                    for key_path in key_paths {
-                        if path::Path::new(&key_path).exists() {
-                            if verbose {
-                                eprintln!("  Attempting: SSH key file at {}", key_path);
-                            }
-                            match git2::Cred::ssh_key(
-                                username,
-                                None, // no public key path
-                                path::Path::new(&key_path),
-                                None, // no passphrase
-                            ) {
-                                Ok(cred) => {
-                                    if verbose {
-                                        eprintln!("  SUCCESS: SSH key file");
-                                    }
-                                    return Ok(cred);
-                                },
-                                Err(e) => {
-                                    if verbose {
-                                        eprintln!("  FAILED: SSH key file - {}", e);
-                                    }
-                                },
-                            }
-                        }
-                    }
-                }
-            }
-
-            // Try credential helper
-            if allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT)
-                || allowed.contains(git2::CredentialType::SSH_KEY)
-                || allowed.contains(git2::CredentialType::DEFAULT)
-            {
-                if verbose {
-                    eprintln!("  Attempting: Credential helper");
-                }
-                match git2::Cred::credential_helper(&config, url, username_from_url) {
-                    Ok(cred) => {
-                        if verbose {
-                            eprintln!("  SUCCESS: Credential helper");
-                        }
-                        return Ok(cred);
-                    },
-                    Err(e) => {
-                        if verbose {
-                            eprintln!("  FAILED: Credential helper - {}", e);
-                        }
-                    },
-                }
-            }
-
-            // Try username only
-            if allowed.contains(git2::CredentialType::USERNAME) {
-                let username = username_from_url.unwrap_or("git");
-                if verbose {
-                    eprintln!("  Attempting: Username only ('{}')", username);
-                }
-                match git2::Cred::username(username) {
-                    Ok(cred) => {
-                        if verbose {
-                            eprintln!("  SUCCESS: Username");
-                        }
-                        return Ok(cred);
-                    },
-                    Err(e) => {
-                        if verbose {
-                            eprintln!("  FAILED: Username - {}", e);
-                        }
-                    },
-                }
-            }
-
-            // Try default
-            if verbose {
-                eprintln!("  Attempting: Default credentials");
-            }
-            match git2::Cred::default() {
-                Ok(cred) => {
-                    if verbose {
-                        eprintln!("  SUCCESS: Default credentials");
-                    }
-                    Ok(cred)
-                },
-                Err(e) => {
-                    if verbose {
-                        eprintln!("  FAILED: All credential methods exhausted");
-                        eprintln!("  Last error: {}", e);
-                    }
-                    Err(e)
-                },
-            }
-        });
-
-        Ok(callbacks)
+        Ok(build_remote_callbacks(self.git_repo.config()?, verbose))
     }
 
     fn resolve_reference(&self, short_name: &str) -> Result<String> {
@@ -575,27 +808,28 @@ impl Repo {
         Ok(Some(TrackingBranch { remote, remote_ref }))
     }
 
+    /// Pull strategy for `branch_name` from the git config:
+    /// `branch.<name>.rebase` takes precedence over `pull.rebase`, and
+    /// `pull.ff = only` asks for fast-forwards only when neither is set.
     fn get_pull_strategy(&self, branch_name: &str) -> Result<PullStrategy> {
         let config = self.git_repo.config()?;
 
-        // First check branch-specific rebase setting (highest priority)
-        let branch_rebase_key = format!("branch.{}.rebase", branch_name);
-        if let Ok(value) = config.get_string(&branch_rebase_key) {
-            return Ok(parse_rebase_config(&value));
-        }
-
-        // Then check global pull.rebase setting
-        if let Ok(value) = config.get_string("pull.rebase") {
-            return Ok(parse_rebase_config(&value));
+        let rebase_keys = [
+            format!("branch.{}.rebase", branch_name),
+            String::from("pull.rebase"),
+        ];
+        for key in rebase_keys {
+            if let Ok(value) = config.get_string(&key)
+                && let Some(strategy) = parse_rebase_config(&key, &value)?
+            {
+                return Ok(strategy);
+            }
         }
 
-        // Try as boolean for backward compatibility
-        if let Ok(value) = config.get_bool("pull.rebase") {
-            return Ok(if value {
-                PullStrategy::Rebase
-            } else {
-                PullStrategy::Merge
-            });
+        if let Ok(value) = config.get_string("pull.ff")
+            && value.eq_ignore_ascii_case("only")
+        {
+            return Ok(PullStrategy::FastForwardOnly);
         }
 
         // Default to merge
@@ -603,6 +837,166 @@ impl Repo {
     }
 }
 
+/// Builds remote callbacks resolving credentials from the SSH agent, SSH key
+/// files and the credential helpers set up in `config`.
+fn build_remote_callbacks(
+    config: git2::Config,
+    verbose: bool,
+) -> git2::RemoteCallbacks<'static> {
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(move |url, username_from_url, allowed| {
+        if verbose {
+            eprintln!("DEBUG: Credential callback invoked");
+            eprintln!("  URL: {}", url);
+            eprintln!("  Username from URL: {:?}", username_from_url);
+            eprintln!("  Allowed types: {:?}", allowed);
+        }
+
+        // Try SSH key from agent (only if SSH_AUTH_SOCK is set)
+        if allowed.contains(git2::CredentialType::SSH_KEY) {
+            if let Some(username) = username_from_url {
+                // Check if SSH agent is actually available
+                if std::env::var("SSH_AUTH_SOCK").is_ok() {
+                    if verbose {
+                        eprintln!(
+                            "  Attempting: SSH key from agent for user '{}'",
+                            username
+                        );
+                    }
+                    match git2::Cred::ssh_key_from_agent(username) {
+                        Ok(cred) => {
+                            if verbose {
+                                eprintln!("  SUCCESS: SSH key from agent");
+                            }
+                            return Ok(cred);
+                        },
+                        Err(e) => {
+                            if verbose {
+                                eprintln!("  FAILED: SSH key from agent - {}", e);
+                            }
+                        },
+                    }
+                } else if verbose {
+                    eprintln!("  SKIPPED: SSH key from agent (SSH_AUTH_SOCK not set)");
+                }
+            } else if verbose {
+                eprintln!("  SKIPPED: SSH key from agent (no username provided)");
+            }
+
+            // Try SSH key files directly
+            if let Some(username) = username_from_url
+                && let Ok(home) = std::env::var("HOME")
+            {
+                let key_paths = vec![
+                    format!("{}/.ssh/id_ed25519", home),
+                    format!("{}/.ssh/id_rsa", home),
+                    format!("{}/.ssh/id_ecdsa", home),
+                ];
+
+                for key_path in key_paths {
+                    if path::Path::new(&key_path).exists() {
+                        if verbose {
+                            eprintln!("  Attempting: SSH key file at {}", key_path);
+                        }
+                        match git2::Cred::ssh_key(
+                            username,
+                            None, // no public key path
+                            path::Path::new(&key_path),
+                            None, // no passphrase
+                        ) {
+                            Ok(cred) => {
+                                if verbose {
+                                    eprintln!("  SUCCESS: SSH key file");
+                                }
+                                return Ok(cred);
+                            },
+                            Err(e) => {
+                                if verbose {
+                                    eprintln!("  FAILED: SSH key file - {}", e);
+                                }
+                            },
+                        }
+                    }
+                }
+            }
+        }
+
+        // Try credential helper
+        if allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT)
+            || allowed.contains(git2::CredentialType::SSH_KEY)
+            || allowed.contains(git2::CredentialType::DEFAULT)
+        {
+            if verbose {
+                eprintln!("  Attempting: Credential helper");
+            }
+            match git2::Cred::credential_helper(&config, url, username_from_url) {
+                Ok(cred) => {
+                    if verbose {
+                        eprintln!("  SUCCESS: Credential helper");
+                    }
+                    return Ok(cred);
+                },
+                Err(e) => {
+                    if verbose {
+                        eprintln!("  FAILED: Credential helper - {}", e);
+                    }
+                },
+            }
+        }
+
+        // Try username only
+        if allowed.contains(git2::CredentialType::USERNAME) {
+            let username = username_from_url.unwrap_or("git");
+            if verbose {
+                eprintln!("  Attempting: Username only ('{}')", username);
+            }
+            match git2::Cred::username(username) {
+                Ok(cred) => {
+                    if verbose {
+                        eprintln!("  SUCCESS: Username");
+                    }
+                    return Ok(cred);
+                },
+                Err(e) => {
+                    if verbose {
+                        eprintln!("  FAILED: Username - {}", e);
+                    }
+                },
+            }
+        }
+
+        // Try default
+        if verbose {
+            eprintln!("  Attempting: Default credentials");
+        }
+        match git2::Cred::default() {
+            Ok(cred) => {
+                if verbose {
+                    eprintln!("  SUCCESS: Default credentials");
+                }
+                Ok(cred)
+            },
+            Err(e) => {
+                if verbose {
+                    eprintln!("  FAILED: All credential methods exhausted");
+                    eprintln!("  Last error: {}", e);
+                }
+                Err(e)
+            },
+        }
+    });
+
+    callbacks
+}
+
+/// Remote callbacks for operations not bound to an existing repo (e.g. cloning),
+/// using the global git configuration for credential helpers.
+pub fn default_remote_callbacks() -> Result<git2::RemoteCallbacks<'static>> {
+    let config = git2::Config::open_default()
+        .context("Cannot open the default git configuration")?;
+    Ok(build_remote_callbacks(config, false))
+}
+
 impl fmt::Debug for Repo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Repo")
@@ -613,21 +1007,108 @@ impl fmt::Debug for Repo {
     }
 }
 
+/// Marks the messages of the stashes made by wok.
+pub const STASH_PREFIX: &str = "wok autostash";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stash {
+    pub index: usize,
+    pub message: String,
+    pub oid: git2::Oid,
+}
+
 pub struct TrackingBranch {
     pub remote: String,
     pub remote_ref: String,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum PullStrategy {
-    Merge,
-    Rebase,
+/// Git config key holding the revision `pin` detached HEAD at.
+const PIN_CONFIG_KEY: &str = "wok.pin";
+
+/// Revision `git_repo` was pinned to, if any.
+fn pinned_rev(git_repo: &git2::Repository) -> Option<String> {
+    git_repo.config().ok()?.get_string(PIN_CONFIG_KEY).ok()
+}
+
+/// Branch being rebased in `git_repo`, if a rebase is in progress.
+fn rebased_branch(git_repo: &git2::Repository) -> Option<String> {
+    let rebase = git_repo.open_rebase(None).ok()?;
+    rebase
+        .orig_head_name()
+        .map(|name| name.strip_prefix("refs/heads/").unwrap_or(name).to_owned())
+}
+
+fn index_conflicts(index: &git2::Index) -> Result<Vec<path::PathBuf>> {
+    let mut conflicts = vec![];
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            conflicts.push(path::PathBuf::from(
+                String::from_utf8_lossy(&entry.path).into_owned(),
+            ));
+        }
+    }
+    Ok(conflicts)
 }
 
-fn parse_rebase_config(value: &str) -> PullStrategy {
+/// Strategy for the value of the `rebase` config `key`. Rebasing with
+/// `interactive` is done as a plain rebase, and `merges` is refused since a
+/// plain rebase would drop the local merge commits. Unknown values are
+/// ignored.
+fn parse_rebase_config(key: &str, value: &str) -> Result<Option<PullStrategy>> {
     match value.to_lowercase().as_str() {
-        "true" | "interactive" | "i" | "merges" | "m" => PullStrategy::Rebase,
-        "false" => PullStrategy::Merge,
-        _ => PullStrategy::Merge, // Default to merge for unknown values
+        "true" | "yes" | "on" | "1" | "interactive" | "i" => {
+            Ok(Some(PullStrategy::Rebase))
+        },
+        "merges" | "m" => bail!(
+            "Cannot rebase keeping merge commits as asked by `{} = {}`; use \
+             --rebase or --merge, or set `pull` in the wok file",
+            key,
+            value
+        ),
+        "false" | "no" | "off" | "0" => Ok(Some(PullStrategy::Merge)),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rebase_config_reads_booleans() {
+        for value in ["true", "yes", "on", "1", "interactive"] {
+            assert_eq!(
+                parse_rebase_config("pull.rebase", value).unwrap(),
+                Some(PullStrategy::Rebase)
+            );
+        }
+        for value in ["false", "No", "off", "0"] {
+            assert_eq!(
+                parse_rebase_config("pull.rebase", value).unwrap(),
+                Some(PullStrategy::Merge)
+            );
+        }
+        assert_eq!(
+            parse_rebase_config("pull.rebase", "sometimes").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_rebase_config_refuses_merges() {
+        for value in ["merges", "m"] {
+            assert_eq!(
+                parse_rebase_config("branch.main.rebase", value)
+                    .unwrap_err()
+                    .to_string(),
+                format!(
+                    "Cannot rebase keeping merge commits as asked by \
+                     `branch.main.rebase = {}`; use --rebase or --merge, or set \
+                     `pull` in the wok file",
+                    value
+                )
+            );
+        }
     }
 }
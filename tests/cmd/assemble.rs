@@ -19,7 +19,7 @@ fn assemble_creates_workspace_config() {
     let config_path = workspace_path.path().join(DEFAULT_CONFIG_NAME);
     let mut output = Cursor::new(Vec::new());
 
-    cmd::assemble(workspace_path.path(), &config_path, &mut output).unwrap();
+    cmd::assemble(workspace_path.path(), &config_path, &mut output, false).unwrap();
 
     let config = Config::load(&config_path).unwrap();
     assert_eq!(config.repos.len(), 2);
@@ -61,6 +61,7 @@ fn assemble_is_idempotent() {
         workspace_path.path(),
         &config_path,
         &mut Cursor::new(Vec::new()),
+        false,
     )
     .unwrap();
 
@@ -70,6 +71,7 @@ fn assemble_is_idempotent() {
         workspace_path.path(),
         &config_path,
         &mut Cursor::new(Vec::new()),
+        false,
     )
     .unwrap();
 
@@ -78,6 +80,40 @@ fn assemble_is_idempotent() {
     assert_eq!(first_config.dump().unwrap(), second_config.dump().unwrap());
 }
 
+#[test]
+fn assemble_dry_run_changes_nothing() {
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    let workspace_path = temp_dir.child("workspace");
+    workspace_path.create_dir_all().unwrap();
+
+    create_component(workspace_path.path(), "component-a");
+
+    let config_path = workspace_path.path().join(DEFAULT_CONFIG_NAME);
+    let mut output = Cursor::new(Vec::new());
+
+    cmd::assemble(workspace_path.path(), &config_path, &mut output, true).unwrap();
+
+    let component_path = workspace_path.path().join("component-a");
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        format!(
+            "Assembling workspace in `{workspace}`\n\
+             Would init git repo at `{workspace}`\n\
+             Would init git repo at `{component}`\n\
+             Would register `component-a` as submodule\n\
+             Would write config at `{config}`\n\
+             - 'component-a': on 'main'\n",
+            workspace = workspace_path.path().display(),
+            component = component_path.display(),
+            config = config_path.display(),
+        )
+    );
+
+    assert!(!workspace_path.path().join(".git").exists());
+    assert!(!component_path.join(".git").exists());
+    assert!(!config_path.exists());
+}
+
 fn create_component(base: &Path, name: &str) {
     let component_dir = base.join(name);
     fs::create_dir_all(&component_dir).unwrap();
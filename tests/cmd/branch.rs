@@ -0,0 +1,224 @@
+use std::io::Cursor;
+use std::{fs, path};
+
+use pretty_assertions::assert_eq;
+use rstest::*;
+
+use git_wok::{cmd, config, output};
+
+use super::*;
+
+fn run_branch(
+    repo_sample: &TestRepo,
+    actual_config: &mut config::Config,
+    action: cmd::BranchAction,
+) -> (String, output::Report) {
+    let mut output = Cursor::new(Vec::new());
+    let report = cmd::branch(
+        actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &action,
+        &[], // repos
+        &[], // groups
+    )
+    .unwrap();
+    (
+        String::from_utf8_lossy(output.get_ref()).into_owned(),
+        report,
+    )
+}
+
+fn add_remote(repo_path: &PathBuf) -> PathBuf {
+    let remote_path = repo_path.with_extension("git");
+    _run(
+        &format!("git init -q --bare {}", remote_path.display()),
+        repo_path,
+    )
+    .unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        repo_path,
+    )
+    .unwrap();
+    _run("git push -q origin other", repo_path).unwrap();
+    remote_path
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn branch_lists_branches_of_all_repos(repo_sample: TestRepo) {
+    let sub_a = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git branch feature", sub_a).unwrap();
+    add_remote(repo_sample.subrepo_path("sub-b").unwrap());
+
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let (output, report) =
+        run_branch(&repo_sample, &mut actual_config, cmd::BranchAction::List);
+
+    assert_eq!(
+        output,
+        "branch   umbrella  sub-a  sub-b\n\
+         feature  -         L      -\n\
+         main     *         *      *\n\
+         other    L         L      LR\n\
+         (* checked out, L local, R remote)\n"
+    );
+    let report = serde_json::to_value(&report).unwrap();
+    assert_eq!(report["repos"][2]["path"], "sub-b");
+    assert_eq!(
+        report["repos"][2]["remote"],
+        serde_json::json!(["origin/other"])
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn branch_deletes_only_merged_branches(repo_sample: TestRepo) {
+    let sub_a = repo_sample.subrepo_path("sub-a").unwrap();
+    let sub_b = repo_sample.subrepo_path("sub-b").unwrap();
+    _run("git branch feature", sub_a).unwrap();
+    _run("git switch -q -c feature", sub_b).unwrap();
+    _run("git commit -q --allow-empty -m 'Unmerged work'", sub_b).unwrap();
+    _run("git switch -q main", sub_b).unwrap();
+
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let (output, report) = run_branch(
+        &repo_sample,
+        &mut actual_config,
+        cmd::BranchAction::Delete {
+            branch: String::from("feature"),
+            remote: false,
+        },
+    );
+
+    assert_eq!(
+        output,
+        "Deleting branch 'feature'...\n\
+         - 'sub-a': deleted 'feature'\n\
+         - 'sub-b': not deleting 'feature' - 'feature' is not fully merged into \
+         'main'\n"
+    );
+    assert_eq!(report.exit_code, 1);
+    assert_eq!(_run("git branch --list feature", sub_a).unwrap(), "");
+    assert_eq!(
+        _run("git branch --list feature", sub_b).unwrap(),
+        "  feature\n"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn branch_deletes_remote_branch(repo_sample: TestRepo) {
+    let sub_a = repo_sample.subrepo_path("sub-a").unwrap();
+    let remote_path = add_remote(sub_a);
+    _run("git branch feature", sub_a).unwrap();
+    _run("git push -q -u origin feature", sub_a).unwrap();
+
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let (output, report) = run_branch(
+        &repo_sample,
+        &mut actual_config,
+        cmd::BranchAction::Delete {
+            branch: String::from("feature"),
+            remote: true,
+        },
+    );
+
+    assert_eq!(
+        output,
+        "Deleting branch 'feature'...\n\
+         - 'sub-a': deleted 'feature' here and on 'origin'\n"
+    );
+    assert_eq!(report.exit_code, 0);
+    assert_eq!(
+        _run("git for-each-ref --format=%(refname:short)", &remote_path).unwrap(),
+        "other\n"
+    );
+    assert_eq!(_run("git branch --all --list *feature", sub_a).unwrap(), "");
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn branch_renames_and_updates_heads(repo_sample: TestRepo) {
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let (output, report) = run_branch(
+        &repo_sample,
+        &mut actual_config,
+        cmd::BranchAction::Rename {
+            from: String::from("main"),
+            to: String::from("trunk"),
+        },
+    );
+
+    assert_eq!(
+        output,
+        "Renaming branch 'main' to 'trunk'...\n\
+         - 'sub-a': renamed 'main' to 'trunk'\n\
+         - 'sub-b': renamed 'main' to 'trunk'\n"
+    );
+    assert!(report.config_updated);
+    assert!(actual_config.repos.iter().all(|repo| repo.head == "trunk"));
+    for name in ["sub-a", "sub-b"] {
+        let subrepo_path = repo_sample.subrepo_path(name).unwrap();
+        assert_eq!(
+            _run("git branch --show-current", subrepo_path).unwrap(),
+            "trunk\n"
+        );
+    }
+    assert_eq!(
+        _run("git branch --show-current", repo_sample.repo_path()).unwrap(),
+        "main\n"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn branch_rename_saves_heads_of_renamed_repos_when_another_fails(
+    repo_sample: TestRepo,
+) {
+    // A stale ref lock keeps the branch from being renamed in sub-a
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let git_dir = _run("git rev-parse --absolute-git-dir", subrepo_path).unwrap();
+    fs::write(
+        path::Path::new(git_dir.trim()).join("refs/heads/trunk.lock"),
+        "",
+    )
+    .unwrap();
+
+    let (output, exit_code) = _wok("branch -m main trunk", repo_sample.repo_path());
+
+    assert_eq!(exit_code, 1);
+    assert!(output.contains("- 'sub-a': failed to rename 'main' - "));
+    assert!(output.contains("- 'sub-b': renamed 'main' to 'trunk'\n"));
+    let actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let heads: Vec<_> = actual_config
+        .repos
+        .iter()
+        .map(|repo| (repo.path.to_string_lossy().into_owned(), repo.head.as_str()))
+        .collect();
+    assert_eq!(
+        heads,
+        [
+            (String::from("sub-a"), "main"),
+            (String::from("sub-b"), "trunk")
+        ]
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn branch_refuses_to_rename_onto_existing_branch(repo_sample: TestRepo) {
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let (output, report) = run_branch(
+        &repo_sample,
+        &mut actual_config,
+        cmd::BranchAction::Rename {
+            from: String::from("main"),
+            to: String::from("other"),
+        },
+    );
+
+    assert_eq!(
+        output,
+        "Renaming branch 'main' to 'other'...\n\
+         - 'sub-a': not renaming 'main' - 'other' already exists\n"
+    );
+    assert_eq!(report.exit_code, 1);
+    assert!(!report.config_updated);
+    assert_eq!(actual_config.repos[0].head, "main");
+}
@@ -0,0 +1,120 @@
+use std::{fs, io::Cursor};
+
+use rstest::*;
+
+use git_wok::{DEFAULT_CONFIG_NAME, cmd};
+
+use super::*;
+
+/// Publish the sample workspace to bare remotes and return the umbrella remote.
+fn publish_workspace(repo_sample: &TestRepo, subrepo_names: &[&str]) -> PathBuf {
+    let remote_parent = repo_sample.repo_path().join("remotes");
+    fs::create_dir_all(&remote_parent).unwrap();
+
+    for name in subrepo_names {
+        let subrepo_path = repo_sample.subrepo_path(name).unwrap();
+        let remote_path = remote_parent.join(format!("{name}.git"));
+
+        _run(&format!("git init --bare {name}.git"), &remote_parent).unwrap();
+        _run(
+            &format!("git remote add origin {}", remote_path.display()),
+            subrepo_path,
+        )
+        .unwrap();
+        _run("git push -u origin main", subrepo_path).unwrap();
+        _run(
+            &format!(
+                "git config -f .gitmodules submodule.{name}.url {}",
+                remote_path.display()
+            ),
+            repo_sample.repo_path(),
+        )
+        .unwrap();
+    }
+
+    let umbrella_remote = remote_parent.join("umbrella.git");
+    _run("git init --bare umbrella.git", &remote_parent).unwrap();
+    _run(
+        &format!("git add .gitmodules {DEFAULT_CONFIG_NAME}"),
+        repo_sample.repo_path(),
+    )
+    .unwrap();
+    _run(
+        &format!("git add {}", subrepo_names.join(" ")),
+        repo_sample.repo_path(),
+    )
+    .unwrap();
+    _run("git commit -m workspace", repo_sample.repo_path()).unwrap();
+    _run(
+        &format!("git push {} main", umbrella_remote.display()),
+        repo_sample.repo_path(),
+    )
+    .unwrap();
+
+    umbrella_remote
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn clone_checks_out_configured_heads(repo_sample: TestRepo) {
+    let umbrella_remote = publish_workspace(&repo_sample, &["sub-a", "sub-b"]);
+    let target_dir = repo_sample.repo_path().join("clones/workspace");
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::clone(
+        &umbrella_remote.to_string_lossy(),
+        &target_dir,
+        path::Path::new(DEFAULT_CONFIG_NAME),
+        &mut output,
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains("- 'sub-a': checked out 'main'"),
+        "Output: {output_str}"
+    );
+    assert!(
+        output_str.contains("tracking 'origin/main'"),
+        "Output: {output_str}"
+    );
+    assert!(
+        output_str.contains("Cloned workspace with 2 repositories"),
+        "Output: {output_str}"
+    );
+
+    // The clone is a valid workspace: no detached heads anywhere
+    let umbrella = repo::Repo::new(&target_dir, None).unwrap();
+    assert_eq!(umbrella.subrepos.len(), 2);
+
+    for name in ["sub-a", "sub-b"] {
+        let subrepo_path = target_dir.join(name);
+        let branch = _run("git rev-parse --abbrev-ref HEAD", &subrepo_path).unwrap();
+        assert_eq!(branch.trim(), "main");
+
+        let upstream =
+            _run("git rev-parse --abbrev-ref main@{upstream}", &subrepo_path).unwrap();
+        assert_eq!(upstream.trim(), "origin/main");
+
+        let gitlink = _run(&format!("git rev-parse HEAD:{name}"), &target_dir).unwrap();
+        let head = _run("git rev-parse HEAD", &subrepo_path).unwrap();
+        assert_eq!(head.trim(), gitlink.trim());
+    }
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn clone_refuses_non_empty_destination(repo_sample: TestRepo) {
+    let umbrella_remote = publish_workspace(&repo_sample, &["sub-a"]);
+    let target_dir = repo_sample.repo_path().join("clones/workspace");
+    fs::create_dir_all(&target_dir).unwrap();
+    fs::write(target_dir.join("README.md"), "occupied").unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let result = cmd::clone(
+        &umbrella_remote.to_string_lossy(),
+        &target_dir,
+        path::Path::new(DEFAULT_CONFIG_NAME),
+        &mut output,
+    );
+
+    assert!(result.is_err());
+}
@@ -0,0 +1,213 @@
+use std::io::Cursor;
+
+use pretty_assertions::assert_eq;
+use rstest::*;
+
+use git_wok::{cmd, config};
+
+use super::*;
+
+fn run_doctor(repo_path: &path::Path, fix: bool) -> (String, i32, bool) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config =
+        config::Config::load(&repo_path.join(DEFAULT_CONFIG_NAME)).unwrap();
+
+    let report = cmd::doctor(&mut actual_config, repo_path, &mut output, fix).unwrap();
+    if report.config_updated {
+        actual_config
+            .save(&repo_path.join(DEFAULT_CONFIG_NAME))
+            .unwrap();
+    }
+
+    (
+        String::from_utf8_lossy(output.get_ref()).into_owned(),
+        report.exit_code,
+        report.config_updated,
+    )
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn doctor_finds_no_problems(repo_sample: TestRepo) {
+    let (output, exit_code, _) = run_doctor(repo_sample.repo_path(), false);
+
+    assert_eq!(output, "Checking workspace...\nNo problems found\n");
+    assert_eq!(exit_code, 0);
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a-b.toml")))]
+fn doctor_keeps_config_entry_without_submodule(
+    repo_sample: TestRepo,
+    #[with("a-b.toml")] expected_config: String,
+) {
+    let (output, exit_code, _) = run_doctor(repo_sample.repo_path(), false);
+
+    assert!(
+        output.contains("- 'sub-b': configured in the wok file but not a submodule\n"),
+        "Output: {output}"
+    );
+    assert!(output.contains("Found 1 problems"), "Output: {output}");
+    assert_eq!(exit_code, 1);
+
+    let (output, exit_code, config_updated) = run_doctor(repo_sample.repo_path(), true);
+
+    assert!(
+        output.contains(
+            "- 'sub-b': configured in the wok file but not a submodule, cannot be \
+             fixed automatically\n"
+        ),
+        "Output: {output}"
+    );
+    assert_eq!(exit_code, 1);
+    assert!(!config_updated);
+    assert_eq!(
+        fs::read_to_string(repo_sample.config_path()).unwrap(),
+        expected_config
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a.toml")))]
+fn doctor_adds_unconfigured_submodule(
+    repo_sample: TestRepo,
+    #[with("a-b.toml")] expected_config: String,
+) {
+    let (output, exit_code, config_updated) = run_doctor(repo_sample.repo_path(), true);
+
+    assert!(
+        output.contains(
+            "- 'sub-b': submodule missing from the wok file, fixed: added to the wok \
+             file on 'main'\n"
+        ),
+        "Output: {output}"
+    );
+    assert_eq!(exit_code, 0);
+    assert!(config_updated);
+    assert_eq!(
+        fs::read_to_string(repo_sample.config_path()).unwrap(),
+        expected_config
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn doctor_fixes_gitmodules_branch(repo_sample: TestRepo) {
+    _run(
+        "git config -f .gitmodules submodule.sub-a.branch other",
+        repo_sample.repo_path(),
+    )
+    .unwrap();
+
+    let (output, exit_code, config_updated) = run_doctor(repo_sample.repo_path(), true);
+
+    assert!(
+        output.contains(
+            "- 'sub-a': .gitmodules tracks branch 'other' instead of configured head \
+             'main', fixed: set .gitmodules branch to 'main'\n"
+        ),
+        "Output: {output}"
+    );
+    assert_eq!(exit_code, 0);
+    assert!(!config_updated);
+
+    let branch = _run(
+        "git config -f .gitmodules submodule.sub-a.branch",
+        repo_sample.repo_path(),
+    )
+    .unwrap();
+    assert_eq!(branch.trim(), "main");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn doctor_reattaches_detached_head(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git checkout --detach", subrepo_path).unwrap();
+
+    let (output, exit_code, _) = run_doctor(repo_sample.repo_path(), true);
+
+    assert!(
+        output.contains("- 'sub-a': HEAD is detached, fixed: attached to 'main'\n"),
+        "Output: {output}"
+    );
+    assert_eq!(exit_code, 0);
+
+    let branch = _run("git rev-parse --abbrev-ref HEAD", subrepo_path).unwrap();
+    assert_eq!(branch.trim(), "main");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn doctor_leaves_detached_head_off_branch(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git checkout --detach", subrepo_path).unwrap();
+    _run("git commit --allow-empty -m detached", subrepo_path).unwrap();
+
+    let (output, exit_code, _) = run_doctor(repo_sample.repo_path(), true);
+
+    assert!(
+        output.contains("- 'sub-a': HEAD is detached, cannot be fixed automatically\n"),
+        "Output: {output}"
+    );
+    assert!(output.contains("Fixed 0 of 1 problems"), "Output: {output}");
+    assert_eq!(exit_code, 1);
+
+    let branch = _run("git rev-parse --abbrev-ref HEAD", subrepo_path).unwrap();
+    assert_eq!(branch.trim(), "HEAD");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn doctor_syncs_mismatched_url(repo_sample: TestRepo) {
+    _run(
+        "git config submodule.sub-a.url /nowhere/sub-a",
+        repo_sample.repo_path(),
+    )
+    .unwrap();
+
+    let (output, exit_code, _) = run_doctor(repo_sample.repo_path(), false);
+
+    assert!(
+        output.contains(
+            "- 'sub-a': .gitmodules URL './sub-a' differs from '/nowhere/sub-a' in \
+             .git/config\n"
+        ),
+        "Output: {output}"
+    );
+    assert_eq!(exit_code, 1);
+
+    let (output, exit_code, _) = run_doctor(repo_sample.repo_path(), true);
+
+    assert!(
+        output.contains("fixed: synced URL from .gitmodules"),
+        "Output: {output}"
+    );
+    assert_eq!(exit_code, 0);
+
+    let (output, _, _) = run_doctor(repo_sample.repo_path(), false);
+    assert!(output.contains("No problems found"), "Output: {output}");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn doctor_initializes_submodules(repo_sample: TestRepo) {
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m baseline", repo_sample.repo_path()).unwrap();
+    _run("git clone . clone", repo_sample.repo_path()).unwrap();
+    let clone_path = repo_sample.repo_path().join("clone");
+
+    let (output, exit_code, _) = run_doctor(&clone_path, false);
+
+    assert!(
+        output.contains("- 'sub-a': submodule is not initialized\n"),
+        "Output: {output}"
+    );
+    assert_eq!(exit_code, 1);
+
+    let (output, exit_code, _) = run_doctor(&clone_path, true);
+
+    assert!(
+        output.contains(
+            "- 'sub-a': submodule is not initialized, fixed: initialized on 'main'\n"
+        ),
+        "Output: {output}"
+    );
+    assert_eq!(exit_code, 0);
+
+    let branch =
+        _run("git rev-parse --abbrev-ref HEAD", &clone_path.join("sub-a")).unwrap();
+    assert_eq!(branch.trim(), "main");
+}
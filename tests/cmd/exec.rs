@@ -0,0 +1,191 @@
+use std::io::Cursor;
+
+use rstest::*;
+
+use git_wok::{cmd, config};
+
+use super::*;
+
+fn shell(script: &str) -> Vec<String> {
+    vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn exec_runs_in_each_repo_with_environment(repo_sample: TestRepo) {
+    // Run one repo at a time, the command writes straight to the terminal
+    let (output, exit_code) = _wok(
+        "exec -- sh -c 'echo \"$WOK_REPO_PATH@$WOK_REPO_HEAD in $(basename $PWD)\"'",
+        repo_sample.repo_path(),
+    );
+
+    assert_eq!(exit_code, 0);
+    assert!(
+        output.contains("Running 'sh -c") && output.contains("in 2 repositories..."),
+        "Output: {output}"
+    );
+    assert!(
+        output.contains("- 'sub-a':\nsub-a@main in sub-a\n"),
+        "Output: {output}"
+    );
+    assert!(
+        output.contains("- 'sub-b':\nsub-b@main in sub-b\n"),
+        "Output: {output}"
+    );
+    assert!(
+        output.ends_with("Summary:\n- 'sub-a': exit code 0\n- 'sub-b': exit code 0\n"),
+        "Output: {output}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn exec_exports_umbrella_path(repo_sample: TestRepo) {
+    let (output, _) = _wok(
+        "exec sub-a -- sh -c 'test -f \"$WOK_UMBRELLA/wok.toml\" && echo found'",
+        repo_sample.repo_path(),
+    );
+
+    assert!(output.contains("- 'sub-a':\nfound\n"), "Output: {output}");
+    assert!(!output.contains("- 'sub-b':"), "Output: {output}");
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn exec_parallel_honors_a_single_job(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let marker = repo_sample.repo_path().join("running");
+
+    // With one job the second run never sees the first one running
+    cmd::exec(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        true, // all
+        &[],  // repos
+        &shell(&format!(
+            "test ! -e {marker} && touch {marker} && sleep 0.2 && rm {marker}",
+            marker = marker.display()
+        )),
+        true,    // parallel
+        false,   // keep_going
+        Some(1), // jobs
+        &[],
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str
+            .ends_with("Summary:\n- 'sub-a': exit code 0\n- 'sub-b': exit code 0\n"),
+        "Output: {output_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn exec_stops_after_first_failure(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let result = cmd::exec(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        true, // all
+        &[],  // repos
+        &shell("exit 3"),
+        false, // parallel
+        false, // keep_going
+        None,  // jobs
+        &[],
+    );
+
+    let error = result.unwrap_err();
+    assert_eq!(error.to_string(), "Command failed in 1 of 2 repositories");
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.ends_with("Summary:\n- 'sub-a': exit code 3\n- 'sub-b': not run\n"),
+        "Output: {output_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn exec_keep_going_runs_everywhere(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let result = cmd::exec(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        true, // all
+        &[],  // repos
+        &shell("test \"$WOK_REPO_PATH\" = sub-b"),
+        false, // parallel
+        true,  // keep_going
+        None,  // jobs
+        &[],
+    );
+
+    let error = result.unwrap_err();
+    assert_eq!(error.to_string(), "Command failed in 1 of 2 repositories");
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str
+            .ends_with("Summary:\n- 'sub-a': exit code 1\n- 'sub-b': exit code 0\n"),
+        "Output: {output_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b-skip.toml")))]
+fn exec_all_honors_skip_for(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    actual_config.repos[0].skip_for.push("exec".to_string());
+
+    cmd::exec(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        true, // all
+        &[],  // repos
+        &shell("true"),
+        false, // parallel
+        false, // keep_going
+        None,  // jobs
+        &[],
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.ends_with("Summary:\n- 'sub-b': exit code 0\n"),
+        "Output: {output_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn exec_parallel_reports_in_configured_order(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::exec(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        true, // all
+        &[],  // repos
+        &shell("test \"$WOK_REPO_PATH\" = sub-a && sleep 0.2; echo $WOK_REPO_PATH"),
+        true,    // parallel
+        false,   // keep_going
+        Some(2), // jobs
+        &[],
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains("- 'sub-a':\nsub-a\n- 'sub-b':\nsub-b\n"),
+        "Output: {output_str}"
+    );
+}
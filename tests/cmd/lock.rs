@@ -23,7 +23,15 @@ fn lock_with_no_changes_skips_commit(repo_sample: TestRepo) {
     let initial_count = revwalk.count();
 
     // Run the lock command without making any changes
-    cmd::lock(&mut actual_config, &repo_sample.repo(), &mut output).unwrap();
+    cmd::lock(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &[],
+        None,
+        false,
+    )
+    .unwrap();
 
     // Check the output indicates no changes
     assert_eq!(
@@ -50,7 +58,15 @@ fn lock_with_no_submodules(repo_sample: TestRepo) {
     let initial_count = revwalk.count();
 
     // Run the lock command with no submodules
-    cmd::lock(&mut actual_config, &repo_sample.repo(), &mut output).unwrap();
+    cmd::lock(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &[],
+        None,
+        false,
+    )
+    .unwrap();
 
     // Check the output indicates no changes
     assert_eq!(
@@ -86,7 +102,7 @@ fn lock_with_changed_submodule(repo_sample: TestRepo) {
     let mut output = Cursor::new(Vec::new());
 
     // Run the lock command
-    cmd::lock(&mut actual_config, &repo, &mut output).unwrap();
+    cmd::lock(&mut actual_config, &repo, &mut output, &[], None, false).unwrap();
 
     // Check the output
     assert_eq!(
@@ -114,6 +130,39 @@ fn lock_with_changed_submodule(repo_sample: TestRepo) {
     );
 }
 
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn lock_leaves_skipped_repo_out(repo_sample: TestRepo) {
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    actual_config.repos[0].skip_for = vec![String::from("lock")];
+    actual_config.save(&repo_sample.config_path()).unwrap();
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m 'Initial setup'", repo_sample.repo_path()).unwrap();
+
+    for name in ["sub-a", "sub-b"] {
+        let subrepo_path = repo_sample.subrepo_path(name).unwrap();
+        _run("git commit --allow-empty -m 'Change'", subrepo_path).unwrap();
+    }
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::lock(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &[],
+        None,
+        false,
+    )
+    .unwrap();
+
+    let message = _run("git log -1 --format=%B", repo_sample.repo_path()).unwrap();
+    assert!(message.contains("sub-b:"), "Message: {message}");
+    assert!(!message.contains("sub-a:"), "Message: {message}");
+    assert_eq!(
+        _run("git status --porcelain", repo_sample.repo_path()).unwrap(),
+        " M sub-a\n"
+    );
+}
+
 #[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
 fn lock_with_short_commit_message(repo_sample: TestRepo) {
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
@@ -130,7 +179,7 @@ fn lock_with_short_commit_message(repo_sample: TestRepo) {
 
     // Run the lock command
     let repo = repo_sample.repo();
-    cmd::lock(&mut actual_config, &repo, &mut output).unwrap();
+    cmd::lock(&mut actual_config, &repo, &mut output, &[], None, false).unwrap();
 
     // Verify commit message includes full submodule message (not truncated)
     let head = repo.git_repo.head().unwrap();
@@ -143,3 +192,120 @@ fn lock_with_short_commit_message(repo_sample: TestRepo) {
     // Should not have ellipsis
     assert!(!message.contains("..."));
 }
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn lock_writes_lock_file(repo_sample: TestRepo) {
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m 'Initial setup'", repo_sample.repo_path()).unwrap();
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git commit --allow-empty -m 'Change in a'", subrepo_path).unwrap();
+    let commit = _run("git rev-parse HEAD", subrepo_path).unwrap();
+
+    let lock_path = config::Lock::path_for(&repo_sample.config_path());
+    assert_eq!(lock_path, repo_sample.repo_path().join("wok.lock"));
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    cmd::lock(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &[],
+        Some(&lock_path),
+        false,
+    )
+    .unwrap();
+
+    let lock = config::Lock::load(&lock_path).unwrap();
+    assert_eq!(lock.repos.len(), 2);
+    assert_eq!(lock.repos[0].path, path::Path::new("sub-a"));
+    assert_eq!(lock.repos[0].branch, "main");
+    assert_eq!(lock.repos[0].commit, commit.trim());
+    assert_eq!(lock.repos[0].url, None);
+    assert_eq!(lock.repos[1].path, path::Path::new("sub-b"));
+
+    // The lock file is committed along with the gitlinks
+    let committed = _run("git ls-files wok.lock", repo_sample.repo_path()).unwrap();
+    assert_eq!(committed.trim(), "wok.lock");
+    let status = _run("git status --porcelain", repo_sample.repo_path()).unwrap();
+    assert_eq!(status, "");
+
+    // Locking the same state again leaves the lock file as it is
+    let lock_contents = fs::read_to_string(&lock_path).unwrap();
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    cmd::lock(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &[],
+        Some(&lock_path),
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(fs::read_to_string(&lock_path).unwrap(), lock_contents);
+    assert!(
+        String::from_utf8_lossy(output.get_ref()).ends_with("nothing to lock\n"),
+        "Output: {}",
+        String::from_utf8_lossy(output.get_ref())
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn load_ignores_lock_of_unconfigured_repo(repo_sample: TestRepo) {
+    fs::write(
+        repo_sample.repo_path().join("wok.lock"),
+        "version = \"1.0\"\n\n[[repo]]\npath = \"sub-x\"\nbranch = \"main\"\n\
+         commit = \"0123456789abcdef0123456789abcdef01234567\"\n\
+         locked_at = 2025-01-02T03:04:05Z\n",
+    )
+    .unwrap();
+
+    let actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    assert_eq!(actual_config.stale_locked, [path::PathBuf::from("sub-x")]);
+    assert!(actual_config.lock.unwrap().repos.is_empty());
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn lock_dry_run_prints_commit_message(repo_sample: TestRepo) {
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m 'Initial setup'", repo_sample.repo_path()).unwrap();
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git commit --allow-empty -m 'Change in a'", subrepo_path).unwrap();
+    let umbrella_head = _run("git rev-parse HEAD", repo_sample.repo_path()).unwrap();
+    let lock_path = config::Lock::path_for(&repo_sample.config_path());
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    cmd::lock(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &[],
+        Some(&lock_path),
+        true,
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        format!(
+            "Would write lock file `{}`\n\
+             Would commit to the umbrella repo:\n    \
+             Lock submodule state\n\
+             \n    \
+             Changed submodules:\n    \
+             - sub-a: Change in a\n",
+            lock_path.display()
+        )
+    );
+    assert!(!lock_path.exists());
+    assert_eq!(
+        _run("git rev-parse HEAD", repo_sample.repo_path()).unwrap(),
+        umbrella_head
+    );
+    let status = _run("git status --porcelain", repo_sample.repo_path()).unwrap();
+    assert_eq!(status, " M sub-a\n");
+}
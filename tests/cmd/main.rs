@@ -6,15 +6,22 @@ use std::path::{self, PathBuf};
 use std::{env, fs, process};
 
 mod assemble;
+mod branch;
+mod clone;
 mod completion;
+mod doctor;
+mod exec;
 mod init;
 mod lock;
 mod push;
 mod repo_add;
 mod repo_rm;
+mod stash;
 mod status;
 mod switch;
+mod sync;
 mod tag;
+mod undo;
 mod update;
 mod version;
 mod version_migration;
@@ -137,3 +144,17 @@ fn _run(cmd: &str, cwd: &PathBuf) -> Result<String> {
         .output()?;
     Ok(String::from_utf8_lossy(&output.stdout).into_owned())
 }
+
+/// Run the `wok` binary, returning its output and exit code
+fn _wok(args: &str, cwd: &PathBuf) -> (String, i32) {
+    let wok_binary = format!("{}/target/debug/wok", env!("CARGO_MANIFEST_DIR"));
+    let output = process::Command::new(wok_binary)
+        .args(shell_words::split(args).unwrap())
+        .current_dir(cwd)
+        .output()
+        .unwrap();
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        output.status.code().unwrap(),
+    )
+}
@@ -16,11 +16,10 @@ fn push_all_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false, // set_upstream
-        true,  // all
-        None,  // branch
-        true,  // include umbrella
-        &[],   // repos
+        &cmd::PushOptions {
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -47,11 +46,10 @@ fn push_specific_repo(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,                                // set_upstream
-        false,                                // all
-        None,                                 // branch
-        true,                                 // include umbrella
-        &[std::path::PathBuf::from("sub-a")], // repos
+        &cmd::PushOptions {
+            repos: &[std::path::PathBuf::from("sub-a")],
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -78,11 +76,10 @@ fn push_with_upstream_option(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        true,  // set_upstream
-        false, // all
-        None,  // branch
-        true,  // include umbrella
-        &[],   // repos
+        &cmd::PushOptions {
+            set_upstream: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -109,11 +106,10 @@ fn push_with_branch_option(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,           // set_upstream
-        false,           // all
-        Some("develop"), // branch
-        true,            // include umbrella
-        &[],             // repos
+        &cmd::PushOptions {
+            branch: Some("develop"),
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -134,11 +130,7 @@ fn push_with_no_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false, // set_upstream
-        false, // all
-        None,  // branch
-        true,  // include umbrella
-        &[],   // repos
+        &cmd::PushOptions::default(),
     )
     .unwrap();
 
@@ -159,11 +151,10 @@ fn push_nonexistent_repo(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,                                      // set_upstream
-        false,                                      // all
-        None,                                       // branch
-        true,                                       // include umbrella
-        &[std::path::PathBuf::from("nonexistent")], // repos
+        &cmd::PushOptions {
+            repos: &[std::path::PathBuf::from("nonexistent")],
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -184,11 +175,10 @@ fn push_multiple_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false, // set_upstream
-        true,  // all
-        None,  // branch
-        true,  // include umbrella
-        &[],   // repos
+        &cmd::PushOptions {
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -210,11 +200,10 @@ fn push_all_skips_configured_repo(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,
-        true,
-        None,
-        true,
-        &[],
+        &cmd::PushOptions {
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -235,11 +224,11 @@ fn push_all_includes_explicit_repo_overrides_skip(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,
-        true,
-        None,
-        true,
-        &[std::path::PathBuf::from("sub-a")],
+        &cmd::PushOptions {
+            all: true,
+            repos: &[std::path::PathBuf::from("sub-a")],
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -261,14 +250,13 @@ fn push_specific_multiple_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false, // set_upstream
-        false, // all
-        None,  // branch
-        true,  // include umbrella
-        &[
-            std::path::PathBuf::from("sub-a"),
-            std::path::PathBuf::from("sub-b"),
-        ], // repos
+        &cmd::PushOptions {
+            repos: &[
+                std::path::PathBuf::from("sub-a"),
+                std::path::PathBuf::from("sub-b"),
+            ],
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -290,11 +278,11 @@ fn push_skips_umbrella_when_disabled(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,
-        true,
-        None,
-        false,
-        &[],
+        &cmd::PushOptions {
+            all: true,
+            include_umbrella: false,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -314,11 +302,10 @@ fn push_no_repos_without_umbrella(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,
-        false,
-        None,
-        false,
-        &[],
+        &cmd::PushOptions {
+            include_umbrella: false,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -326,3 +313,485 @@ fn push_no_repos_without_umbrella(repo_sample: TestRepo) {
     assert!(output_str.contains("No repositories to push"));
     assert!(!output_str.contains("- 'umbrella':"));
 }
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn push_with_jobs_reports_in_configured_order(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::push(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::PushOptions {
+            all: true,
+            jobs: 4,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    let sub_a = output_str.find("- 'sub-a':").unwrap();
+    let sub_b = output_str.find("- 'sub-b':").unwrap();
+    let umbrella = output_str.find("- 'umbrella':").unwrap();
+    assert!(sub_a < sub_b, "Output: {output_str}");
+    assert!(sub_b < umbrella, "Output: {output_str}");
+    assert!(output_str.contains("Successfully processed 3 repositories"));
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn push_report_records_results(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::push(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::PushOptions {
+            all: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let value = serde_json::to_value(&report).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({
+            "command": "push",
+            "repos": [
+                {"path": "sub-a", "branch": "main", "result": "no_remote"},
+                {"path": ".", "umbrella": true, "branch": "main", "result": "no_remote"},
+            ],
+            "summary": {"branch": "main", "repos": 2, "failed": 0},
+        })
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b-groups.toml")))]
+fn push_group_combines_with_explicit_repos(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::push(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::PushOptions {
+            include_umbrella: false,
+            repos: &[std::path::PathBuf::from("sub-b")],
+            groups: &["backend".into()],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(output_str.contains("Pushing 2 repositories to branch 'main'"));
+    assert!(output_str.contains("- 'sub-a':"));
+    assert!(output_str.contains("- 'sub-b':"));
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn push_dry_run_prints_refspecs(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let remote_path = repo_sample.repo_path().join("sub-a.git");
+    _run("git init --bare sub-a.git", repo_sample.repo_path()).unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        subrepo_path,
+    )
+    .unwrap();
+    // Listing the refs of an empty remote trips up libgit2
+    _run("git push -q origin other", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::push(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::PushOptions {
+            set_upstream: true,
+            all: true,
+            include_umbrella: false,
+            dry_run: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        "Pushing 1 repositories to branch 'main'...\n\
+         - 'sub-a': would push 'refs/heads/main:refs/heads/main' to 'origin' and \
+         set upstream\n\
+         Successfully processed 1 repositories\n"
+    );
+    let value = serde_json::to_value(&report).unwrap();
+    assert_eq!(value["repos"][0]["result"], "planned");
+    assert_eq!(value["summary"]["dry_run"], true);
+
+    assert_eq!(_run("git branch", &remote_path).unwrap(), "  other\n");
+    let upstream = _run("git config --get branch.main.remote", subrepo_path).unwrap();
+    assert_eq!(upstream, "");
+}
+
+/// Give the umbrella and `sub-a` bare remotes that already have the `other`
+/// branch, and record a new `sub-a` commit in the umbrella.
+fn setup_remotes(repo_sample: &TestRepo) -> (PathBuf, PathBuf) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let umbrella_remote = repo_sample.repo_path().join("umbrella.git");
+    let subrepo_remote = repo_sample.repo_path().join("sub-a.git");
+    for (remote_path, repo_path) in [
+        (&umbrella_remote, repo_sample.repo_path()),
+        (&subrepo_remote, subrepo_path),
+    ] {
+        _run(
+            &format!("git init -q --bare {}", remote_path.display()),
+            repo_path,
+        )
+        .unwrap();
+        _run(
+            &format!("git remote add origin {}", remote_path.display()),
+            repo_path,
+        )
+        .unwrap();
+        // Listing the refs of an empty remote trips up libgit2
+        _run("git push -q origin other", repo_path).unwrap();
+    }
+
+    _run("git commit -q --allow-empty -m 'New work'", subrepo_path).unwrap();
+    _run("git add sub-a", repo_sample.repo_path()).unwrap();
+    _run("git commit -q -m 'Record sub-a'", repo_sample.repo_path()).unwrap();
+
+    (umbrella_remote, subrepo_remote)
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn push_blocks_umbrella_referencing_unpushed_commits(repo_sample: TestRepo) {
+    let (umbrella_remote, _) = setup_remotes(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let commit = _run("git rev-parse HEAD", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    actual_config.repos[0].skip_for = vec![String::from("push")];
+
+    let report = cmd::push(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::PushOptions {
+            all: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        format!(
+            "Pushing 1 repositories to branch 'main'...\n\
+             - 'umbrella': not pushed to 'main', it references commits missing \
+             from subrepo remotes:\n    \
+             - 'sub-a': {} is not on 'origin'\n\
+             Processed 1 repositories, 1 failed\n",
+            &commit[..8]
+        )
+    );
+    assert_eq!(report.exit_code, 1);
+    let value = serde_json::to_value(&report).unwrap();
+    assert_eq!(value["repos"][0]["result"], "blocked");
+    assert_eq!(value["repos"][0]["missing"][0]["commit"], commit.trim());
+    assert_eq!(value["summary"]["failed"], 1);
+    assert_eq!(
+        _run(
+            "git for-each-ref --format=%(refname:short)",
+            &umbrella_remote
+        )
+        .unwrap(),
+        "other\n"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn push_finds_gitlinks_below_unfetched_remote_tips(repo_sample: TestRepo) {
+    let (_, subrepo_remote) = setup_remotes(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git push -q origin main", subrepo_path).unwrap();
+    // Someone else builds on the recorded commit after our last fetch
+    let remote_tip = _run(
+        "git -c user.name=Test -c user.email=test@localhost commit-tree main^{tree} \
+         -p main -m 'Remote work'",
+        &subrepo_remote,
+    )
+    .unwrap();
+    _run(
+        &format!("git update-ref refs/heads/main {}", remote_tip.trim()),
+        &subrepo_remote,
+    )
+    .unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    actual_config.repos[0].skip_for = vec![String::from("push")];
+
+    let report = cmd::push(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::PushOptions {
+            all: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        "Pushing 1 repositories to branch 'main'...\n\
+         - 'umbrella': pushed to 'main'\n\
+         Successfully processed 1 repositories\n"
+    );
+    assert_eq!(report.exit_code, 0);
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn push_umbrella_after_its_subrepo_commits(repo_sample: TestRepo) {
+    let (umbrella_remote, subrepo_remote) = setup_remotes(&repo_sample);
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::push(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::PushOptions {
+            all: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        "Pushing 2 repositories to branch 'main'...\n\
+         - 'sub-a': pushed to 'main'\n\
+         - 'umbrella': pushed to 'main'\n\
+         Successfully processed 2 repositories\n"
+    );
+    assert_eq!(report.exit_code, 0);
+    assert_eq!(
+        _run(
+            "git for-each-ref --format=%(refname:short)",
+            &umbrella_remote
+        )
+        .unwrap(),
+        "main\nother\n"
+    );
+    assert_eq!(
+        _run(
+            "git for-each-ref --format=%(refname:short)",
+            &subrepo_remote
+        )
+        .unwrap(),
+        "main\nother\n"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn push_atomic_pushes_nothing_when_a_repo_would_be_rejected(repo_sample: TestRepo) {
+    let (umbrella_remote, subrepo_remote) = setup_remotes(&repo_sample);
+    // The remote umbrella branch has a commit the local one lacks
+    _run("git push -q origin other:main", repo_sample.repo_path()).unwrap();
+    _run(
+        "git commit -q --allow-empty -m 'Remote work'",
+        repo_sample.repo_path(),
+    )
+    .unwrap();
+    _run("git push -q origin HEAD:main", repo_sample.repo_path()).unwrap();
+    _run("git reset -q --hard HEAD~1", repo_sample.repo_path()).unwrap();
+    let remote_main = _run("git rev-parse main", &umbrella_remote).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let result = cmd::push(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::PushOptions {
+            all: true,
+            atomic: true,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Cannot push to 'main'; no repositories were pushed"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        "Pushing 2 repositories to branch 'main'...\n\
+         - 'umbrella': cannot be pushed - 'main' on 'origin' has commits that are \
+         not in the local branch\n"
+    );
+    assert_eq!(
+        _run(
+            "git for-each-ref --format=%(refname:short)",
+            &subrepo_remote
+        )
+        .unwrap(),
+        "other\n"
+    );
+    assert_eq!(
+        _run("git rev-parse main", &umbrella_remote).unwrap(),
+        remote_main
+    );
+}
+
+/// Push the current `sub-a` commit to its remote, then amend it locally so
+/// that the branches diverge.
+fn diverge_subrepo(repo_sample: &TestRepo) -> String {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git push -q origin main", subrepo_path).unwrap();
+    _run(
+        "git commit -q --amend --allow-empty -m 'Rewritten work'",
+        subrepo_path,
+    )
+    .unwrap();
+    _run("git rev-parse main", subrepo_path).unwrap()
+}
+
+fn push_sub_a(
+    repo_sample: &TestRepo,
+    force_with_lease: bool,
+) -> (String, serde_json::Value, i32) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::push(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::PushOptions {
+            all: true,
+            include_umbrella: false,
+            force_with_lease,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    (
+        String::from_utf8_lossy(output.get_ref()).into_owned(),
+        serde_json::to_value(&report).unwrap(),
+        report.exit_code,
+    )
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn push_reports_non_fast_forward_as_rejected(repo_sample: TestRepo) {
+    let (_, subrepo_remote) = setup_remotes(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let remote_main = _run("git rev-parse main", subrepo_path).unwrap();
+    diverge_subrepo(&repo_sample);
+
+    let (output, report, exit_code) = push_sub_a(&repo_sample, false);
+
+    assert_eq!(
+        output,
+        "Pushing 1 repositories to branch 'main'...\n\
+         - 'sub-a': push to 'main' rejected - 'main' on 'origin' has commits that \
+         are not in the local branch\n\
+         Processed 1 repositories, 1 failed\n"
+    );
+    assert_eq!(report["repos"][0]["result"], "rejected");
+    assert_eq!(report["summary"]["failed"], 1);
+    assert_eq!(exit_code, 1);
+    assert_eq!(
+        _run("git rev-parse main", &subrepo_remote).unwrap(),
+        remote_main
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn push_force_with_lease_overwrites_fetched_branch(repo_sample: TestRepo) {
+    let (_, subrepo_remote) = setup_remotes(&repo_sample);
+    let local_main = diverge_subrepo(&repo_sample);
+
+    let (output, report, exit_code) = push_sub_a(&repo_sample, true);
+
+    assert_eq!(
+        output,
+        "Pushing 1 repositories to branch 'main'...\n\
+         - 'sub-a': pushed to 'main'\n\
+         Successfully processed 1 repositories\n"
+    );
+    assert_eq!(report["repos"][0]["result"], "pushed");
+    assert_eq!(exit_code, 0);
+    assert_eq!(
+        _run("git rev-parse main", &subrepo_remote).unwrap(),
+        local_main
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn push_force_with_lease_rejects_unfetched_changes(repo_sample: TestRepo) {
+    let (_, subrepo_remote) = setup_remotes(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    diverge_subrepo(&repo_sample);
+    // Someone else moves the remote branch after our last fetch
+    let other = _run("git rev-parse other", subrepo_path).unwrap();
+    _run(
+        &format!("git update-ref refs/heads/main {}", other.trim()),
+        &subrepo_remote,
+    )
+    .unwrap();
+
+    let (output, report, exit_code) = push_sub_a(&repo_sample, true);
+
+    assert_eq!(
+        output,
+        "Pushing 1 repositories to branch 'main'...\n\
+         - 'sub-a': push to 'main' rejected - 'main' on 'origin' has changed since \
+         it was last fetched\n\
+         Processed 1 repositories, 1 failed\n"
+    );
+    assert_eq!(report["repos"][0]["result"], "rejected");
+    assert_eq!(
+        report["repos"][0]["reason"],
+        "'main' on 'origin' has changed since it was last fetched"
+    );
+    assert_eq!(exit_code, 1);
+    assert_eq!(_run("git rev-parse main", &subrepo_remote).unwrap(), other);
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn push_exits_with_failure_when_a_repo_fails_to_push(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let missing_remote = repo_sample.repo_path().join("missing.git");
+    _run(
+        &format!("git remote add origin {}", missing_remote.display()),
+        subrepo_path,
+    )
+    .unwrap();
+
+    let (output, exit_code) = _wok("push --no-umbrella", repo_sample.repo_path());
+
+    assert!(
+        output.contains("- 'sub-a': failed to push to 'main' - "),
+        "Output: {output}"
+    );
+    assert!(
+        output.ends_with("Processed 1 repositories, 1 failed\n"),
+        "Output: {output}"
+    );
+    assert_eq!(exit_code, 1);
+}
@@ -12,3 +12,106 @@ fn in_a_single_repo(repo_sample: TestRepo, expected_config: String) {
 
     assert_eq!(actual_config.dump().unwrap(), expected_config);
 }
+
+/// Create a repo outside the umbrella to add from its URL.
+fn source_repo() -> assert_fs::TempDir {
+    let temp_dir = assert_fs::TempDir::new().unwrap();
+    TestRepo::init_repo(&temp_dir.path().to_path_buf());
+    _run(
+        "git commit --allow-empty -m 'on other'",
+        &temp_dir.path().to_path_buf(),
+    )
+    .unwrap();
+    _run("git branch -f other", &temp_dir.path().to_path_buf()).unwrap();
+    _run("git reset --hard HEAD~1", &temp_dir.path().to_path_buf()).unwrap();
+    temp_dir
+}
+
+#[rstest(repo_sample(vec![], Some("empty.toml")), expected_config("a.toml"))]
+fn from_url_clones_and_commits(repo_sample: TestRepo, expected_config: String) {
+    let source = source_repo();
+    let mut actual_config = Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::repo::add_from_url(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &repo_sample.config_path(),
+        &source.path().to_string_lossy(),
+        path::Path::new("sub-a"),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(actual_config.dump().unwrap(), expected_config);
+    assert_eq!(
+        fs::read_to_string(repo_sample.config_path()).unwrap(),
+        expected_config
+    );
+
+    let tracked = _run(
+        "git config -f .gitmodules submodule.sub-a.branch",
+        repo_sample.repo_path(),
+    )
+    .unwrap();
+    assert_eq!(tracked.trim(), "main");
+
+    let message = _run("git log -1 --format=%s", repo_sample.repo_path()).unwrap();
+    assert_eq!(message.trim(), "Add subrepo sub-a");
+    let status = _run("git status --porcelain", repo_sample.repo_path()).unwrap();
+    assert_eq!(status, "");
+}
+
+#[rstest(repo_sample(vec![], Some("empty.toml")))]
+fn from_url_checks_out_branch(repo_sample: TestRepo) {
+    let source = source_repo();
+    let mut actual_config = Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::repo::add_from_url(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &repo_sample.config_path(),
+        &source.path().to_string_lossy(),
+        path::Path::new("sub-a"),
+        Some("other"),
+    )
+    .unwrap();
+
+    assert_eq!(actual_config.repos[0].head, "other");
+
+    let subrepo_path = repo_sample.repo_path().join("sub-a");
+    let branch = _run("git rev-parse --abbrev-ref HEAD", &subrepo_path).unwrap();
+    assert_eq!(branch.trim(), "other");
+    let upstream =
+        _run("git rev-parse --abbrev-ref @{upstream}", &subrepo_path).unwrap();
+    assert_eq!(upstream.trim(), "origin/other");
+    let message = _run("git log -1 --format=%s", &subrepo_path).unwrap();
+    assert_eq!(message.trim(), "on other");
+
+    let tracked = _run(
+        "git config -f .gitmodules submodule.sub-a.branch",
+        repo_sample.repo_path(),
+    )
+    .unwrap();
+    assert_eq!(tracked.trim(), "other");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn from_url_rejects_configured_path(repo_sample: TestRepo) {
+    let source = source_repo();
+    let mut actual_config = Config::load(&repo_sample.config_path()).unwrap();
+
+    let error = cmd::repo::add_from_url(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &repo_sample.config_path(),
+        &source.path().to_string_lossy(),
+        path::Path::new("sub-a"),
+        None,
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Subrepo at `sub-a` is already in the wok file"
+    );
+}
@@ -1,5 +1,6 @@
 use super::*;
 use pretty_assertions::assert_eq;
+use std::fs;
 
 use git_wok::{self, cmd, config::Config};
 
@@ -12,3 +13,136 @@ fn in_a_single_repo(repo_sample: TestRepo, expected_config: String) {
 
     assert_eq!(actual_config.dump().unwrap(), expected_config);
 }
+
+/// Commit the sample workspace and push `sub-a` to a bare remote, so that
+/// nothing would be lost by purging it.
+fn publish_sub_a(repo_sample: &TestRepo) -> assert_fs::TempDir {
+    let remote_dir = assert_fs::TempDir::new().unwrap();
+    let remote_path = remote_dir.path().to_path_buf();
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+
+    _run("git init --bare", &remote_path).unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        subrepo_path,
+    )
+    .unwrap();
+    _run("git push -u origin main other", subrepo_path).unwrap();
+
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m baseline", repo_sample.repo_path()).unwrap();
+
+    remote_dir
+}
+
+fn purge(repo_sample: &TestRepo, delete_git_dir: bool, force: bool) -> Result<Config> {
+    let mut actual_config = Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::repo::rm_purge(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &repo_sample.config_path(),
+        path::Path::new("sub-a"),
+        delete_git_dir,
+        force,
+    )?;
+
+    Ok(actual_config)
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")), expected_config("empty.toml"))]
+fn purge_removes_submodule(repo_sample: TestRepo, expected_config: String) {
+    let _remote = publish_sub_a(&repo_sample);
+
+    let actual_config = purge(&repo_sample, false, false).unwrap();
+
+    assert_eq!(actual_config.dump().unwrap(), expected_config);
+    assert_eq!(
+        fs::read_to_string(repo_sample.config_path()).unwrap(),
+        expected_config
+    );
+    assert!(!repo_sample.repo_path().join("sub-a").exists());
+    assert!(repo_sample.repo_path().join(".git/modules/sub-a").exists());
+
+    let gitmodules = fs::read_to_string(repo_sample.repo_path().join(".gitmodules"))
+        .unwrap_or_default();
+    assert!(!gitmodules.contains("sub-a"), "{gitmodules}");
+    let tracked = _run("git ls-files sub-a", repo_sample.repo_path()).unwrap();
+    assert_eq!(tracked, "");
+
+    let message = _run("git log -1 --format=%s", repo_sample.repo_path()).unwrap();
+    assert_eq!(message.trim(), "Remove subrepo sub-a");
+    let status = _run("git status --porcelain", repo_sample.repo_path()).unwrap();
+    assert_eq!(status, "");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn purge_drops_repo_from_lock(repo_sample: TestRepo) {
+    let _remote = publish_sub_a(&repo_sample);
+    let lock_path = git_wok::config::Lock::path_for(&repo_sample.config_path());
+    let mut actual_config = Config::load(&repo_sample.config_path()).unwrap();
+    cmd::lock(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut std::io::sink(),
+        &[],
+        Some(&lock_path),
+        false,
+    )
+    .unwrap();
+    _run("git add wok.lock", repo_sample.repo_path()).unwrap();
+    _run("git commit -m lock", repo_sample.repo_path()).unwrap();
+
+    purge(&repo_sample, false, false).unwrap();
+
+    let actual_config = Config::load(&repo_sample.config_path()).unwrap();
+    assert!(actual_config.lock.unwrap().repos.is_empty());
+    assert_eq!(
+        _run("git status --porcelain", repo_sample.repo_path()).unwrap(),
+        ""
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn purge_deletes_git_dir(repo_sample: TestRepo) {
+    let _remote = publish_sub_a(&repo_sample);
+
+    purge(&repo_sample, true, false).unwrap();
+
+    assert!(!repo_sample.repo_path().join(".git/modules/sub-a").exists());
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn purge_refuses_uncommitted_changes(repo_sample: TestRepo) {
+    let _remote = publish_sub_a(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    fs::write(subrepo_path.join("file.txt"), "data").unwrap();
+
+    let error = purge(&repo_sample, false, false).unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Subrepo at `sub-a` has uncommitted changes; use --force to remove it anyway"
+    );
+    assert!(subrepo_path.join("file.txt").exists());
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn purge_refuses_unpushed_commits_unless_forced(repo_sample: TestRepo) {
+    let _remote = publish_sub_a(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git commit --allow-empty -m unpushed", subrepo_path).unwrap();
+
+    let error = purge(&repo_sample, false, false).unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Subrepo at `sub-a` has commits not pushed to any remote; use --force to \
+         remove it anyway"
+    );
+
+    let actual_config = purge(&repo_sample, false, true).unwrap();
+
+    assert!(actual_config.repos.is_empty());
+    assert!(!repo_sample.repo_path().join("sub-a").exists());
+}
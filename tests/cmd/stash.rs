@@ -0,0 +1,82 @@
+use std::io::Cursor;
+
+use pretty_assertions::assert_eq;
+use rstest::*;
+
+use git_wok::cmd;
+
+use super::*;
+
+/// Leave a wok stash with a change to `file.txt` in `sub-a`.
+fn make_stash(repo_sample: &TestRepo) -> &PathBuf {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    fs::write(subrepo_path.join("file.txt"), "line\n").unwrap();
+    _run("git add file.txt", subrepo_path).unwrap();
+    _run("git commit -q -m 'Add file'", subrepo_path).unwrap();
+    fs::write(subrepo_path.join("file.txt"), "local line\n").unwrap();
+
+    let subrepo = repo::Repo::new(subrepo_path, None).unwrap();
+    assert!(subrepo.autostash("update").unwrap().is_some());
+    subrepo_path
+}
+
+fn run_stash(repo_sample: &TestRepo, action: cmd::StashAction) -> (String, Result<()>) {
+    let mut output = Cursor::new(Vec::new());
+    let result = cmd::stash(&repo_sample.repo(), &mut output, action);
+    (
+        String::from_utf8_lossy(output.get_ref()).into_owned(),
+        result,
+    )
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], None))]
+fn stash_list_pop_and_drop(repo_sample: TestRepo) {
+    let subrepo_path = make_stash(&repo_sample);
+
+    let (output, result) = run_stash(&repo_sample, cmd::StashAction::List);
+    result.unwrap();
+    assert_eq!(
+        output,
+        "- 'sub-a': stash@{0}: On main: wok autostash before update\n"
+    );
+
+    let (output, result) = run_stash(&repo_sample, cmd::StashAction::Pop);
+    result.unwrap();
+    assert_eq!(output, "- 'sub-a': applied and dropped stash@{0}\n");
+    assert_eq!(
+        fs::read_to_string(subrepo_path.join("file.txt")).unwrap(),
+        "local line\n"
+    );
+
+    make_stash(&repo_sample);
+    let (output, result) = run_stash(&repo_sample, cmd::StashAction::Drop);
+    result.unwrap();
+    assert_eq!(output, "- 'sub-a': dropped stash@{0}\n");
+    assert_eq!(
+        fs::read_to_string(subrepo_path.join("file.txt")).unwrap(),
+        "line\n"
+    );
+
+    let (output, result) = run_stash(&repo_sample, cmd::StashAction::List);
+    result.unwrap();
+    assert_eq!(output, "No stashes made by wok\n");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], None))]
+fn stash_ignores_stashes_not_made_by_wok(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    fs::write(subrepo_path.join("file.txt"), "line\n").unwrap();
+    _run("git add file.txt", subrepo_path).unwrap();
+    _run("git stash -q", subrepo_path).unwrap();
+
+    let (output, result) = run_stash(&repo_sample, cmd::StashAction::Pop);
+    result.unwrap();
+    assert_eq!(output, "No stashes made by wok\n");
+    assert_eq!(
+        _run("git stash list", subrepo_path)
+            .unwrap()
+            .lines()
+            .count(),
+        1
+    );
+}
@@ -3,7 +3,7 @@ use std::io::Cursor;
 use pretty_assertions::assert_eq;
 use rstest::*;
 
-use git_wok::{cmd, config};
+use git_wok::{cmd, config, output};
 
 use super::*;
 
@@ -12,7 +12,16 @@ fn no_submodules_no_changes(repo_sample: TestRepo) {
     let mut output = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     assert_eq!(
         String::from_utf8_lossy(output.get_ref()),
@@ -26,7 +35,16 @@ fn with_submodules_branch_matches_no_changes(repo_sample: TestRepo) {
     let mut output = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let expected = "✓ (umbrella) on branch 'main', all clean\n✓ 'sub-a' on branch 'main', all clean\n";
     assert_eq!(String::from_utf8_lossy(output.get_ref()), expected);
@@ -55,7 +73,16 @@ fn status_shows_up_to_date_with_remote(repo_sample: TestRepo) {
     let mut output = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(
@@ -92,7 +119,16 @@ fn status_shows_ahead_of_remote(repo_sample: TestRepo) {
     let mut output = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(
@@ -132,7 +168,16 @@ fn status_shows_ahead_of_remote_multiple_commits(repo_sample: TestRepo) {
     let mut output = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(
@@ -183,7 +228,16 @@ fn status_shows_behind_remote(repo_sample: TestRepo) {
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
     // Fetch to update remote refs, then check status
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, true).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        true,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(
@@ -239,7 +293,16 @@ fn status_shows_diverged(repo_sample: TestRepo) {
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
     // Fetch to update remote refs, then check status
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, true).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        true,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(
@@ -254,7 +317,16 @@ fn status_handles_no_tracking_branch(repo_sample: TestRepo) {
     let mut output = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     // Should not show any remote status
@@ -293,7 +365,16 @@ fn status_with_fetch_flag_updates_remote_refs(repo_sample: TestRepo) {
     // First status check - should be up to date
     let mut output1 = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output1, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output1,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str1 = String::from_utf8_lossy(output1.get_ref());
     assert!(
@@ -322,7 +403,16 @@ fn status_with_fetch_flag_updates_remote_refs(repo_sample: TestRepo) {
     // Status without fetch - should still show up to date (stale remote refs)
     let mut output2 = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output2, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output2,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str2 = String::from_utf8_lossy(output2.get_ref());
     assert!(
@@ -333,7 +423,16 @@ fn status_with_fetch_flag_updates_remote_refs(repo_sample: TestRepo) {
     // Status with fetch - should now show behind
     let mut output3 = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output3, true).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output3,
+        true,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str3 = String::from_utf8_lossy(output3.get_ref());
     assert!(
@@ -368,7 +467,16 @@ fn status_shows_umbrella_remote_status(repo_sample: TestRepo) {
     let mut output = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(
@@ -394,7 +502,16 @@ fn with_submodules_branch_doesnt_match_no_changes(repo_sample: TestRepo) {
     let mut output = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
 
@@ -442,7 +559,16 @@ fn with_submodules_branch_doesnt_match_with_changes(repo_sample: TestRepo) {
     let mut output = Cursor::new(Vec::new());
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
-    cmd::status(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
 
@@ -466,3 +592,320 @@ fn with_submodules_branch_doesnt_match_with_changes(repo_sample: TestRepo) {
         "Expected sub-a to show 'dirty' with uncommitted changes: {output_str}"
     );
 }
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn status_with_jobs_keeps_configured_order(repo_sample: TestRepo) {
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let subrepo_path = repo_sample.subrepo_path("sub-b").unwrap();
+    fs::write(subrepo_path.join("DIRTY.md"), "dirty").unwrap();
+
+    let mut sequential = Cursor::new(Vec::new());
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut sequential,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
+
+    let mut parallel = Cursor::new(Vec::new());
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut parallel,
+        false,
+        false,
+        4,
+        &[],
+    )
+    .unwrap();
+
+    let parallel_str = String::from_utf8_lossy(parallel.get_ref());
+    assert_eq!(String::from_utf8_lossy(sequential.get_ref()), parallel_str);
+    assert!(
+        parallel_str.ends_with(
+            "✓ 'sub-a' on branch 'main', all clean\n✗ 'sub-b' on branch 'main', dirty\n"
+        ),
+        "Output: {parallel_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn status_report_records_remote_comparison(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_paths.get("sub-a").unwrap();
+
+    _run("git add .", &repo_sample.repo_path).unwrap();
+    _run("git commit -m baseline", &repo_sample.repo_path).unwrap();
+
+    let remote_parent = repo_sample.repo_path.join("remotes");
+    fs::create_dir_all(&remote_parent).unwrap();
+    let remote_path = remote_parent.join("sub-a.git");
+
+    _run("git init --bare sub-a.git", &remote_parent).unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        subrepo_path,
+    )
+    .unwrap();
+    _run("git push -u origin main", subrepo_path).unwrap();
+    _run("git commit --allow-empty -m 'local commit'", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
+
+    let mut json = Cursor::new(Vec::new());
+    output::write_report(&mut json, output::Format::Json, &report).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(json.get_ref()).unwrap();
+
+    assert_eq!(value["command"], "status");
+    assert_eq!(value["repos"][0]["path"], ".");
+    assert_eq!(value["repos"][0]["umbrella"], true);
+    assert_eq!(
+        value["repos"][1],
+        serde_json::json!({
+            "path": "sub-a",
+            "branch": "main",
+            "clean": true,
+            "remote": {
+                "upstream": "origin/main",
+                "state": "ahead",
+                "ahead": 1,
+                "behind": 0,
+            },
+        })
+    );
+    assert_eq!(
+        value["summary"],
+        serde_json::json!({"repos": 1, "clean": false})
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn status_report_as_ndjson(repo_sample: TestRepo) {
+    _run("git add .", &repo_sample.repo_path).unwrap();
+    _run("git commit -m baseline", &repo_sample.repo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &[],
+    )
+    .unwrap();
+
+    let mut ndjson = Cursor::new(Vec::new());
+    output::write_report(&mut ndjson, output::Format::Ndjson, &report).unwrap();
+
+    let lines: Vec<serde_json::Value> = String::from_utf8_lossy(ndjson.get_ref())
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[1]["type"], "repo");
+    assert_eq!(lines[1]["command"], "status");
+    assert_eq!(lines[1]["path"], "sub-a");
+    assert_eq!(lines[2]["path"], "sub-b");
+    assert_eq!(
+        lines[3],
+        serde_json::json!({
+            "type": "summary",
+            "command": "status",
+            "repos": 2,
+            "clean": true,
+        })
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b-groups.toml")))]
+fn status_group_shows_members_only(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        false,
+        1,
+        &["frontend".into()],
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(output_str.contains("(umbrella) on branch 'main'"));
+    assert!(!output_str.contains("'sub-a'"), "Output: {output_str}");
+    assert!(
+        output_str.contains("✓ 'sub-b' on branch 'main', all clean"),
+        "Output: {output_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn status_check_passes_on_clean_workspace(repo_sample: TestRepo) {
+    _run("git add .", &repo_sample.repo_path).unwrap();
+    _run("git commit -m baseline", &repo_sample.repo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        true,
+        1,
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(report.exit_code, 0);
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.ends_with("Check passed\n"),
+        "Output: {output_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn status_check_reports_wrong_branch(repo_sample: TestRepo) {
+    _run("git add .", &repo_sample.repo_path).unwrap();
+    _run("git commit -m baseline", &repo_sample.repo_path).unwrap();
+    _run(
+        "git checkout -b detour",
+        repo_sample.subrepo_path("sub-b").unwrap(),
+    )
+    .unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        true,
+        1,
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(report.exit_code, 4);
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.ends_with(
+            "Check failed:\n- 'sub-b': on branch 'detour' instead of configured head 'main'\n"
+        ),
+        "Output: {output_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn status_check_reports_unlocked_commits(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+
+    _run("git add .", &repo_sample.repo_path).unwrap();
+    _run("git commit -m baseline", &repo_sample.repo_path).unwrap();
+    _run("git commit --allow-empty -m 'not locked'", subrepo_path).unwrap();
+    fs::write(subrepo_path.join("WIP.md"), "work in progress").unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        true,
+        1,
+        &[],
+    )
+    .unwrap();
+
+    // Dirty (2) and gitlink mismatch (16)
+    assert_eq!(report.exit_code, 18);
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains("- 'umbrella': has uncommitted changes\n"),
+        "Output: {output_str}"
+    );
+    assert!(
+        output_str.contains("- 'sub-a': has uncommitted changes\n"),
+        "Output: {output_str}"
+    );
+    assert!(
+        output_str.contains("- 'sub-a': HEAD ")
+            && output_str.contains("locked in the umbrella"),
+        "Output: {output_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn status_check_reports_behind_remote(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+
+    _run("git add .", &repo_sample.repo_path).unwrap();
+    _run("git commit -m baseline", &repo_sample.repo_path).unwrap();
+
+    let remote_parent = repo_sample.repo_path.join("remotes");
+    fs::create_dir_all(&remote_parent).unwrap();
+    let remote_path = remote_parent.join("sub-a.git");
+
+    _run("git init --bare sub-a.git", &remote_parent).unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        subrepo_path,
+    )
+    .unwrap();
+    _run("git commit --allow-empty -m upstream", subrepo_path).unwrap();
+    _run("git push -u origin main", subrepo_path).unwrap();
+    _run("git reset --hard HEAD~1", subrepo_path).unwrap();
+    // Keep the bare remote from making the umbrella dirty
+    fs::write(repo_sample.repo_path.join(".git/info/exclude"), "remotes\n").unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::status(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        false,
+        true,
+        1,
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(report.exit_code, 8);
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str
+            .ends_with("Check failed:\n- 'sub-a': behind 'origin/main' by 1 commit\n"),
+        "Output: {output_str}"
+    );
+}
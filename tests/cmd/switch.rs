@@ -16,12 +16,13 @@ fn switch_all_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false, // create
-        true,  // all
-        None,  // branch
-        &[],   // repos
+        &cmd::SwitchOptions {
+            all: true,
+            ..Default::default()
+        },
     )
-    .unwrap();
+    .unwrap()
+    .config_updated;
 
     assert!(!config_changed);
     let repo_entry = actual_config
@@ -52,12 +53,13 @@ fn switch_all_skips_configured_repo(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,
-        true,
-        None,
-        &[],
+        &cmd::SwitchOptions {
+            all: true,
+            ..Default::default()
+        },
     )
-    .unwrap();
+    .unwrap()
+    .config_updated;
 
     assert!(!config_changed);
     let output_str = String::from_utf8_lossy(output.get_ref());
@@ -77,12 +79,14 @@ fn switch_all_includes_explicit_repo_overrides_skip(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,
-        true,
-        None,
-        &[std::path::PathBuf::from("sub-a")],
+        &cmd::SwitchOptions {
+            all: true,
+            repos: &[std::path::PathBuf::from("sub-a")],
+            ..Default::default()
+        },
     )
-    .unwrap();
+    .unwrap()
+    .config_updated;
 
     assert!(!config_changed);
     let output_str = String::from_utf8_lossy(output.get_ref());
@@ -103,12 +107,13 @@ fn switch_specific_repo(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,                                // create
-        false,                                // all
-        None,                                 // branch
-        &[std::path::PathBuf::from("sub-a")], // repos
+        &cmd::SwitchOptions {
+            repos: &[std::path::PathBuf::from("sub-a")],
+            ..Default::default()
+        },
     )
-    .unwrap();
+    .unwrap()
+    .config_updated;
 
     assert!(!config_changed);
     let repo_entry = actual_config
@@ -140,12 +145,14 @@ fn switch_with_create_option(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        true,                   // create
-        false,                  // all
-        Some("feature-branch"), // branch
-        &[],                    // repos
+        &cmd::SwitchOptions {
+            create: true,
+            branch: Some("feature-branch"),
+            ..Default::default()
+        },
     )
-    .unwrap();
+    .unwrap()
+    .config_updated;
 
     assert!(config_changed);
     let repo_entry = actual_config
@@ -176,12 +183,13 @@ fn switch_with_branch_option(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,           // create
-        false,           // all
-        Some("develop"), // branch
-        &[],             // repos
+        &cmd::SwitchOptions {
+            branch: Some("develop"),
+            ..Default::default()
+        },
     )
-    .unwrap();
+    .unwrap()
+    .config_updated;
 
     assert!(config_changed);
     let repo_entry = actual_config
@@ -211,12 +219,13 @@ fn switch_all_repos_moves_repo_when_cached_head_matches_target(repo_sample: Test
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false, // create
-        true,  // all
-        None,  // branch
-        &[],   // repos
+        &cmd::SwitchOptions {
+            all: true,
+            ..Default::default()
+        },
     )
-    .unwrap();
+    .unwrap()
+    .config_updated;
 
     assert!(config_changed);
     let repo_entry = actual_config
@@ -248,12 +257,10 @@ fn switch_with_no_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false, // create
-        false, // all
-        None,  // branch
-        &[],   // repos
+        &cmd::SwitchOptions::default(),
     )
-    .unwrap();
+    .unwrap()
+    .config_updated;
 
     assert!(!config_changed);
     // Check the output
@@ -271,15 +278,549 @@ fn switch_nonexistent_repo(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        false,                                      // create
-        false,                                      // all
-        None,                                       // branch
-        &[std::path::PathBuf::from("nonexistent")], // repos
+        &cmd::SwitchOptions {
+            repos: &[std::path::PathBuf::from("nonexistent")],
+            ..Default::default()
+        },
     )
-    .unwrap();
+    .unwrap()
+    .config_updated;
 
     assert!(!config_changed);
     // Check the output
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(output_str.contains("No repositories to switch"));
 }
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b-groups.toml")))]
+fn switch_group_only_switches_members(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let config_changed = cmd::switch(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::SwitchOptions {
+            create: true,
+            branch: Some("feature"),
+            groups: &["backend".into()],
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .config_updated;
+
+    assert!(config_changed);
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(output_str.contains("Switching 1 repositories to branch 'feature'"));
+    assert!(output_str.contains("- 'sub-a': created and switched to 'feature'"));
+    assert!(!output_str.contains("- 'sub-b':"));
+
+    let heads: Vec<_> = actual_config
+        .repos
+        .iter()
+        .map(|r| (r.path.to_string_lossy().to_string(), r.head.clone()))
+        .collect();
+    assert_eq!(
+        heads,
+        vec![
+            ("sub-a".to_string(), "feature".to_string()),
+            ("sub-b".to_string(), "main".to_string()),
+        ]
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn switch_dry_run_leaves_repos_untouched(repo_sample: TestRepo) {
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m 'Initial setup'", repo_sample.repo_path()).unwrap();
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git checkout -q other", subrepo_path).unwrap();
+    _run("git commit --allow-empty -m 'on other'", subrepo_path).unwrap();
+    _run("git checkout -q main", subrepo_path).unwrap();
+    let umbrella_head = _run("git rev-parse HEAD", repo_sample.repo_path()).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::switch(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::SwitchOptions {
+            all: true,
+            branch: Some("other"),
+            dry_run: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert!(!report.config_updated);
+    assert_eq!(actual_config.repos[0].head, "main");
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        "Switching 1 repositories to branch 'other'...\n\
+         - 'sub-a': would switch to 'other'\n\
+         Would commit to the umbrella repo:\n    \
+         Switch and lock submodule state\n\
+         \n    \
+         Switched to 'other':\n    \
+         - sub-a: other\n"
+    );
+
+    let branch = _run("git rev-parse --abbrev-ref HEAD", subrepo_path).unwrap();
+    assert_eq!(branch.trim(), "main");
+    assert_eq!(
+        _run("git rev-parse HEAD", repo_sample.repo_path()).unwrap(),
+        umbrella_head
+    );
+}
+
+/// Create `feature` in sub-a only, so that switching sub-b to it fails.
+fn create_feature_in_sub_a(repo_sample: &TestRepo) {
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m 'Initial setup'", repo_sample.repo_path()).unwrap();
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git checkout -q -b feature", subrepo_path).unwrap();
+    fs::write(subrepo_path.join("feature.txt"), "on feature\n").unwrap();
+    _run("git add feature.txt", subrepo_path).unwrap();
+    _run("git commit -m 'on feature'", subrepo_path).unwrap();
+    _run("git checkout -q main", subrepo_path).unwrap();
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn switch_atomic_rolls_back_on_failure(repo_sample: TestRepo) {
+    create_feature_in_sub_a(&repo_sample);
+    let umbrella_head = _run("git rev-parse HEAD", repo_sample.repo_path()).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let error = cmd::switch(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::SwitchOptions {
+            all: true,
+            branch: Some("feature"),
+            atomic: true,
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Cannot switch `sub-b` to 'feature'; no repositories were switched"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        "Switching 2 repositories to branch 'feature'...\n\
+         - 'sub-a': switched to 'feature'\n\
+         - 'sub-b': failed to switch to 'feature' - Branch 'feature' does not exist \
+         and --create not specified\n\
+         - 'sub-a': rolled back to 'main'\n"
+    );
+
+    let branch = _run(
+        "git rev-parse --abbrev-ref HEAD",
+        repo_sample.subrepo_path("sub-a").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(branch.trim(), "main");
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    assert!(!subrepo_path.join("feature.txt").exists());
+    assert_eq!(_run("git status --porcelain", subrepo_path).unwrap(), "");
+    assert!(actual_config.repos.iter().all(|repo| repo.head == "main"));
+    assert_eq!(
+        _run("git rev-parse HEAD", repo_sample.repo_path()).unwrap(),
+        umbrella_head
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn switch_atomic_refuses_autostash(repo_sample: TestRepo) {
+    create_feature_in_sub_a(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    fs::write(subrepo_path.join("notes.txt"), "notes\n").unwrap();
+    _run("git add notes.txt", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    actual_config.atomic_switch = true;
+
+    let error = cmd::switch(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::SwitchOptions {
+            all: true,
+            branch: Some("feature"),
+            autostash: true,
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "--autostash cannot be combined with an atomic switch"
+    );
+    assert_eq!(String::from_utf8_lossy(output.get_ref()), "");
+    assert_eq!(
+        _run("git branch --show-current", subrepo_path).unwrap(),
+        "main\n"
+    );
+    assert_eq!(
+        _run("git status --porcelain", subrepo_path).unwrap(),
+        "A  notes.txt\n"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn switch_atomic_from_config_deletes_created_branches(repo_sample: TestRepo) {
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m 'Initial setup'", repo_sample.repo_path()).unwrap();
+    // A stale ref lock keeps the branch from being created in sub-b
+    let subrepo_path = repo_sample.subrepo_path("sub-b").unwrap();
+    let git_dir = _run("git rev-parse --absolute-git-dir", subrepo_path).unwrap();
+    fs::write(
+        path::Path::new(git_dir.trim()).join("refs/heads/feature.lock"),
+        "",
+    )
+    .unwrap();
+
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    actual_config.atomic_switch = true;
+    let mut output = Cursor::new(Vec::new());
+
+    cmd::switch(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::SwitchOptions {
+            create: true,
+            all: true,
+            branch: Some("feature"),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains("- 'sub-a': rolled back to 'main'\n"),
+        "Output: {output_str}"
+    );
+    let sub_a_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let branch = _run("git rev-parse --abbrev-ref HEAD", sub_a_path).unwrap();
+    assert_eq!(branch.trim(), "main");
+    let branches = _run("git branch --list feature", sub_a_path).unwrap();
+    assert_eq!(branches, "");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn switch_create_from_local_base(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    fs::write(subrepo_path.join("BASE.md"), "base").unwrap();
+    _run("git add BASE.md", subrepo_path).unwrap();
+    _run("git commit -q -m 'Base work'", subrepo_path).unwrap();
+    let base = _run("git rev-parse main", subrepo_path).unwrap();
+    _run("git switch -q other", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::switch(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::SwitchOptions {
+            create: true,
+            from: Some("main"),
+            all: true,
+            branch: Some("feature"),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str
+            .contains("- 'sub-a': created and switched to 'feature' from 'main'\n"),
+        "Output: {output_str}"
+    );
+    assert_eq!(_run("git rev-parse HEAD", subrepo_path).unwrap(), base);
+    assert_eq!(
+        _run("git rev-parse --abbrev-ref HEAD", subrepo_path).unwrap(),
+        "feature\n"
+    );
+    assert!(subrepo_path.join("BASE.md").exists());
+    let upstream =
+        _run("git config --get branch.feature.remote", subrepo_path).unwrap();
+    assert_eq!(upstream, "");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn switch_create_from_refuses_existing_branch(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::switch(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::SwitchOptions {
+            create: true,
+            from: Some("main"),
+            all: true,
+            branch: Some("other"),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains(
+            "- 'sub-a': failed to switch to 'other' - Branch 'other' already exists, \
+             so it can't be created from 'main'\n"
+        ),
+        "Output: {output_str}"
+    );
+    assert_eq!(
+        _run("git rev-parse --abbrev-ref HEAD", subrepo_path).unwrap(),
+        "main\n"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn switch_create_from_remote_branch_fetches_and_tracks(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let remote_path = repo_sample.repo_path().join("sub-a.git");
+    _run("git init -q --bare sub-a.git", repo_sample.repo_path()).unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        subrepo_path,
+    )
+    .unwrap();
+    _run("git push -q origin main", subrepo_path).unwrap();
+    // The remote gets a commit that hasn't been fetched yet
+    _run("git commit -q --allow-empty -m 'Remote work'", subrepo_path).unwrap();
+    let remote_main = _run("git rev-parse HEAD", subrepo_path).unwrap();
+    _run("git push -q origin main", subrepo_path).unwrap();
+    _run("git reset -q --hard HEAD~1", subrepo_path).unwrap();
+    _run("git update-ref refs/remotes/origin/main HEAD", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::switch(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::SwitchOptions {
+            create: true,
+            from: Some("origin/main"),
+            all: true,
+            branch: Some("feature"),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains(
+            "- 'sub-a': created and switched to 'feature' from 'origin/main'\n"
+        ),
+        "Output: {output_str}"
+    );
+    assert_eq!(
+        _run("git rev-parse HEAD", subrepo_path).unwrap(),
+        remote_main
+    );
+    assert_eq!(
+        _run(
+            "git rev-parse --abbrev-ref feature@{upstream}",
+            subrepo_path
+        )
+        .unwrap(),
+        "origin/main\n"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn switch_fetches_and_tracks_remote_branch(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let remote_path = repo_sample.repo_path().join("sub-a.git");
+    _run("git init -q --bare sub-a.git", repo_sample.repo_path()).unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        subrepo_path,
+    )
+    .unwrap();
+    // A teammate pushes `feature` with a commit we haven't fetched
+    _run(
+        "git commit -q --allow-empty -m 'Teammate work'",
+        subrepo_path,
+    )
+    .unwrap();
+    let feature = _run("git rev-parse HEAD", subrepo_path).unwrap();
+    _run("git push -q origin HEAD:feature", subrepo_path).unwrap();
+    _run("git reset -q --hard HEAD~1", subrepo_path).unwrap();
+    _run(
+        "git update-ref -d refs/remotes/origin/feature",
+        subrepo_path,
+    )
+    .unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::switch(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::SwitchOptions {
+            fetch: true,
+            all: true,
+            branch: Some("feature"),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str
+            .contains("- 'sub-a': switched to 'feature' tracking 'origin/feature'\n"),
+        "Output: {output_str}"
+    );
+    let value = serde_json::to_value(&report).unwrap();
+    assert_eq!(value["repos"][0]["result"], "tracked");
+    assert_eq!(value["repos"][0]["upstream"], "origin/feature");
+    assert_eq!(_run("git rev-parse HEAD", subrepo_path).unwrap(), feature);
+    assert_eq!(
+        _run(
+            "git rev-parse --abbrev-ref feature@{upstream}",
+            subrepo_path
+        )
+        .unwrap(),
+        "origin/feature\n"
+    );
+}
+
+/// Commit a ten line file to `main` of `sub-a` and change its first line on a
+/// `feature` branch.
+fn diverge_file(repo_sample: &TestRepo) -> &PathBuf {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let lines: Vec<String> = (1..=10).map(|n| format!("line {n}\n")).collect();
+    fs::write(subrepo_path.join("file.txt"), lines.concat()).unwrap();
+    _run("git add file.txt", subrepo_path).unwrap();
+    _run("git commit -q -m 'Add file'", subrepo_path).unwrap();
+    _run("git switch -q -c feature", subrepo_path).unwrap();
+    fs::write(
+        subrepo_path.join("file.txt"),
+        lines.concat().replace("line 1\n", "feature line 1\n"),
+    )
+    .unwrap();
+    _run("git commit -q -am 'Change file'", subrepo_path).unwrap();
+    _run("git switch -q main", subrepo_path).unwrap();
+    subrepo_path
+}
+
+fn switch_to_feature(
+    repo_sample: &TestRepo,
+    autostash: bool,
+) -> (String, Result<serde_json::Value>) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::switch(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::SwitchOptions {
+            all: true,
+            branch: Some("feature"),
+            autostash,
+            ..Default::default()
+        },
+    )
+    .map(|report| serde_json::to_value(&report).unwrap());
+
+    (
+        String::from_utf8_lossy(output.get_ref()).into_owned(),
+        report,
+    )
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn switch_autostash_reapplies_changes(repo_sample: TestRepo) {
+    let subrepo_path = diverge_file(&repo_sample);
+    let contents = fs::read_to_string(subrepo_path.join("file.txt")).unwrap();
+    fs::write(
+        subrepo_path.join("file.txt"),
+        contents.replace("line 10\n", "local line 10\n"),
+    )
+    .unwrap();
+
+    let (output, _) = switch_to_feature(&repo_sample, false);
+    assert!(
+        output.contains("- 'sub-a': failed to switch to 'feature'"),
+        "Output: {output}"
+    );
+
+    let (output, report) = switch_to_feature(&repo_sample, true);
+
+    assert!(
+        output.contains(
+            "- 'sub-a': switched to 'feature'\n\
+             - 'sub-a': re-applied stashed changes\n"
+        ),
+        "Output: {output}"
+    );
+    assert_eq!(report.unwrap()["repos"][0]["autostash"], "restored");
+    let contents = fs::read_to_string(subrepo_path.join("file.txt")).unwrap();
+    assert!(contents.starts_with("feature line 1\n"));
+    assert!(contents.ends_with("local line 10\n"));
+    assert_eq!(_run("git stash list", subrepo_path).unwrap(), "");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn switch_autostash_keeps_conflicting_changes(repo_sample: TestRepo) {
+    let subrepo_path = diverge_file(&repo_sample);
+    let contents = fs::read_to_string(subrepo_path.join("file.txt")).unwrap();
+    fs::write(
+        subrepo_path.join("file.txt"),
+        contents.replace("line 1\n", "local line 1\n"),
+    )
+    .unwrap();
+
+    let (output, report) = switch_to_feature(&repo_sample, true);
+
+    assert!(
+        output.contains(
+            "- 'sub-a': switched to 'feature'\n\
+             - 'sub-a': stashed changes conflict in 'file.txt'; resolve them and run \
+             `wok stash drop`\n"
+        ),
+        "Output: {output}"
+    );
+    assert_eq!(report.unwrap()["repos"][0]["autostash"], "conflicts");
+    let contents = fs::read_to_string(subrepo_path.join("file.txt")).unwrap();
+    assert!(
+        contents.contains("feature line 1\n") && contents.contains("local line 1\n")
+    );
+    let stashes = _run("git stash list --format=%s", subrepo_path).unwrap();
+    assert_eq!(
+        stashes,
+        "On main: wok autostash before switch to 'feature'\n"
+    );
+}
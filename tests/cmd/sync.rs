@@ -0,0 +1,162 @@
+use std::{fs, io::Cursor};
+
+use rstest::*;
+
+use git_wok::{cmd, config};
+
+use super::*;
+
+/// Commit a new change in `sub-a` and lock it in the umbrella, then move the
+/// subrepo back to its previous commit. Returns the locked commit.
+fn lock_ahead_of_subrepo(repo_sample: &TestRepo) -> String {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m baseline", repo_sample.repo_path()).unwrap();
+
+    _run("git commit --allow-empty -m 'locked change'", subrepo_path).unwrap();
+    let locked = _run("git rev-parse HEAD", subrepo_path).unwrap();
+    _run("git add sub-a", repo_sample.repo_path()).unwrap();
+    _run("git commit -m lock", repo_sample.repo_path()).unwrap();
+
+    _run("git reset --hard HEAD~1", subrepo_path).unwrap();
+
+    locked.trim().to_owned()
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn sync_moves_branch_to_locked_commit(repo_sample: TestRepo) {
+    let locked = lock_ahead_of_subrepo(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::sync(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains(&format!("- 'sub-a': synced 'main' to {}", &locked[..8])),
+        "Output: {output_str}"
+    );
+
+    let head = _run("git rev-parse HEAD", subrepo_path).unwrap();
+    assert_eq!(head.trim(), locked);
+    let branch = _run("git rev-parse --abbrev-ref HEAD", subrepo_path).unwrap();
+    assert_eq!(branch.trim(), "main");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn sync_reports_up_to_date(repo_sample: TestRepo) {
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m baseline", repo_sample.repo_path()).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::sync(&mut actual_config, &repo_sample.repo(), &mut output, false).unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains("- 'sub-a': already at"),
+        "Output: {output_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn sync_refuses_dirty_subrepo(repo_sample: TestRepo) {
+    let locked = lock_ahead_of_subrepo(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    fs::write(subrepo_path.join("WIP.md"), "work in progress").unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let result = cmd::sync(&mut actual_config, &repo_sample.repo(), &mut output, false);
+    assert!(result.is_err());
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains("- 'sub-a': has uncommitted changes, refusing to sync"),
+        "Output: {output_str}"
+    );
+
+    let head = _run("git rev-parse HEAD", subrepo_path).unwrap();
+    assert_ne!(head.trim(), locked);
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn sync_refuses_to_drop_local_commits(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m baseline", repo_sample.repo_path()).unwrap();
+    _run("git commit --allow-empty -m 'unlocked work'", subrepo_path).unwrap();
+    let local = _run("git rev-parse HEAD", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let result = cmd::sync(&mut actual_config, &repo_sample.repo(), &mut output, false);
+    assert!(result.is_err());
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains("- 'sub-a': 'main' has commits not contained in"),
+        "Output: {output_str}"
+    );
+
+    let head = _run("git rev-parse HEAD", subrepo_path).unwrap();
+    assert_eq!(head, local);
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn sync_locked_restores_lock_file_state(repo_sample: TestRepo) {
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m baseline", repo_sample.repo_path()).unwrap();
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let locked = _run("git rev-parse HEAD", subrepo_path).unwrap();
+
+    let lock_path = config::Lock::path_for(&repo_sample.config_path());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    cmd::lock(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut Cursor::new(Vec::new()),
+        &[],
+        Some(&lock_path),
+        false,
+    )
+    .unwrap();
+
+    _run("git checkout -b feature", subrepo_path).unwrap();
+    _run("git commit --allow-empty -m 'feature work'", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    cmd::sync(&mut actual_config, &repo_sample.repo(), &mut output, true).unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains(&format!("- 'sub-a': synced 'main' to {}", &locked[..8])),
+        "Output: {output_str}"
+    );
+    let head = _run("git rev-parse HEAD", subrepo_path).unwrap();
+    assert_eq!(head, locked);
+    let branch = _run("git rev-parse --abbrev-ref HEAD", subrepo_path).unwrap();
+    assert_eq!(branch.trim(), "main");
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn sync_locked_requires_lock_file(repo_sample: TestRepo) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let error = cmd::sync(&mut actual_config, &repo_sample.repo(), &mut output, true)
+        .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "No lock file found next to the wok file; create one with `wok lock --file`"
+    );
+}
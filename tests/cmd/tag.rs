@@ -17,13 +17,12 @@ fn tag_list_all_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        None,  // tag_name
-        false, // sign
-        None,  // message
-        false, // push
-        true,  // all
-        true,  // include umbrella
-        &[],   // repos
+        None,
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -45,12 +44,12 @@ fn tag_list_skips_umbrella_when_disabled(repo_sample: TestRepo) {
         &repo_sample.repo(),
         &mut output,
         None,
-        false,
-        None,
-        false,
-        true,
-        false,
-        &[],
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            all: true,
+            include_umbrella: false,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -71,13 +70,12 @@ fn tag_list_specific_repo(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        None,                                 // tag_name
-        false,                                // sign
-        None,                                 // message
-        false,                                // push
-        false,                                // all
-        true,                                 // include umbrella
-        &[std::path::PathBuf::from("sub-a")], // repos
+        None,
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            repos: &[std::path::PathBuf::from("sub-a")],
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -99,13 +97,12 @@ fn tag_create_all_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        Some("v1.0.0"), // tag_name
-        false,          // sign
-        None,           // message
-        false,          // push
-        true,           // all
-        true,           // include umbrella
-        &[],            // repos
+        Some("v1.0.0"),
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -127,13 +124,12 @@ fn tag_create_specific_repo(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        Some("v1.0.0"),                       // tag_name
-        false,                                // sign
-        None,                                 // message
-        false,                                // push
-        false,                                // all
-        true,                                 // include umbrella
-        &[std::path::PathBuf::from("sub-a")], // repos
+        Some("v1.0.0"),
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            repos: &[std::path::PathBuf::from("sub-a")],
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -155,13 +151,13 @@ fn tag_create_with_sign(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        Some("v1.0.0"), // tag_name
-        true,           // sign
-        None,           // message
-        false,          // push
-        true,           // all
-        true,           // include umbrella
-        &[],            // repos
+        Some("v1.0.0"),
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            sign: true,
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -179,17 +175,17 @@ fn tag_create_with_push(repo_sample: TestRepo) {
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
 
     // Run the tag command with --create and --push
-    cmd::tag(
+    let report = cmd::tag(
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        Some("v1.0.0"), // tag_name
-        false,          // sign
-        None,           // message
-        true,           // push
-        true,           // all
-        true,           // include umbrella
-        &[],            // repos
+        Some("v1.0.0"),
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            push: true,
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -199,7 +195,13 @@ fn tag_create_with_push(repo_sample: TestRepo) {
     assert!(output_str.contains("- 'umbrella':"));
     assert!(output_str.contains("- 'sub-a': created tag 'v1.0.0'"));
     assert!(output_str.contains("Pushing tags to remotes"));
-    assert!(output_str.contains("Successfully processed 2 repositories"));
+    // Neither repo has a remote to push to
+    assert!(
+        output_str
+            .contains("- 'sub-a': failed to push tags - No remote 'origin' configured")
+    );
+    assert!(output_str.contains("Processed 2 repositories, 2 failed"));
+    assert_eq!(report.exit_code, 1);
 }
 
 #[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
@@ -228,12 +230,12 @@ fn tag_push_reports_when_up_to_date(repo_sample: TestRepo) {
         &repo_sample.repo(),
         &mut first_output,
         Some("v1.0.0"),
-        false,
-        None,
-        true,
-        true,
-        true,
-        &[],
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            push: true,
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -243,12 +245,11 @@ fn tag_push_reports_when_up_to_date(repo_sample: TestRepo) {
         &repo_sample.repo(),
         &mut output,
         None,
-        false,
-        None,
-        true,
-        false,
-        true,
-        &[],
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            push: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -257,7 +258,11 @@ fn tag_push_reports_when_up_to_date(repo_sample: TestRepo) {
     assert!(output_str.contains("- 'umbrella':"));
     assert!(output_str.contains("Pushing tags to remotes"));
     assert!(output_str.contains("- 'sub-a': no tags to push"));
-    assert!(output_str.contains("Successfully processed 2 repositories"));
+    // The umbrella has no remote to push to
+    assert!(output_str.contains("Processed 2 repositories, 1 failed"));
+
+    let (_, exit_code) = _wok("tag --push", repo_sample.repo_path());
+    assert_eq!(exit_code, 1);
 }
 
 #[rstest(repo_sample(vec![], Some("empty.toml")))]
@@ -270,13 +275,9 @@ fn tag_with_no_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        None,  // tag_name
-        false, // sign
-        None,  // message
-        false, // push
-        false, // all
-        true,  // include umbrella
-        &[],   // repos
+        None,
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions::default(),
     )
     .unwrap();
 
@@ -297,13 +298,12 @@ fn tag_nonexistent_repo(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        None,                                       // tag_name
-        false,                                      // sign
-        None,                                       // message
-        false,                                      // push
-        false,                                      // all
-        true,                                       // include umbrella
-        &[std::path::PathBuf::from("nonexistent")], // repos
+        None,
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            repos: &[std::path::PathBuf::from("nonexistent")],
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -324,12 +324,11 @@ fn tag_no_repos_without_umbrella(repo_sample: TestRepo) {
         &repo_sample.repo(),
         &mut output,
         None,
-        false,
-        None,
-        false,
-        false,
-        false,
-        &[],
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            include_umbrella: false,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -348,13 +347,12 @@ fn tag_multiple_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        Some("v1.0.0"), // tag_name
-        false,          // sign
-        None,           // message
-        false,          // push
-        true,           // all
-        true,           // include umbrella
-        &[],            // repos
+        Some("v1.0.0"),
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -377,16 +375,15 @@ fn tag_specific_multiple_repos(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        Some("v1.0.0"), // tag_name
-        false,          // sign
-        None,           // message
-        false,          // push
-        false,          // all
-        true,           // include umbrella
-        &[
-            std::path::PathBuf::from("sub-a"),
-            std::path::PathBuf::from("sub-b"),
-        ], // repos
+        Some("v1.0.0"),
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            repos: &[
+                std::path::PathBuf::from("sub-a"),
+                std::path::PathBuf::from("sub-b"),
+            ],
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -409,12 +406,11 @@ fn tag_all_skips_configured_repo(repo_sample: TestRepo) {
         &repo_sample.repo(),
         &mut output,
         Some("v1.0.0"),
-        false,
-        None,
-        false,
-        true,
-        true,
-        &[],
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -436,12 +432,8 @@ fn tag_default_skips_configured_repo(repo_sample: TestRepo) {
         &repo_sample.repo(),
         &mut output,
         None,
-        false,
-        None,
-        false,
-        false,
-        true,
-        &[],
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions::default(),
     )
     .unwrap();
 
@@ -463,12 +455,12 @@ fn tag_all_includes_explicit_repo_overrides_skip(repo_sample: TestRepo) {
         &repo_sample.repo(),
         &mut output,
         Some("v1.0.0"),
-        false,
-        None,
-        false,
-        true,
-        true,
-        &[std::path::PathBuf::from("sub-a")],
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            all: true,
+            repos: &[std::path::PathBuf::from("sub-a")],
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -490,13 +482,13 @@ fn tag_create_with_message(repo_sample: TestRepo) {
         &mut actual_config,
         &repo_sample.repo(),
         &mut output,
-        Some("v1.0.0"),                // tag_name
-        false,                         // sign
-        Some("Release version 1.0.0"), // message
-        false,                         // push
-        true,                          // all
-        true,                          // include umbrella
-        &[],                           // repos
+        Some("v1.0.0"),
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            message: Some("Release version 1.0.0"),
+            all: true,
+            ..Default::default()
+        },
     )
     .unwrap();
 
@@ -516,3 +508,324 @@ fn tag_create_with_message(repo_sample: TestRepo) {
     let tag = tag_obj.as_tag().unwrap();
     assert_eq!(tag.message().unwrap().trim(), "Release version 1.0.0");
 }
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn tag_dry_run_plans_tags_and_pushes(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let remote_path = repo_sample.repo_path().join("sub-a.git");
+    _run("git init --bare sub-a.git", repo_sample.repo_path()).unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        subrepo_path,
+    )
+    .unwrap();
+    // Listing the refs of an empty remote trips up libgit2
+    _run("git push -q origin other", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    let report = cmd::tag(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        Some("v1.0.0"),
+        &cmd::TagFilter::default(),
+        &cmd::TagOptions {
+            message: Some("Release"),
+            push: true,
+            include_umbrella: false,
+            repos: &["sub-a".into()],
+            dry_run: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains("- 'sub-a': would create tag 'v1.0.0' (annotated)"),
+        "Output: {output_str}"
+    );
+    assert!(
+        output_str
+            .contains("- 'sub-a': would push 'refs/tags/v1.0.0:refs/tags/v1.0.0'"),
+        "Output: {output_str}"
+    );
+    assert_eq!(report.summary["dry_run"], serde_json::json!(true));
+
+    assert_eq!(_run("git tag", subrepo_path).unwrap(), "");
+    assert_eq!(_run("git tag", &remote_path).unwrap(), "");
+}
+
+fn run_bump(
+    repo_sample: &TestRepo,
+    level: cmd::BumpLevel,
+    pre: Option<&str>,
+    include_umbrella: bool,
+) -> String {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    actual_config.tag_prefix = Some(String::from("v"));
+
+    cmd::tag_bump(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        level,
+        pre,
+        &cmd::TagOptions {
+            all: true,
+            include_umbrella,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    String::from_utf8_lossy(output.get_ref()).into_owned()
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn tag_bump_shows_versions_before_tagging(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_paths.get("sub-a").unwrap();
+    _run("git tag v1.2.3", subrepo_path).unwrap();
+    _run("git tag v1.10.0", subrepo_path).unwrap();
+    _run("git tag v2.0.0-beta.1", subrepo_path).unwrap();
+    _run("git tag 3.0.0", subrepo_path).unwrap();
+    _run("git tag release", subrepo_path).unwrap();
+
+    let output = run_bump(&repo_sample, cmd::BumpLevel::Patch, None, true);
+
+    assert_eq!(
+        output,
+        "Bumping patch versions in 2 repositories...\n\
+         repo      current        next\n\
+         umbrella  -              v0.0.1\n\
+         sub-a     v2.0.0-beta.1  v2.0.0\n\
+         - 'umbrella': created tag 'v0.0.1'\n\
+         - 'sub-a': created tag 'v2.0.0'\n\
+         Successfully processed 2 repositories\n"
+    );
+    assert!(
+        _run("git tag", &repo_sample.repo_path)
+            .unwrap()
+            .contains("v0.0.1\n")
+    );
+    assert!(_run("git tag", subrepo_path).unwrap().contains("v2.0.0\n"));
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn tag_bump_counts_up_prereleases(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_paths.get("sub-a").unwrap();
+    _run("git tag v1.2.3", subrepo_path).unwrap();
+
+    let output = run_bump(&repo_sample, cmd::BumpLevel::Minor, Some("rc"), false);
+    assert!(
+        output.contains("sub-a  v1.2.3   v1.3.0-rc.1\n"),
+        "Output: {output}"
+    );
+
+    let output = run_bump(&repo_sample, cmd::BumpLevel::Minor, Some("rc"), false);
+    assert!(
+        output.contains("sub-a  v1.3.0-rc.1  v1.3.0-rc.2\n"),
+        "Output: {output}"
+    );
+
+    let output = run_bump(&repo_sample, cmd::BumpLevel::Minor, None, false);
+    assert!(
+        output.contains("sub-a  v1.3.0-rc.2  v1.3.0\n"),
+        "Output: {output}"
+    );
+
+    let output = run_bump(&repo_sample, cmd::BumpLevel::Major, Some("rc"), false);
+    assert!(
+        output.contains("sub-a  v1.3.0   v2.0.0-rc.1\n"),
+        "Output: {output}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn tag_bump_refuses_lower_prerelease(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_paths.get("sub-a").unwrap();
+    _run("git tag v1.2.4-rc.2", subrepo_path).unwrap();
+
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    actual_config.tag_prefix = Some(String::from("v"));
+    let error = cmd::tag_bump(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut Cursor::new(Vec::new()),
+        cmd::BumpLevel::Patch,
+        Some("alpha"),
+        &cmd::TagOptions {
+            all: true,
+            include_umbrella: false,
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        format!("{:#}", error),
+        "Cannot bump the version of 'sub-a': Version 1.2.4-alpha.1 would not come \
+         after 1.2.4-rc.2; use a later prerelease identifier than 'alpha' or release \
+         1.2.4 first"
+    );
+    assert_eq!(_run("git tag", subrepo_path).unwrap(), "v1.2.4-rc.2\n");
+}
+
+fn run_list(repo_sample: &TestRepo, filter: &cmd::TagFilter) -> String {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    actual_config.tag_prefix = Some(String::from("v"));
+
+    cmd::tag(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        None,
+        filter,
+        &cmd::TagOptions {
+            include_umbrella: false,
+            repos: &["sub-a".into()],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    String::from_utf8_lossy(output.get_ref()).into_owned()
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn tag_list_matches_glob_and_sorts_by_version(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    for tag in ["v1.10.0", "v1.2.0", "v1.9.0-rc.1", "v2.0.0", "nightly"] {
+        _run(&format!("git tag {tag}"), subrepo_path).unwrap();
+    }
+
+    let output = run_list(
+        &repo_sample,
+        &cmd::TagFilter {
+            pattern: Some(String::from("v1.*")),
+            sort: cmd::TagSort::Version,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(
+        output,
+        "Listing tags in 1 repositories...\n\
+         - 'sub-a':\n    \
+         - 'v1.2.0'\n    \
+         - 'v1.9.0-rc.1'\n    \
+         - 'v1.10.0'\n\
+         Successfully processed 1 repositories\n"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn tag_list_points_at_revision(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git tag v1.0.0", subrepo_path).unwrap();
+    _run("git commit --allow-empty -m next", subrepo_path).unwrap();
+    _run("git tag -a v1.1.0 -m release", subrepo_path).unwrap();
+    _run("git tag latest", subrepo_path).unwrap();
+
+    let output = run_list(
+        &repo_sample,
+        &cmd::TagFilter {
+            points_at: Some(String::from("HEAD")),
+            ..Default::default()
+        },
+    );
+
+    assert!(
+        output.contains("- 'sub-a':\n    - 'latest'\n    - 'v1.1.0'\nSuccessfully"),
+        "Output: {output}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn tag_delete_removes_tag_here_and_on_remote(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let remote_path = repo_sample.repo_path().join("sub-a.git");
+    _run("git init --bare sub-a.git", repo_sample.repo_path()).unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        subrepo_path,
+    )
+    .unwrap();
+    _run("git tag v1.0.0", subrepo_path).unwrap();
+    _run("git push -q origin other v1.0.0", subrepo_path).unwrap();
+
+    let delete = |push: bool, dry_run: bool| {
+        let mut output = Cursor::new(Vec::new());
+        let mut actual_config =
+            config::Config::load(&repo_sample.config_path()).unwrap();
+        cmd::tag_delete(
+            &mut actual_config,
+            &repo_sample.repo(),
+            &mut output,
+            "v1.0.0",
+            &cmd::TagOptions {
+                push,
+                include_umbrella: false,
+                repos: &["sub-a".into()],
+                dry_run,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        String::from_utf8_lossy(output.get_ref()).into_owned()
+    };
+
+    let output = delete(true, true);
+    assert!(
+        output.contains("- 'sub-a': would delete tag 'v1.0.0' here and on 'origin'\n"),
+        "Output: {output}"
+    );
+    assert_eq!(_run("git tag", subrepo_path).unwrap(), "v1.0.0\n");
+
+    let output = delete(true, false);
+    assert_eq!(
+        output,
+        "Deleting tag 'v1.0.0' in 1 repositories...\n\
+         - 'sub-a': deleted tag 'v1.0.0' here and on 'origin'\n\
+         Successfully processed 1 repositories\n"
+    );
+    assert_eq!(_run("git tag", subrepo_path).unwrap(), "");
+    assert_eq!(_run("git tag", &remote_path).unwrap(), "");
+
+    let output = delete(false, false);
+    assert!(
+        output.contains("- 'sub-a': tag 'v1.0.0' not found\n"),
+        "Output: {output}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn tag_delete_push_without_remote_deletes_local_tag(repo_sample: TestRepo) {
+    _run("git tag v1.0.0", repo_sample.repo_path()).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let report = cmd::tag_delete(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        "v1.0.0",
+        &cmd::TagOptions {
+            push: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output.contains("- 'umbrella': deleted tag 'v1.0.0' (no remote configured)\n"),
+        "Output: {output}"
+    );
+    assert_eq!(_run("git tag", repo_sample.repo_path()).unwrap(), "");
+    assert_eq!(report.exit_code, 0);
+}
@@ -0,0 +1,322 @@
+use std::io::Cursor;
+
+use pretty_assertions::assert_eq;
+use rstest::*;
+
+use git_wok::{cmd, config, journal};
+
+use super::*;
+
+/// Run `switch --all --branch other` the way the binary does, recording a
+/// journal entry first.
+fn switch_to_other(repo_sample: &TestRepo) -> u64 {
+    let umbrella = repo_sample.repo();
+    let journal = journal::Journal::open(&umbrella);
+    let id = journal
+        .record(
+            &umbrella,
+            &repo_sample.config_path(),
+            "switch --all --branch other",
+        )
+        .unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let report = cmd::switch(
+        &mut actual_config,
+        &umbrella,
+        &mut output,
+        &cmd::SwitchOptions {
+            all: true,
+            branch: Some("other"),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    if report.config_updated {
+        actual_config.save(&repo_sample.config_path()).unwrap();
+    }
+
+    journal
+        .forget_if_unchanged(id, &umbrella, &repo_sample.config_path())
+        .unwrap();
+    id
+}
+
+fn setup(repo_sample: &TestRepo) {
+    _run("git add .", repo_sample.repo_path()).unwrap();
+    _run("git commit -m 'Initial setup'", repo_sample.repo_path()).unwrap();
+    for name in ["sub-a", "sub-b"] {
+        let subrepo_path = repo_sample.subrepo_path(name).unwrap();
+        _run("git checkout -q other", subrepo_path).unwrap();
+        _run("git commit --allow-empty -m 'on other'", subrepo_path).unwrap();
+        _run("git checkout -q main", subrepo_path).unwrap();
+    }
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn undo_restores_switched_workspace(repo_sample: TestRepo) {
+    setup(&repo_sample);
+    let config_before = fs::read_to_string(repo_sample.config_path()).unwrap();
+    let umbrella_head = _run("git rev-parse HEAD", repo_sample.repo_path()).unwrap();
+    let commit_a = _run(
+        "git rev-parse HEAD",
+        repo_sample.subrepo_path("sub-a").unwrap(),
+    )
+    .unwrap();
+
+    let id = switch_to_other(&repo_sample);
+    assert_ne!(
+        fs::read_to_string(repo_sample.config_path()).unwrap(),
+        config_before
+    );
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::history(&repo_sample.repo(), &mut output).unwrap();
+    let history = String::from_utf8_lossy(output.get_ref()).into_owned();
+    assert!(
+        history.starts_with(&format!("- {}: `switch --all --branch other` at ", id)),
+        "History: {history}"
+    );
+    assert!(history.ends_with(" (2 repos)\n"), "History: {history}");
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::undo(&repo_sample.repo(), &repo_sample.config_path(), &mut output).unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.starts_with("Undoing `switch --all --branch other` recorded at "),
+        "Output: {output_str}"
+    );
+    assert!(
+        output_str.contains(&format!(
+            "- 'sub-a': restored 'main' at {}\n",
+            &commit_a[..8]
+        )),
+        "Output: {output_str}"
+    );
+    assert!(
+        output_str.ends_with("Undid `switch --all --branch other`\n"),
+        "Output: {output_str}"
+    );
+
+    for name in ["sub-a", "sub-b"] {
+        let subrepo_path = repo_sample.subrepo_path(name).unwrap();
+        let branch = _run("git rev-parse --abbrev-ref HEAD", subrepo_path).unwrap();
+        assert_eq!(branch.trim(), "main");
+    }
+    assert_eq!(
+        _run("git rev-parse HEAD", repo_sample.repo_path()).unwrap(),
+        umbrella_head
+    );
+    assert_eq!(
+        fs::read_to_string(repo_sample.config_path()).unwrap(),
+        config_before
+    );
+    let status = _run("git status --porcelain", repo_sample.repo_path()).unwrap();
+    assert_eq!(status, "");
+
+    // The entry is used up
+    let mut output = Cursor::new(Vec::new());
+    cmd::history(&repo_sample.repo(), &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        "No operations recorded\n"
+    );
+    let error = cmd::undo(&repo_sample.repo(), &repo_sample.config_path(), &mut output)
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Nothing to undo");
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn undo_restores_deleted_branch(repo_sample: TestRepo) {
+    setup(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    _run("git branch topic", subrepo_path).unwrap();
+    let topic_commit = _run("git rev-parse topic", subrepo_path).unwrap();
+
+    let umbrella = repo_sample.repo();
+    let journal = journal::Journal::open(&umbrella);
+    let id = journal
+        .record(
+            &umbrella,
+            &repo_sample.config_path(),
+            "branch --delete topic",
+        )
+        .unwrap();
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    cmd::branch(
+        &mut actual_config,
+        &umbrella,
+        &mut Cursor::new(Vec::new()),
+        &cmd::BranchAction::Delete {
+            branch: String::from("topic"),
+            remote: false,
+        },
+        &[],
+        &[],
+    )
+    .unwrap();
+    journal
+        .forget_if_unchanged(id, &umbrella, &repo_sample.config_path())
+        .unwrap();
+    assert_eq!(_run("git branch --list topic", subrepo_path).unwrap(), "");
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::undo(&repo_sample.repo(), &repo_sample.config_path(), &mut output).unwrap();
+    let output = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output.contains("- 'sub-a': restored branch 'topic'\n"),
+        "Output: {output}"
+    );
+    assert_eq!(
+        _run("git rev-parse topic", subrepo_path).unwrap(),
+        topic_commit
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn undo_reverts_branch_rename(repo_sample: TestRepo) {
+    setup(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    let main_commit = _run("git rev-parse main", subrepo_path).unwrap();
+
+    let umbrella = repo_sample.repo();
+    let journal = journal::Journal::open(&umbrella);
+    let id = journal
+        .record(
+            &umbrella,
+            &repo_sample.config_path(),
+            "branch -m main trunk",
+        )
+        .unwrap();
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    cmd::branch(
+        &mut actual_config,
+        &umbrella,
+        &mut Cursor::new(Vec::new()),
+        &cmd::BranchAction::Rename {
+            from: String::from("main"),
+            to: String::from("trunk"),
+        },
+        &[],
+        &[],
+    )
+    .unwrap();
+    journal
+        .forget_if_unchanged(id, &umbrella, &repo_sample.config_path())
+        .unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::undo(&repo_sample.repo(), &repo_sample.config_path(), &mut output).unwrap();
+    let output = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output.contains(
+            "- 'sub-a': restored branch 'main'\n\
+             - 'sub-a': restored 'main' at "
+        ),
+        "Output: {output}"
+    );
+    assert!(
+        output.contains("- 'sub-a': deleted branch 'trunk'\n"),
+        "Output: {output}"
+    );
+    assert_eq!(
+        _run("git branch --show-current", subrepo_path).unwrap(),
+        "main\n"
+    );
+    assert_eq!(
+        _run("git rev-parse main", subrepo_path).unwrap(),
+        main_commit
+    );
+    assert_eq!(_run("git branch --list trunk", subrepo_path).unwrap(), "");
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn undo_restores_dropped_stash(repo_sample: TestRepo) {
+    setup(&repo_sample);
+    let subrepo_path = repo_sample.subrepo_path("sub-a").unwrap();
+    fs::write(subrepo_path.join("file.txt"), "line\n").unwrap();
+    _run("git add file.txt", subrepo_path).unwrap();
+    _run("git commit -q -m 'Add file'", subrepo_path).unwrap();
+    fs::write(subrepo_path.join("file.txt"), "local line\n").unwrap();
+    let subrepo = git_wok::repo::Repo::new(subrepo_path, None).unwrap();
+    subrepo.autostash("update").unwrap();
+    let stashes = _run("git stash list", subrepo_path).unwrap();
+
+    let umbrella = repo_sample.repo();
+    let journal = journal::Journal::open(&umbrella);
+    let id = journal
+        .record(&umbrella, &repo_sample.config_path(), "stash drop")
+        .unwrap();
+    cmd::stash(
+        &umbrella,
+        &mut Cursor::new(Vec::new()),
+        cmd::StashAction::Drop,
+    )
+    .unwrap();
+    journal
+        .forget_if_unchanged(id, &umbrella, &repo_sample.config_path())
+        .unwrap();
+    assert_eq!(_run("git stash list", subrepo_path).unwrap(), "");
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::undo(&repo_sample.repo(), &repo_sample.config_path(), &mut output).unwrap();
+    let output = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output.contains(
+            "- 'sub-a': restored stash 'On main: wok autostash before update'\n"
+        ),
+        "Output: {output}"
+    );
+    assert_eq!(_run("git stash list", subrepo_path).unwrap(), stashes);
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn undo_refuses_dirty_subrepo(repo_sample: TestRepo) {
+    setup(&repo_sample);
+    switch_to_other(&repo_sample);
+
+    let subrepo_path = repo_sample.subrepo_path("sub-b").unwrap();
+    fs::write(subrepo_path.join("README"), "wip").unwrap();
+    _run("git add README", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let error = cmd::undo(&repo_sample.repo(), &repo_sample.config_path(), &mut output)
+        .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Cannot undo `switch --all --branch other`: subrepo `sub-b` has uncommitted \
+         changes"
+    );
+    // Nothing was restored
+    let branch = _run(
+        "git rev-parse --abbrev-ref HEAD",
+        repo_sample.subrepo_path("sub-a").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(branch.trim(), "other");
+    assert_eq!(
+        journal::Journal::open(&repo_sample.repo())
+            .entries()
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn unchanged_workspace_is_not_journaled(repo_sample: TestRepo) {
+    let umbrella = repo_sample.repo();
+    let journal = journal::Journal::open(&umbrella);
+    let id = journal
+        .record(&umbrella, &repo_sample.config_path(), "lock")
+        .unwrap();
+
+    journal
+        .forget_if_unchanged(id, &umbrella, &repo_sample.config_path())
+        .unwrap();
+
+    assert!(journal.entries().unwrap().is_empty());
+}
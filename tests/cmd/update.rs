@@ -50,7 +50,13 @@ fn update_pulls_tracking_branch(repo_sample: TestRepo) {
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
     let umbrella = repo_sample.repo();
 
-    cmd::update(&mut actual_config, &umbrella, &mut output, false, true).unwrap();
+    cmd::update(
+        &mut actual_config,
+        &umbrella,
+        &mut output,
+        &cmd::UpdateOptions::default(),
+    )
+    .unwrap();
 
     let local_after = _run("git rev-parse HEAD", subrepo_path).unwrap();
     let remote_tip = _run("git rev-parse origin/main", subrepo_path).unwrap();
@@ -100,7 +106,13 @@ fn update_submodules(repo_sample: TestRepo) {
         .id();
 
     // Run the update command
-    cmd::update(&mut actual_config, &umbrella, &mut output, false, true).unwrap();
+    cmd::update(
+        &mut actual_config,
+        &umbrella,
+        &mut output,
+        &cmd::UpdateOptions::default(),
+    )
+    .unwrap();
 
     // Check the output
     let output_str = String::from_utf8_lossy(output.get_ref());
@@ -150,7 +162,13 @@ fn update_with_no_submodules(repo_sample: TestRepo) {
         .id();
 
     // Run the update command with no submodules
-    cmd::update(&mut actual_config, &umbrella, &mut output, false, true).unwrap();
+    cmd::update(
+        &mut actual_config,
+        &umbrella,
+        &mut output,
+        &cmd::UpdateOptions::default(),
+    )
+    .unwrap();
 
     // Check the output
     let output_str = String::from_utf8_lossy(output.get_ref());
@@ -187,7 +205,13 @@ fn update_skips_configured_repo(repo_sample: TestRepo) {
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
     let umbrella = repo_sample.repo();
 
-    cmd::update(&mut actual_config, &umbrella, &mut output, false, true).unwrap();
+    cmd::update(
+        &mut actual_config,
+        &umbrella,
+        &mut output,
+        &cmd::UpdateOptions::default(),
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(
@@ -217,7 +241,16 @@ fn update_with_no_commit_flag_skips_commit(repo_sample: TestRepo) {
         .unwrap()
         .id();
 
-    cmd::update(&mut actual_config, &umbrella, &mut output, true, true).unwrap();
+    cmd::update(
+        &mut actual_config,
+        &umbrella,
+        &mut output,
+        &cmd::UpdateOptions {
+            no_commit: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(
@@ -295,7 +328,13 @@ fn update_respects_rebase_config(repo_sample: TestRepo) {
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
     let umbrella = repo_sample.repo();
 
-    cmd::update(&mut actual_config, &umbrella, &mut output, false, true).unwrap();
+    cmd::update(
+        &mut actual_config,
+        &umbrella,
+        &mut output,
+        &cmd::UpdateOptions::default(),
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     // Should say "rebased" not "merged"
@@ -366,7 +405,13 @@ fn update_uses_merge_by_default(repo_sample: TestRepo) {
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
     let umbrella = repo_sample.repo();
 
-    cmd::update(&mut actual_config, &umbrella, &mut output, false, true).unwrap();
+    cmd::update(
+        &mut actual_config,
+        &umbrella,
+        &mut output,
+        &cmd::UpdateOptions::default(),
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     // Should say "merged" when pull.rebase is false
@@ -374,6 +419,7 @@ fn update_uses_merge_by_default(repo_sample: TestRepo) {
         output_str.contains("- 'sub-a': merged 'main'"),
         "Output: {output_str}"
     );
+    assert!(subrepo_path.join("UPSTREAM.md").exists());
     assert!(output_str.contains("- 'umbrella':"), "Output: {output_str}");
     assert!(
         output_str.contains("Updated submodule state committed"),
@@ -387,7 +433,16 @@ fn update_skips_umbrella_when_disabled(repo_sample: TestRepo) {
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
     let umbrella = repo_sample.repo();
 
-    cmd::update(&mut actual_config, &umbrella, &mut output, false, false).unwrap();
+    cmd::update(
+        &mut actual_config,
+        &umbrella,
+        &mut output,
+        &cmd::UpdateOptions {
+            include_umbrella: false,
+            ..Default::default()
+        },
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(output_str.contains("- 'sub-a':"), "Output: {output_str}");
@@ -403,7 +458,16 @@ fn update_without_umbrella_when_no_submodules(repo_sample: TestRepo) {
     let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
     let umbrella = repo_sample.repo();
 
-    cmd::update(&mut actual_config, &umbrella, &mut output, false, false).unwrap();
+    cmd::update(
+        &mut actual_config,
+        &umbrella,
+        &mut output,
+        &cmd::UpdateOptions {
+            include_umbrella: false,
+            ..Default::default()
+        },
+    )
+    .unwrap();
 
     let output_str = String::from_utf8_lossy(output.get_ref());
     assert!(
@@ -415,3 +479,550 @@ fn update_without_umbrella_when_no_submodules(repo_sample: TestRepo) {
         "Output: {output_str}"
     );
 }
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn update_dry_run_plans_fast_forward(repo_sample: TestRepo) {
+    let subrepo_path = repo_sample.subrepo_paths.get("sub-a").unwrap();
+
+    _run("git add .", &repo_sample.repo_path).unwrap();
+    _run("git commit -m baseline", &repo_sample.repo_path).unwrap();
+
+    let remote_parent = repo_sample.repo_path.join("remotes");
+    fs::create_dir_all(&remote_parent).unwrap();
+    let remote_path = remote_parent.join("sub-a.git");
+
+    _run("git init --bare sub-a.git", &remote_parent).unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        subrepo_path,
+    )
+    .unwrap();
+    _run("git push -u origin main", subrepo_path).unwrap();
+
+    let contributor_path = remote_parent.join("contributor");
+    _run(
+        &format!(
+            "git clone {} {}",
+            remote_path.display(),
+            contributor_path.display()
+        ),
+        &remote_parent,
+    )
+    .unwrap();
+    _run("git config user.email 'test@localhost'", &contributor_path).unwrap();
+    _run("git config user.name 'Test User'", &contributor_path).unwrap();
+    _run("git commit --allow-empty -m upstream", &contributor_path).unwrap();
+    _run("git push", &contributor_path).unwrap();
+    let upstream_tip = _run("git rev-parse HEAD", &contributor_path).unwrap();
+    let short_tip = &upstream_tip[..8];
+
+    let local_before = _run("git rev-parse HEAD", subrepo_path).unwrap();
+    let umbrella_head_before =
+        _run("git rev-parse HEAD", &repo_sample.repo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+
+    cmd::update(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::UpdateOptions {
+            include_umbrella: false,
+            dry_run: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        format!(
+            "Planning repository updates...\n\
+             - 'sub-a': would fast-forward 'main' to {short_tip}\n\
+             Would commit to the umbrella repo:\n    \
+             Update submodules to latest\n\
+             \n    \
+             Updated submodules:\n    \
+             - sub-a: main to {short_tip}\n"
+        )
+    );
+
+    // The remote was fetched, but nothing else changed
+    let remote_tip = _run("git rev-parse origin/main", subrepo_path).unwrap();
+    assert_eq!(remote_tip, upstream_tip);
+    assert_eq!(
+        _run("git rev-parse HEAD", subrepo_path).unwrap(),
+        local_before
+    );
+    assert_eq!(
+        _run("git rev-parse HEAD", &repo_sample.repo_path).unwrap(),
+        umbrella_head_before
+    );
+    let status = _run("git status --porcelain", &repo_sample.repo_path).unwrap();
+    assert_eq!(status, "?? remotes/\n");
+}
+
+/// Give the `sub-a` branch a local commit adding `local_file` and its remote an
+/// upstream commit adding `upstream_file`, with different contents.
+fn diverge_from_remote<'a>(
+    repo_sample: &'a TestRepo,
+    local_file: &str,
+    upstream_file: &str,
+) -> &'a PathBuf {
+    let subrepo_path = repo_sample.subrepo_paths.get("sub-a").unwrap();
+    _run("git add .", &repo_sample.repo_path).unwrap();
+    _run("git commit -m baseline", &repo_sample.repo_path).unwrap();
+
+    let remote_parent = repo_sample.repo_path.join("remotes");
+    fs::create_dir_all(&remote_parent).unwrap();
+    let remote_path = remote_parent.join("sub-a.git");
+    _run("git init --bare sub-a.git", &remote_parent).unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        subrepo_path,
+    )
+    .unwrap();
+    _run("git push -u origin main", subrepo_path).unwrap();
+
+    fs::write(subrepo_path.join(local_file), "local change\n").unwrap();
+    _run(&format!("git add {local_file}"), subrepo_path).unwrap();
+    _run("git commit -m 'local commit'", subrepo_path).unwrap();
+
+    let contributor_path = remote_parent.join("contributor");
+    _run(
+        &format!(
+            "git clone {} {}",
+            remote_path.display(),
+            contributor_path.display()
+        ),
+        &remote_parent,
+    )
+    .unwrap();
+    _run("git config user.email 'test@localhost'", &contributor_path).unwrap();
+    _run("git config user.name 'Test User'", &contributor_path).unwrap();
+    fs::write(contributor_path.join(upstream_file), "upstream change\n").unwrap();
+    _run(&format!("git add {upstream_file}"), &contributor_path).unwrap();
+    _run("git commit -m upstream", &contributor_path).unwrap();
+    _run("git push", &contributor_path).unwrap();
+
+    subrepo_path
+}
+
+fn run_update(repo_sample: &TestRepo) -> (String, Result<serde_json::Value>) {
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let report = cmd::update(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::UpdateOptions {
+            include_umbrella: false,
+            ..Default::default()
+        },
+    )
+    .map(|report| serde_json::to_value(&report).unwrap());
+    (
+        String::from_utf8_lossy(output.get_ref()).into_owned(),
+        report,
+    )
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn update_ff_only_leaves_diverged_branch(repo_sample: TestRepo) {
+    let subrepo_path = diverge_from_remote(&repo_sample, "LOCAL.md", "UPSTREAM.md");
+    let local_before = _run("git rev-parse HEAD", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    let report = cmd::update(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::UpdateOptions {
+            include_umbrella: false,
+            pull: Some(config::PullStrategy::FastForwardOnly),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains(
+            "- 'sub-a': 'main' has diverged from its remote branch, not \
+             fast-forwarding"
+        ),
+        "Output: {output_str}"
+    );
+    let report = serde_json::to_value(&report).unwrap();
+    assert_eq!(report["repos"][0]["result"], "diverged");
+    assert_eq!(
+        _run("git rev-parse HEAD", subrepo_path).unwrap(),
+        local_before
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn update_pull_from_config_overrides_git_config(repo_sample: TestRepo) {
+    let subrepo_path = diverge_from_remote(&repo_sample, "LOCAL.md", "UPSTREAM.md");
+    _run("git config pull.rebase false", subrepo_path).unwrap();
+
+    let mut config_file = config::Config::read(&repo_sample.config_path()).unwrap();
+    config_file.push_str("pull = \"rebase\"\n");
+    fs::write(repo_sample.config_path(), config_file).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    assert_eq!(
+        actual_config.repos[0].pull,
+        Some(config::PullStrategy::Rebase)
+    );
+    cmd::update(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::UpdateOptions {
+            include_umbrella: false,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains("- 'sub-a': rebased 'main'"),
+        "Output: {output_str}"
+    );
+
+    // The command line overrides the wok file
+    let mut output = Cursor::new(Vec::new());
+    cmd::update(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::UpdateOptions {
+            include_umbrella: false,
+            pull: Some(config::PullStrategy::Merge),
+            dry_run: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let output_str = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output_str.contains("- 'sub-a': already up to date on 'main'"),
+        "Output: {output_str}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn update_lists_conflicts_and_continues_merge(repo_sample: TestRepo) {
+    let subrepo_path = diverge_from_remote(&repo_sample, "FILE.md", "FILE.md");
+    _run("git config pull.rebase false", subrepo_path).unwrap();
+
+    let (output, report) = run_update(&repo_sample);
+    assert!(
+        output.contains("manual resolution required\n    - 'FILE.md'\n"),
+        "Output: {output}"
+    );
+    assert!(
+        output.contains("run `wok update --continue`"),
+        "Output: {output}"
+    );
+    assert_eq!(
+        report.unwrap()["repos"][0]["conflicts"],
+        serde_json::json!(["FILE.md"])
+    );
+
+    // A new update can't start until this one is done
+    let (_, report) = run_update(&repo_sample);
+    assert!(report.is_err());
+
+    fs::write(subrepo_path.join("FILE.md"), "resolved\n").unwrap();
+    _run("git add FILE.md", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::update_continue(&repo_sample.repo(), &mut output).unwrap();
+    let output = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output.contains("- 'sub-a': merged 'main' to "),
+        "Output: {output}"
+    );
+    assert!(
+        output.contains("Updated submodule state committed"),
+        "Output: {output}"
+    );
+
+    let parents = _run("git log -1 --format=%P", subrepo_path).unwrap();
+    assert_eq!(parents.split_whitespace().count(), 2);
+    assert_eq!(_run("git status --porcelain", subrepo_path).unwrap(), "");
+    let message = _run("git log -1 --format=%B", &repo_sample.repo_path).unwrap();
+    assert!(message.contains("- sub-a: main to "), "Message: {message}");
+
+    let mut output = Cursor::new(Vec::new());
+    assert!(cmd::update_continue(&repo_sample.repo(), &mut output).is_err());
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn update_continues_rebase(repo_sample: TestRepo) {
+    let subrepo_path = diverge_from_remote(&repo_sample, "FILE.md", "FILE.md");
+    _run("git config pull.rebase true", subrepo_path).unwrap();
+
+    let (output, _) = run_update(&repo_sample);
+    assert!(output.contains("    - 'FILE.md'\n"), "Output: {output}");
+
+    fs::write(subrepo_path.join("FILE.md"), "resolved\n").unwrap();
+    _run("git add FILE.md", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::update_continue(&repo_sample.repo(), &mut output).unwrap();
+    let output = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output.contains("- 'sub-a': rebased 'main' to "),
+        "Output: {output}"
+    );
+
+    let log = _run("git log --format=%s", subrepo_path).unwrap();
+    assert!(log.starts_with("local commit\nupstream\n"), "Log: {log}");
+    assert_eq!(
+        _run("git branch --show-current", subrepo_path).unwrap(),
+        "main\n"
+    );
+    assert_eq!(
+        fs::read_to_string(subrepo_path.join("FILE.md")).unwrap(),
+        "resolved\n"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn update_abort_restores_repos(repo_sample: TestRepo) {
+    let subrepo_path = diverge_from_remote(&repo_sample, "FILE.md", "FILE.md");
+    _run("git config pull.rebase true", subrepo_path).unwrap();
+    let local_before = _run("git rev-parse HEAD", subrepo_path).unwrap();
+
+    let (output, _) = run_update(&repo_sample);
+    assert!(output.contains("    - 'FILE.md'\n"), "Output: {output}");
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::update_abort(&repo_sample.repo(), &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(output.get_ref()),
+        format!(
+            "Aborting update...\n\
+             - 'sub-a': restored 'main' at {}\n\
+             Aborted update\n",
+            &local_before[..8]
+        )
+    );
+
+    assert_eq!(
+        _run("git rev-parse HEAD", subrepo_path).unwrap(),
+        local_before
+    );
+    assert_eq!(
+        _run("git branch --show-current", subrepo_path).unwrap(),
+        "main\n"
+    );
+    assert_eq!(_run("git status --porcelain", subrepo_path).unwrap(), "");
+
+    // The update can be started again
+    let (_, report) = run_update(&repo_sample);
+    assert!(report.is_ok());
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn update_abort_keeps_unchanged_repos(repo_sample: TestRepo) {
+    let sub_b_path = repo_sample.subrepo_paths.get("sub-b").unwrap();
+    fs::write(sub_b_path.join("NOTES.md"), "notes\n").unwrap();
+    _run("git add NOTES.md", sub_b_path).unwrap();
+    _run("git commit -m notes", sub_b_path).unwrap();
+    fs::write(sub_b_path.join("NOTES.md"), "work in progress\n").unwrap();
+
+    let subrepo_path = diverge_from_remote(&repo_sample, "FILE.md", "FILE.md");
+    _run("git config pull.rebase true", subrepo_path).unwrap();
+
+    let (output, _) = run_update(&repo_sample);
+    assert!(output.contains("    - 'FILE.md'\n"), "Output: {output}");
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::update_abort(&repo_sample.repo(), &mut output).unwrap();
+    let output = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output.contains("- 'sub-b': left 'main' as it is\n"),
+        "Output: {output}"
+    );
+    assert!(output.ends_with("Aborted update\n"), "Output: {output}");
+
+    assert_eq!(
+        fs::read_to_string(sub_b_path.join("NOTES.md")).unwrap(),
+        "work in progress\n"
+    );
+    assert_eq!(_run("git status --porcelain", subrepo_path).unwrap(), "");
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn update_exits_with_failure_until_fully_aborted(repo_sample: TestRepo) {
+    // sub-b fast-forwards over uncommitted changes that --autostash re-applies
+    let sub_b_path = repo_sample.subrepo_paths.get("sub-b").unwrap();
+    fs::write(sub_b_path.join("NOTES.md"), "notes\n").unwrap();
+    _run("git add NOTES.md", sub_b_path).unwrap();
+    _run("git commit -m notes", sub_b_path).unwrap();
+    let remote_path = repo_sample.repo_path.join("sub-b.git");
+    _run(
+        &format!("git clone --bare . {}", remote_path.display()),
+        sub_b_path,
+    )
+    .unwrap();
+    _run(
+        &format!("git remote add origin {}", remote_path.display()),
+        sub_b_path,
+    )
+    .unwrap();
+    _run("git fetch origin", sub_b_path).unwrap();
+    _run("git branch -u origin/main", sub_b_path).unwrap();
+    _run("git commit --allow-empty -m upstream", sub_b_path).unwrap();
+    _run("git push origin main", sub_b_path).unwrap();
+    _run("git reset -q --hard HEAD~1", sub_b_path).unwrap();
+    fs::write(sub_b_path.join("NOTES.md"), "work in progress\n").unwrap();
+
+    let subrepo_path = diverge_from_remote(&repo_sample, "FILE.md", "FILE.md");
+    _run("git config pull.rebase true", subrepo_path).unwrap();
+
+    let (output, exit_code) = _wok("update --autostash", &repo_sample.repo_path);
+    assert!(output.contains("    - 'FILE.md'\n"), "Output: {output}");
+    assert_eq!(exit_code, 1);
+
+    let (output, exit_code) = _wok("update --abort", &repo_sample.repo_path);
+    assert!(
+        output.ends_with("Update not fully aborted\n"),
+        "Output: {output}"
+    );
+    assert_eq!(exit_code, 1);
+
+    _run("git stash", sub_b_path).unwrap();
+    let (output, exit_code) = _wok("update --abort", &repo_sample.repo_path);
+    assert!(output.ends_with("Aborted update\n"), "Output: {output}");
+    assert_eq!(exit_code, 0);
+}
+
+#[rstest(repo_sample(vec!["sub-a", "sub-b"], Some("a-b.toml")))]
+fn update_keeps_conflicts_pending_when_another_repo_fails(repo_sample: TestRepo) {
+    let sub_b_path = repo_sample.subrepo_paths.get("sub-b").unwrap();
+    let missing_remote = repo_sample.repo_path.join("missing.git");
+    _run(
+        &format!("git remote add origin {}", missing_remote.display()),
+        sub_b_path,
+    )
+    .unwrap();
+    _run("git config branch.main.remote origin", sub_b_path).unwrap();
+    _run("git config branch.main.merge refs/heads/main", sub_b_path).unwrap();
+
+    let subrepo_path = diverge_from_remote(&repo_sample, "FILE.md", "FILE.md");
+    _run("git config pull.rebase false", subrepo_path).unwrap();
+
+    let (output, report) = run_update(&repo_sample);
+    assert!(report.is_err());
+    assert!(output.contains("    - 'FILE.md'\n"), "Output: {output}");
+
+    let mut output = Cursor::new(Vec::new());
+    cmd::update_abort(&repo_sample.repo(), &mut output).unwrap();
+    let output = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output.contains("- 'sub-a': restored 'main' at "),
+        "Output: {output}"
+    );
+}
+
+/// Tag a commit of sub-a's remote that no branch points to, so that it only
+/// comes with the tags. Returns the tagged commit.
+fn tag_off_branch(repo_sample: &TestRepo, tag: &str) -> String {
+    let contributor_path = repo_sample.repo_path.join("remotes").join("contributor");
+    _run("git checkout -q --detach", &contributor_path).unwrap();
+    fs::write(contributor_path.join("RELEASE.md"), "release\n").unwrap();
+    _run("git add RELEASE.md", &contributor_path).unwrap();
+    _run("git commit -m release", &contributor_path).unwrap();
+    _run(&format!("git tag {tag}"), &contributor_path).unwrap();
+    _run(&format!("git push origin {tag}"), &contributor_path).unwrap();
+    _run("git rev-parse HEAD", &contributor_path).unwrap()
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn update_pins_repo_to_configured_tag(repo_sample: TestRepo) {
+    let subrepo_path = diverge_from_remote(&repo_sample, "LOCAL.md", "UPSTREAM.md");
+    let tagged = tag_off_branch(&repo_sample, "v1.0");
+
+    let mut config_file = config::Config::read(&repo_sample.config_path()).unwrap();
+    config_file.push_str("tag = \"v1.0\"\n");
+    fs::write(repo_sample.config_path(), config_file).unwrap();
+
+    let (output, report) = run_update(&repo_sample);
+    assert!(
+        output.contains(&format!("- 'sub-a': pinned to 'v1.0' ({})\n", &tagged[..8])),
+        "Output: {output}"
+    );
+    assert_eq!(report.unwrap()["repos"][0]["pin"], "v1.0");
+
+    assert_eq!(_run("git rev-parse HEAD", subrepo_path).unwrap(), tagged);
+    assert_eq!(_run("git config wok.pin", subrepo_path).unwrap(), "v1.0\n");
+    let message = _run("git log -1 --format=%B", &repo_sample.repo_path).unwrap();
+    assert!(
+        message.contains(&format!("- sub-a: pinned to v1.0 ({})", &tagged[..8])),
+        "Message: {message}"
+    );
+
+    // The detached repo still loads and stays where it is
+    let (output, _) = run_update(&repo_sample);
+    assert!(
+        output.contains(&format!(
+            "- 'sub-a': already up to date on 'v1.0' ({})\n",
+            &tagged[..8]
+        )),
+        "Output: {output}"
+    );
+}
+
+#[rstest(repo_sample(vec!["sub-a"], Some("a.toml")))]
+fn update_to_rev_pins_given_repos(repo_sample: TestRepo) {
+    let subrepo_path = diverge_from_remote(&repo_sample, "LOCAL.md", "UPSTREAM.md");
+    let tagged = tag_off_branch(&repo_sample, "v1.0");
+    let local_before = _run("git rev-parse main", subrepo_path).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let mut actual_config = config::Config::load(&repo_sample.config_path()).unwrap();
+    cmd::update(
+        &mut actual_config,
+        &repo_sample.repo(),
+        &mut output,
+        &cmd::UpdateOptions {
+            include_umbrella: false,
+            to: Some(&tagged[..12]),
+            repos: &[PathBuf::from("sub-a")],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let output = String::from_utf8_lossy(output.get_ref());
+    assert!(
+        output.contains(&format!("- 'sub-a': pinned to '{}'", &tagged[..12])),
+        "Output: {output}"
+    );
+    assert_eq!(_run("git rev-parse HEAD", subrepo_path).unwrap(), tagged);
+    assert_eq!(
+        _run("git rev-parse main", subrepo_path).unwrap(),
+        local_before
+    );
+
+    // Without a pin the repo goes back to its branch
+    let (output, _) = run_update(&repo_sample);
+    assert!(
+        output.contains("- 'sub-a': merged 'main'"),
+        "Output: {output}"
+    );
+    assert_eq!(
+        _run("git branch --show-current", subrepo_path).unwrap(),
+        "main\n"
+    );
+    assert_eq!(_run("git config wok.pin", subrepo_path).unwrap(), "");
+}